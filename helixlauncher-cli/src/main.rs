@@ -1,17 +1,36 @@
 //! Helix Launcher CLI
 //! This is an example implementation of the Helix Launcher CLI.
 
-use anyhow::Result;
+use std::{
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Result};
 use clap::{Parser, Subcommand, ValueEnum};
 use clap_verbosity_flag::{InfoLevel, Verbosity};
+use helixlauncher_core::archive::{self, ExportOptions, InstanceArchive};
 use helixlauncher_core::auth::account::AccountConfig;
 use helixlauncher_core::auth::{MinecraftAuthenticator, DEFAULT_ACCOUNT_JSON};
 use helixlauncher_core::config::Config;
+use helixlauncher_core::import::{prism::PrismInstance, vanilla::VanillaLauncher, Transfer};
 use helixlauncher_core::launch::{
     asset::merge_components,
-    instance::{Instance, InstanceLaunchConfig, Modloader},
-    prepared::{prepare_launch, LaunchOptions},
+    crash::{diagnose, CrashDiagnosis, CrashFileKind, RuleDatabase},
+    instance::{
+        GroupListing, Instance, InstanceFilter, InstanceGroup, InstanceSort, Modloader,
+        TrashedInstance, MINECRAFT_COMPONENT_ID,
+    },
+    prepared::{prepare_launch, ArgumentPassing, LaunchOptions},
+    process::{ExitKind, GameExit, GameProcess, LifecycleEvent},
+    runtime::RuntimeManager,
+    script::{ScriptKind, ACCESS_TOKEN_VAR},
 };
+use helixlauncher_core::meta::MetaClient;
+use tokio::sync::mpsc;
+
+/// How long the game gets to shut down after Ctrl+C before it is killed
+const GAME_STOP_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[derive(Parser, Debug)]
 struct HelixLauncher {
@@ -27,17 +46,74 @@ enum ClapModloader {
     Fabric,
     Forge,
     Quilt,
+    Vanilla,
+}
+
+impl From<ClapModloader> for Modloader {
+    fn from(modloader: ClapModloader) -> Self {
+        match modloader {
+            ClapModloader::Fabric => Self::Fabric,
+            ClapModloader::Forge => Self::Forge,
+            ClapModloader::Quilt => Self::Quilt,
+            ClapModloader::Vanilla => Self::Vanilla,
+        }
+    }
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+enum ClapInstanceSort {
+    Name,
+    LastPlayed,
+    Playtime,
+    Created,
+}
+
+impl From<ClapInstanceSort> for InstanceSort {
+    fn from(sort: ClapInstanceSort) -> Self {
+        match sort {
+            ClapInstanceSort::Name => Self::Name,
+            ClapInstanceSort::LastPlayed => Self::LastPlayed,
+            ClapInstanceSort::Playtime => Self::Playtime,
+            ClapInstanceSort::Created => Self::Created,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Launches a new instance
     Launch {
-        name: String,
-        #[arg(long)]
+        /// ID of the instance, as shown by `list`
+        id: String,
+        /// Singleplayer world to launch into
+        #[arg(long, conflicts_with_all = ["server", "realm"])]
         world: Option<String>,
+        /// Server to connect to, as host or host:port
+        #[arg(long, conflicts_with = "realm")]
+        server: Option<String>,
+        /// ID of the realm to join
+        #[arg(long)]
+        realm: Option<String>,
+        /// Window width
+        #[arg(long, requires = "height")]
+        width: Option<u32>,
+        /// Window height
+        #[arg(long, requires = "width")]
+        height: Option<u32>,
+        #[arg(long)]
+        fullscreen: bool,
+        /// Launch in demo mode
+        #[arg(long)]
+        demo: bool,
+        /// Username or UUID of the account to use instead of the selected one
+        #[arg(long)]
+        account: Option<String>,
+        /// Print the prepared launch as JSON instead of launching
         #[arg(long, short = 'n')]
         dry_run: bool,
+        /// Write a launch script instead of launching, a batch file if the name ends in .bat
+        #[arg(long, value_name = "PATH", conflicts_with = "dry_run")]
+        export_script: Option<PathBuf>,
     },
 
     /// Creates a new instance
@@ -50,10 +126,161 @@ enum Command {
         modloader: Option<ClapModloader>,
         #[arg(long, requires = "modloader")]
         modloader_version: Option<String>,
+        /// ID of the group to create it in
+        #[arg(long)]
+        group: Option<String>,
     },
 
     /// Lists instances
-    List,
+    List {
+        #[arg(long, value_enum, default_value = "name")]
+        sort: ClapInstanceSort,
+        /// Only list instances with this tag, can be repeated
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Only list instances with this text in the name, ID, notes or tags
+        #[arg(long)]
+        search: Option<String>,
+    },
+
+    /// Changes the icon, notes or tags of an instance
+    Edit {
+        id: String,
+        /// Built-in icon name, or name of an image in the instance directory
+        #[arg(long)]
+        icon: Option<String>,
+        #[arg(long)]
+        notes: Option<String>,
+        /// Tag to add, can be repeated
+        #[arg(long)]
+        add_tag: Vec<String>,
+        /// Tag to remove, can be repeated
+        #[arg(long)]
+        remove_tag: Vec<String>,
+    },
+
+    /// Turns a folder in the instances directory into an instance, replacing a broken config
+    Adopt {
+        /// Path of the folder, relative to the instances directory
+        folder: String,
+        /// Minecraft version of the instance
+        #[arg(long)]
+        version: String,
+    },
+
+    /// Renames an instance
+    Rename {
+        id: String,
+        name: String,
+        /// Also change the ID to match the new name
+        #[arg(long)]
+        move_dir: bool,
+    },
+
+    /// Copies an instance
+    Duplicate {
+        id: String,
+        name: String,
+        /// Link to the worlds of the instance instead of copying them
+        #[arg(long)]
+        share_worlds: bool,
+    },
+
+    /// Moves an instance to the trash
+    Delete { id: String },
+
+    /// Restores the most recently deleted instance with the given ID
+    Restore { id: String },
+
+    /// Creates a group of instances
+    GroupCreate {
+        name: String,
+        /// ID of the group to create it in
+        #[arg(long)]
+        parent: Option<String>,
+    },
+
+    /// Deletes an empty group
+    GroupDelete { id: String },
+
+    /// Moves an instance to a group
+    Move {
+        id: String,
+        /// ID of the group, the instance is moved out of all groups if absent
+        #[arg(long)]
+        group: Option<String>,
+    },
+
+    /// Imports Prism Launcher or MultiMC instances
+    ImportPrism {
+        /// An instance folder, or an instances folder to import all of them
+        path: PathBuf,
+        /// Move the game directory instead of copying it
+        #[arg(long = "move")]
+        move_files: bool,
+        /// ID of the group to import into
+        #[arg(long)]
+        group: Option<String>,
+    },
+
+    /// Exports an instance as a Helix archive to share it
+    Export {
+        id: String,
+        /// The archive to write, e.g. instance.zip
+        path: PathBuf,
+        /// Also export the worlds
+        #[arg(long)]
+        saves: bool,
+        /// Export more files of the game directory, e.g. options.txt. Config, mods and resource
+        /// packs are always exported
+        #[arg(long)]
+        include: Vec<String>,
+        /// Leave out files of the game directory, e.g. '**/*.disabled'
+        #[arg(long)]
+        exclude: Vec<String>,
+    },
+
+    /// Imports an instance from a Helix archive, without its launch commands, environment and JVM
+    /// options
+    Import {
+        path: PathBuf,
+        /// ID of the group to import into
+        #[arg(long)]
+        group: Option<String>,
+    },
+
+    /// Imports the profiles of the vanilla launcher
+    ImportVanilla {
+        /// The data folder of the launcher, .minecraft by default
+        path: Option<PathBuf>,
+        /// ID of the group to import into
+        #[arg(long)]
+        group: Option<String>,
+    },
+
+    /// Lists the components of an instance
+    ComponentList { id: String },
+
+    /// Sets the version of a component, adding it if the instance doesn't have it
+    ComponentSet {
+        id: String,
+        /// ID of the component, e.g. net.minecraft
+        component: String,
+        version: String,
+    },
+
+    /// Removes a component from an instance
+    ComponentRemove { id: String, component: String },
+
+    /// Switches the modloader of an instance
+    SetModloader {
+        id: String,
+        #[arg(value_enum)]
+        modloader: ClapModloader,
+        /// Modloader version, required unless switching to vanilla
+        #[arg(long)]
+        version: Option<String>,
+    },
 
     /// Lists accounts
     AccountList,
@@ -63,6 +290,24 @@ enum Command {
 
     /// Select an account
     AccountSelect,
+
+    /// Lists the Java runtimes managed by the launcher
+    JavaList,
+
+    /// Downloads a Java runtime
+    JavaInstall {
+        /// Major version, e.g. 21
+        version: u32,
+    },
+
+    /// Updates managed Java runtimes to their latest release
+    JavaUpdate {
+        /// Major version to update, all of them if absent
+        version: Option<u32>,
+    },
+
+    /// Removes a managed Java runtime
+    JavaRemove { version: u32 },
 }
 
 #[tokio::main]
@@ -75,22 +320,216 @@ async fn main() -> Result<()> {
 
     match cli.subcommand {
         Command::Launch {
-            name,
+            id,
             world,
+            server,
+            realm,
+            width,
+            height,
+            fullscreen,
+            demo,
+            account,
             dry_run,
+            export_script,
         } => {
-            launch_instance(&config, name, world, dry_run).await?;
+            let options = LaunchArgs {
+                world,
+                server,
+                realm,
+                resolution: width.zip(height),
+                fullscreen,
+                demo,
+                account,
+            };
+            launch_instance(&config, &id, options, dry_run, export_script).await?;
         }
         Command::Create {
             name,
             version,
             modloader,
             modloader_version,
+            group,
+        } => {
+            let group = group
+                .map(|group| InstanceGroup::from_id(config.get_instances_path(), &group))
+                .transpose()?;
+            create_instance(
+                &config,
+                name,
+                version,
+                modloader,
+                modloader_version,
+                group.as_ref(),
+            )
+            .await?;
+        }
+        Command::List { sort, tag, search } => {
+            let filter = InstanceFilter {
+                text: search,
+                tags: tag,
+            };
+            list_instances(&config, sort.into(), &filter).await?;
+        }
+        Command::Edit {
+            id,
+            icon,
+            notes,
+            add_tag,
+            remove_tag,
+        } => {
+            let mut instance = Instance::from_id(config.get_instances_path(), &id)?;
+            let metadata = &mut instance.config.metadata;
+            if icon.is_some() {
+                metadata.icon = icon.filter(|icon| !icon.is_empty());
+            }
+            if notes.is_some() {
+                metadata.notes = notes.filter(|notes| !notes.is_empty());
+            }
+            metadata
+                .tags
+                .retain(|t| !remove_tag.iter().any(|tag| t.eq_ignore_ascii_case(tag)));
+            for tag in add_tag {
+                if !metadata.has_tag(&tag) {
+                    metadata.tags.push(tag);
+                }
+            }
+            instance.save_config()?;
+        }
+        Command::Adopt { folder, version } => {
+            let path = config.get_instances_path().join(&folder);
+            let relative = Path::new(&folder)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if !path.is_dir() || !relative {
+                bail!("No folder named {folder} in the instances directory");
+            }
+            let instance = Instance::adopt(path, version)?;
+            println!(
+                "Instance \"{}\" adopted with ID {}",
+                instance.config.name,
+                instance.id()
+            );
+        }
+        Command::Rename { id, name, move_dir } => {
+            let mut instance = Instance::from_id(config.get_instances_path(), &id)?;
+            instance.rename(name, move_dir)?;
+            println!("Instance renamed, its ID is {}", instance.id());
+        }
+        Command::Duplicate {
+            id,
+            name,
+            share_worlds,
+        } => {
+            let instance = Instance::from_id(config.get_instances_path(), &id)?;
+            let copy = instance.duplicate(name, share_worlds)?;
+            println!("Instance copied with ID {}", copy.id());
+        }
+        Command::Delete { id } => {
+            let instance = Instance::from_id(config.get_instances_path(), &id)?;
+            instance.delete(&config.get_trash_path())?;
+            println!("Instance moved to the trash, undo with `restore {id}`");
+        }
+        Command::Restore { id } => {
+            let trashed = TrashedInstance::list(&config.get_trash_path())?
+                .into_iter()
+                .find(|trashed| trashed.id() == id)
+                .ok_or_else(|| anyhow!("No deleted instance with ID {id}"))?;
+            let instance = trashed.restore()?;
+            println!("Instance restored with ID {}", instance.id());
+        }
+        Command::GroupCreate { name, parent } => {
+            let dir = match parent {
+                Some(parent) => InstanceGroup::from_id(config.get_instances_path(), &parent)?.path,
+                None => config.get_instances_path(),
+            };
+            let group = InstanceGroup::create(&dir, name)?;
+            println!("Group created with ID {}", group.id());
+        }
+        Command::GroupDelete { id } => {
+            InstanceGroup::from_id(config.get_instances_path(), &id)?.delete()?;
+        }
+        Command::Move { id, group } => {
+            let mut instance = Instance::from_id(config.get_instances_path(), &id)?;
+            let group = group
+                .map(|group| InstanceGroup::from_id(config.get_instances_path(), &group))
+                .transpose()?;
+            instance.move_to_group(group.as_ref())?;
+        }
+        Command::ImportPrism {
+            path,
+            move_files,
+            group,
+        } => {
+            import_prism(&config, &path, move_files, group).await?;
+        }
+        Command::Export {
+            id,
+            path,
+            saves,
+            include,
+            exclude,
         } => {
-            create_instance(&config, name, version, modloader, modloader_version).await?;
+            let instance = Instance::from_id(config.get_instances_path(), &id)?;
+            let options = ExportOptions::default()
+                .saves(saves)
+                .include(include)
+                .exclude(exclude);
+            let files = archive::export(&instance, &path, &options)?;
+            println!(
+                "Exported \"{}\" with {files} game files to {}",
+                instance.config.name,
+                path.display()
+            );
         }
-        Command::List => {
-            list_instances(&config).await?;
+        Command::Import { path, group } => {
+            let group = group
+                .map(|group| InstanceGroup::from_id(config.get_instances_path(), &group))
+                .transpose()?;
+            let instance = InstanceArchive::open(&path)?
+                .import(
+                    &MetaClient::new(&config),
+                    &config.get_instances_path(),
+                    group.as_ref(),
+                )
+                .await?;
+            println!(
+                "Instance \"{}\" imported with ID {}",
+                instance.config.name,
+                instance.id()
+            );
+        }
+        Command::ImportVanilla { path, group } => {
+            import_vanilla(&config, path, group).await?;
+        }
+        Command::ComponentList { id } => {
+            let instance = Instance::from_id(config.get_instances_path(), &id)?;
+            for component in &instance.config.components {
+                println!("{} {}", component.id, component.version);
+            }
+        }
+        Command::ComponentSet {
+            id,
+            component,
+            version,
+        } => {
+            let mut instance = Instance::from_id(config.get_instances_path(), &id)?;
+            instance
+                .set_component_version(&MetaClient::new(&config), &component, version)
+                .await?;
+        }
+        Command::ComponentRemove { id, component } => {
+            let mut instance = Instance::from_id(config.get_instances_path(), &id)?;
+            instance.remove_component(&component)?;
+        }
+        Command::SetModloader {
+            id,
+            modloader,
+            version,
+        } => {
+            let mut instance = Instance::from_id(config.get_instances_path(), &id)?;
+            instance
+                .set_modloader(&MetaClient::new(&config), modloader.into(), version)
+                .await?;
         }
         Command::AccountList => {
             get_accounts_cmd(&config).await?;
@@ -101,63 +540,208 @@ async fn main() -> Result<()> {
         Command::AccountSelect => {
             todo!();
         }
+        Command::JavaList => {
+            list_java_runtimes(&config).await?;
+        }
+        Command::JavaInstall { version } => {
+            let runtime = RuntimeManager::new(&config).install(version).await?;
+            println!(
+                "Installed {} to {}",
+                runtime.release,
+                runtime.path.display()
+            );
+        }
+        Command::JavaUpdate { version } => {
+            update_java_runtimes(&config, version).await?;
+        }
+        Command::JavaRemove { version } => {
+            RuntimeManager::new(&config).remove(version).await?;
+        }
     }
 
     Ok(())
 }
 
+struct LaunchArgs {
+    world: Option<String>,
+    server: Option<String>,
+    realm: Option<String>,
+    resolution: Option<(u32, u32)>,
+    fullscreen: bool,
+    demo: bool,
+    account: Option<String>,
+}
+
 async fn launch_instance(
     config: &Config,
-    name: String,
-    world: Option<String>,
+    id: &str,
+    options: LaunchArgs,
     dry_run: bool,
+    export_script: Option<PathBuf>,
 ) -> Result<()> {
-    let instance = Instance::from_path(config.get_instances_path().join(name))?;
+    let instance = Instance::from_id(config.get_instances_path(), id)?;
     let components = merge_components(config, &instance.config.components).await?;
 
     let account_config =
         AccountConfig::new(config.get_base_path().as_path().join(DEFAULT_ACCOUNT_JSON))?;
+    let account = match &options.account {
+        Some(account) => Some(
+            account_config
+                .accounts
+                .iter()
+                .find(|it| it.username == *account || it.uuid == *account)
+                .ok_or_else(|| anyhow!("No account named \"{account}\""))?,
+        ),
+        None => account_config.selected(),
+    };
     let prepared = prepare_launch(
         config,
         &instance,
         &components,
         LaunchOptions::default()
-            .world(world)
-            .account(account_config.selected()),
+            .world(options.world)
+            .server(options.server)
+            .realm(options.realm)
+            .resolution(options.resolution)
+            .fullscreen(options.fullscreen)
+            .demo(options.demo)
+            .account(account),
     )
     .await?;
-    if !dry_run {
-        prepared.launch(true).await?.wait().await?;
+    if prepared.java_version_unknown && !dry_run {
+        println!(
+            "Could not determine the Java version of {}, passing the classpath through a pathing jar",
+            prepared.java_path
+        );
+    }
+    if let Some(path) = export_script {
+        prepared
+            .export_script(&path, ScriptKind::from_path(&path))
+            .await?;
+        println!(
+            "Wrote launch script to {}, set {ACCESS_TOKEN_VAR} to launch online",
+            path.display()
+        );
+    } else if !dry_run {
+        let (process, events) = prepared.supervise(prepared.launch(true).await?);
+        let exit = watch_game(&process, events).await;
+        if let Some(failure) = prepared.javalaunch_failure().await {
+            println!("{failure}");
+        }
+        if let Some(diagnosis) = diagnose(
+            &instance.get_game_dir(),
+            &exit,
+            None,
+            &RuleDatabase::builtin(),
+        )? {
+            print_diagnosis(&diagnosis);
+        }
+        if !matches!(exit.kind, ExitKind::Normal | ExitKind::Killed) {
+            bail!("Game {}", exit.kind);
+        }
     } else {
-        println!("{:?}", prepared);
+        println!("{}", serde_json::to_string_pretty(&prepared)?);
+        if let ArgumentPassing::Argfile(path) = &prepared.argument_passing {
+            println!("\n{path}:\n{}", prepared.argfile_contents());
+        }
     }
     Ok(())
 }
 
+async fn watch_game(
+    process: &GameProcess,
+    mut events: mpsc::UnboundedReceiver<LifecycleEvent>,
+) -> GameExit {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(LifecycleEvent::Started { pid, .. }) => {
+                    if let Some(pid) = pid {
+                        println!("Game started (pid {pid})");
+                    }
+                }
+                Some(LifecycleEvent::Stopping { forced }) => {
+                    println!("{}", if forced { "Killing game" } else { "Stopping game" });
+                }
+                Some(LifecycleEvent::Exited(exit)) => {
+                    print_exit(&exit);
+                    return exit;
+                }
+                None => return process.wait().await,
+            },
+            Ok(()) = tokio::signal::ctrl_c() => process.stop(GAME_STOP_TIMEOUT),
+        }
+    }
+}
+
+fn print_exit(exit: &GameExit) {
+    let mut message = format!("Game {}", exit.kind);
+    if let Some(code) = exit.code {
+        message += &format!(" (exit code {code})");
+    } else if let Some(signal) = exit.signal {
+        message += &format!(" (signal {signal})");
+    }
+    println!("{message} after {}s", exit.duration().as_secs());
+    if let Some(crash_report) = &exit.crash_report {
+        println!("Crash report: {}", crash_report.display());
+    }
+    if let Some(error) = &exit.postlaunch_error {
+        println!("{error}");
+    }
+    if let Some(error) = &exit.playtime_error {
+        println!("Could not record the playtime: {error}");
+    }
+}
+
+fn print_diagnosis(diagnosis: &CrashDiagnosis) {
+    if let Some(file) = &diagnosis.file {
+        if file.kind == CrashFileKind::JvmErrorLog {
+            println!("JVM error log: {}", file.path.display());
+        }
+    }
+    if let Some(description) = diagnosis
+        .report
+        .as_ref()
+        .and_then(|report| report.description.as_ref())
+    {
+        println!("Description: {description}");
+    }
+    if let Some(frame) = diagnosis
+        .jvm_error
+        .as_ref()
+        .and_then(|log| log.problematic_frame.as_ref())
+    {
+        println!("Problematic frame: {frame}");
+    }
+    if diagnosis.issues.is_empty() {
+        println!("No known cause found");
+    }
+    for issue in &diagnosis.issues {
+        println!("Possible cause: {}", issue.message);
+    }
+}
+
 async fn create_instance(
     config: &Config,
     name: Option<String>,
     version: Option<String>,
     modloader: Option<ClapModloader>,
     modloader_version: Option<String>,
+    group: Option<&InstanceGroup>,
 ) -> Result<()> {
     // creation wizard
     let (name, version, modloader, modloader_version) = if let Some(name) = name {
         let version = version.unwrap(); // required in clap
-        let (modloader, modloader_version) = if let Some(modloader) = modloader {
-            let modloader = match modloader {
-                ClapModloader::Fabric => Modloader::Fabric,
-                ClapModloader::Forge => Modloader::Forge,
-                ClapModloader::Quilt => Modloader::Quilt,
-            };
-            let modloader_version = if let Some(modloader_version) = modloader_version {
-                modloader_version
-            } else {
-                todo!("Modloader version required")
-            };
-            (modloader, Some(modloader_version))
-        } else {
-            (Modloader::Vanilla, None)
+        let (modloader, modloader_version) = match modloader.map(Modloader::from) {
+            None | Some(Modloader::Vanilla) => (Modloader::Vanilla, None),
+            Some(modloader) => {
+                let modloader_version = if let Some(modloader_version) = modloader_version {
+                    modloader_version
+                } else {
+                    bail!("A {modloader} version is required");
+                };
+                (modloader, Some(modloader_version))
+            }
         };
         (name, version, modloader, modloader_version)
     } else {
@@ -183,22 +767,224 @@ async fn create_instance(
         (name, version, modloader, modloader_version)
     };
 
-    let instance = Instance::new(
-        name,
-        version,
-        InstanceLaunchConfig::default(),
-        &config.get_instances_path(),
-        modloader,
-        modloader_version,
-    )?;
-    println!("Instance \"{}\" created!", instance.config.name);
+    let mut builder = Instance::builder(name);
+    match (modloader.component_id(), modloader_version) {
+        (Some(id), Some(modloader_version)) => builder = builder.component(id, &modloader_version),
+        (Some(_), None) => bail!("A {modloader} version is required"),
+        (None, _) => {}
+    }
+    let instance = builder
+        .component(MINECRAFT_COMPONENT_ID, &version)
+        .group(group)
+        .build(&MetaClient::new(config), &config.get_instances_path())
+        .await?;
+    println!(
+        "Instance \"{}\" created with ID {}!",
+        instance.config.name,
+        instance.id()
+    );
     Ok(())
 }
-async fn list_instances(config: &Config) -> Result<()> {
-    let instances = Instance::list_instances(config.get_instances_path())?;
+async fn list_instances(
+    config: &Config,
+    sort: InstanceSort,
+    filter: &InstanceFilter,
+) -> Result<()> {
+    let mut listing = Instance::list_instances(config.get_instances_path())?;
+    listing.filter(filter);
+    listing.sort(sort);
+
+    print_instances(&listing.instances, &listing.groups, 0);
 
+    for diagnostic in listing.diagnostics {
+        println!(
+            "Skipped {}: {}",
+            diagnostic.path.display(),
+            diagnostic.error
+        );
+        if diagnostic.adoptable {
+            println!(
+                "  it looks like an instance, fix it with `adopt {} --version <minecraft version>`",
+                diagnostic
+                    .path
+                    .strip_prefix(config.get_instances_path())
+                    .unwrap_or(&diagnostic.path)
+                    .display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn print_instances(instances: &[Instance], groups: &[GroupListing], depth: usize) {
+    let indent = "  ".repeat(depth);
     for i in instances {
-        println!("Instance: {}", i.config.name);
+        println!("{indent}Instance: {} ({})", i.config.name, i.id());
+        let metadata = &i.config.metadata;
+        if !metadata.tags.is_empty() {
+            println!("{indent}  tags: {}", metadata.tags.join(", "));
+        }
+        if metadata.last_played.is_some() {
+            println!("{indent}  {}", metadata.describe_playtime());
+        }
+    }
+    for group in groups {
+        let collapsed = if group.group.config.collapsed {
+            " [collapsed]"
+        } else {
+            ""
+        };
+        println!(
+            "{indent}Group: {} ({}){collapsed}",
+            group.group.config.name,
+            group.group.id()
+        );
+        print_instances(&group.instances, &group.groups, depth + 1);
+    }
+}
+
+async fn import_prism(
+    config: &Config,
+    path: &Path,
+    move_files: bool,
+    group: Option<String>,
+) -> Result<()> {
+    let group = group
+        .map(|group| InstanceGroup::from_id(config.get_instances_path(), &group))
+        .transpose()?;
+    let transfer = if move_files {
+        Transfer::Move
+    } else {
+        Transfer::Copy
+    };
+    let paths = if path.join("instance.cfg").is_file() {
+        vec![path.to_path_buf()]
+    } else {
+        PrismInstance::find(path)?
+    };
+    if paths.is_empty() {
+        bail!("No instances found in {}", path.display());
+    }
+
+    let meta_client = MetaClient::new(config);
+    let total = paths.len();
+    let mut failed = 0;
+    for path in paths {
+        let prism = match PrismInstance::read(&path) {
+            Ok(prism) => prism,
+            Err(e) => {
+                println!("{}: could not be read: {e}", path.display());
+                failed += 1;
+                continue;
+            }
+        };
+        let name = prism.name.clone();
+        for warning in &prism.warnings {
+            println!("{name}: {warning}");
+        }
+        match prism
+            .import(
+                &meta_client,
+                &config.get_instances_path(),
+                group.as_ref(),
+                transfer,
+            )
+            .await
+        {
+            Ok(instance) => println!(
+                "Instance \"{}\" imported with ID {}",
+                instance.config.name,
+                instance.id()
+            ),
+            Err(e) => {
+                println!("{name}: could not be imported: {e}");
+                failed += 1;
+            }
+        }
+    }
+    println!("Imported {} of {total} instances", total - failed);
+    if failed > 0 {
+        bail!("{failed} instances could not be imported");
+    }
+    Ok(())
+}
+
+async fn import_vanilla(
+    config: &Config,
+    path: Option<PathBuf>,
+    group: Option<String>,
+) -> Result<()> {
+    let group = group
+        .map(|group| InstanceGroup::from_id(config.get_instances_path(), &group))
+        .transpose()?;
+    let path = path
+        .or_else(VanillaLauncher::default_dir)
+        .ok_or_else(|| anyhow!("The folder of the launcher could not be found"))?;
+    let launcher = VanillaLauncher::read(path)?;
+    for skipped in &launcher.skipped {
+        println!("Skipped {skipped}");
+    }
+    let reused = launcher.reuse_downloads(config)?;
+    println!("Reused {reused} downloaded files");
+
+    let meta_client = MetaClient::new(config);
+    let mut failed = 0;
+    for profile in launcher.profiles {
+        let name = profile.name.clone();
+        for warning in &profile.warnings {
+            println!("{name}: {warning}");
+        }
+        match profile
+            .import(&meta_client, &config.get_instances_path(), group.as_ref())
+            .await
+        {
+            Ok(instance) => println!(
+                "Instance \"{}\" imported with ID {}",
+                instance.config.name,
+                instance.id()
+            ),
+            Err(e) => {
+                println!("{name}: could not be imported: {e}");
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} profiles could not be imported");
+    }
+    Ok(())
+}
+
+async fn list_java_runtimes(config: &Config) -> Result<()> {
+    for runtime in RuntimeManager::new(config).list().await? {
+        println!(
+            "Java {}: {} ({})",
+            runtime.major_version,
+            runtime.release,
+            runtime.path.display()
+        );
+    }
+
+    Ok(())
+}
+
+async fn update_java_runtimes(config: &Config, version: Option<u32>) -> Result<()> {
+    let manager = RuntimeManager::new(config);
+    let versions = match version {
+        Some(version) => vec![version],
+        None => manager
+            .list()
+            .await?
+            .into_iter()
+            .map(|runtime| runtime.major_version)
+            .collect(),
+    };
+    for version in versions {
+        match manager.update(version).await? {
+            Some(runtime) => println!("Java {version}: updated to {}", runtime.release),
+            None => println!("Java {version}: up to date"),
+        }
     }
 
     Ok(())
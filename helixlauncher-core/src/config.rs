@@ -16,6 +16,7 @@ use std::{
 
 pub const CONFIG_NAME: &str = "config.helix.json";
 const META: &str = "https://meta.helixlauncher.dev/";
+const JAVA_RUNTIME_API: &str = "https://api.adoptium.net/";
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
@@ -30,7 +31,23 @@ pub struct Config {
     libraries_dir: PathBuf,
     #[serde(default = "assets_default")]
     assets_dir: PathBuf,
+    #[serde(default = "java_default")]
+    java_dir: PathBuf,
     meta_url: Option<String>,
+    /// Base URL of the Adoptium-style API managed Java runtimes are downloaded from
+    java_runtime_api: Option<String>,
+    /// Commands the game is run under unless the instance sets its own, outermost first, e.g.
+    /// `[["gamemoderun"], ["mangohud"]]`
+    #[serde(default)]
+    wrapper_commands: Vec<Vec<String>>,
+    /// Launch through helixlauncher-javalaunch if it is available, unless the instance says
+    /// otherwise
+    #[serde(default)]
+    use_javalaunch: bool,
+    /// Extra directories searched for Java installs, each either a Java home or a directory of
+    /// Java homes
+    #[serde(default)]
+    java_search_dirs: Vec<PathBuf>,
 }
 
 fn instances_default() -> PathBuf {
@@ -45,10 +62,18 @@ fn assets_default() -> PathBuf {
     PathBuf::from("assets")
 }
 
+fn java_default() -> PathBuf {
+    PathBuf::from("java")
+}
+
 pub(crate) fn meta_url_default() -> String {
     String::from(META)
 }
 
+pub(crate) fn java_runtime_api_default() -> String {
+    String::from(JAVA_RUNTIME_API)
+}
+
 impl Config {
     /// `appdir` is the rDNS name of your application, also used as the macOS bundle id or the
     /// `.desktop` file name on Linux. It will be used in the location of the data folder on macOS.
@@ -109,7 +134,12 @@ impl Config {
             instances_dir: PathBuf::from("instances"),
             libraries_dir: PathBuf::from("libraries"),
             assets_dir: PathBuf::from("assets"),
+            java_dir: PathBuf::from("java"),
             meta_url: None,
+            java_runtime_api: None,
+            wrapper_commands: Vec::new(),
+            use_javalaunch: false,
+            java_search_dirs: Vec::new(),
         }
     }
 
@@ -129,9 +159,55 @@ impl Config {
         self.base_path.join(&self.assets_dir)
     }
 
+    /// The directory of the Java runtimes managed by the launcher
+    pub fn get_java_path(&self) -> PathBuf {
+        self.base_path.join(&self.java_dir)
+    }
+
+    /// Where deleted instances are kept until they are purged
+    pub fn get_trash_path(&self) -> PathBuf {
+        self.base_path.join("trash")
+    }
+
     pub fn get_meta_url(&self) -> Option<&str> {
         self.meta_url.as_deref()
     }
+
+    pub fn set_meta_url(&mut self, meta_url: Option<String>) {
+        self.meta_url = meta_url;
+    }
+
+    pub fn get_java_runtime_api(&self) -> Option<&str> {
+        self.java_runtime_api.as_deref()
+    }
+
+    pub fn set_java_runtime_api(&mut self, java_runtime_api: Option<String>) {
+        self.java_runtime_api = java_runtime_api;
+    }
+
+    pub fn get_wrapper_commands(&self) -> &[Vec<String>] {
+        &self.wrapper_commands
+    }
+
+    pub fn set_wrapper_commands(&mut self, wrapper_commands: Vec<Vec<String>>) {
+        self.wrapper_commands = wrapper_commands;
+    }
+
+    pub fn get_use_javalaunch(&self) -> bool {
+        self.use_javalaunch
+    }
+
+    pub fn set_use_javalaunch(&mut self, use_javalaunch: bool) {
+        self.use_javalaunch = use_javalaunch;
+    }
+
+    pub fn get_java_search_dirs(&self) -> &[PathBuf] {
+        &self.java_search_dirs
+    }
+
+    pub fn set_java_search_dirs(&mut self, java_search_dirs: Vec<PathBuf>) {
+        self.java_search_dirs = java_search_dirs;
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
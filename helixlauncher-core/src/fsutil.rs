@@ -3,12 +3,60 @@ use std::{
     path::{self, Path},
 };
 
+/// Copy a file, as a reflink if the filesystem supports it.
 pub fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
-    // TODO: investigate reflinks
-    fs::copy(from, to)?;
+    reflink_copy::reflink_or_copy(from, to)?;
     Ok(())
 }
 
+/// Copy the directory `from` to `to` recursively, leaving out the paths `skip` returns true for.
+/// Symlinks are copied as symlinks.
+pub fn copy_dir(from: &Path, to: &Path, skip: &dyn Fn(&Path) -> bool) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if skip(&path) {
+            continue;
+        }
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            symlink(&fs::read_link(&path)?, &target, path.is_dir())?;
+        } else if file_type.is_dir() {
+            copy_dir(&path, &target, skip)?;
+        } else {
+            copy_file(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove the symlink `link` to a directory.
+pub fn remove_dir_symlink(link: &Path) -> io::Result<()> {
+    if cfg!(windows) {
+        fs::remove_dir(link)
+    } else {
+        fs::remove_file(link)
+    }
+}
+
+/// Create a symlink at `link` pointing to `target`. Windows needs to know whether the target is a
+/// directory.
+pub fn symlink(target: &Path, link: &Path, is_dir: bool) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let _ = is_dir;
+        std::os::unix::fs::symlink(target, link)
+    }
+    #[cfg(windows)]
+    if is_dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
 const ILLEGAL_FILENAMES: &[&str] = &[
     "aux", "com0", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "con",
     "lpt0", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9", "nul", "prn",
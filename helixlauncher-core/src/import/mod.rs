@@ -0,0 +1,141 @@
+//! Import of instances made by other launchers.
+
+pub mod prism;
+pub mod vanilla;
+
+use std::{fmt::Display, fs, io, path::Path};
+
+use thiserror::Error;
+
+use crate::{
+    fsutil::copy_dir,
+    launch::instance::{Instance, InstanceManagerError},
+};
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+
+    #[error(transparent)]
+    ParseError(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    InstanceError(#[from] InstanceManagerError),
+
+    #[error("{0} is not an instance folder")]
+    NotAnInstance(String),
+
+    #[error("{0} has no launcher profiles")]
+    NoProfiles(String),
+
+    #[error("Unsupported instance: {0}")]
+    Unsupported(String),
+}
+
+/// A setting or component that could not be carried over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportWarning {
+    /// The name of the setting or component in the other launcher
+    pub setting: String,
+    pub message: String,
+}
+
+impl ImportWarning {
+    fn new(setting: &str, message: impl Into<String>) -> Self {
+        Self {
+            setting: setting.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ImportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.setting, self.message)
+    }
+}
+
+/// How the game directory is brought over
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Transfer {
+    /// Copy it, leaving the other launcher's instance working
+    Copy,
+    /// Move it, which is faster but leaves the other launcher's instance without its worlds and
+    /// mods
+    Move,
+}
+
+/// Bring the game directory `from` over to the freshly created `instance`.
+fn transfer_game_dir(from: &Path, instance: &Instance, transfer: Transfer) -> io::Result<()> {
+    let to = instance.get_game_dir();
+    if !from.is_dir() {
+        // nothing was played yet
+        return Ok(());
+    }
+    if transfer == Transfer::Move {
+        // the new game directory is still empty
+        fs::remove_dir(&to)?;
+        match fs::rename(from, &to) {
+            Ok(()) => return Ok(()),
+            // copied below instead
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => fs::create_dir(&to)?,
+            Err(e) => {
+                fs::create_dir(&to)?;
+                return Err(e);
+            }
+        }
+    }
+    copy_dir(from, &to, &|_| false)?;
+    if transfer == Transfer::Move {
+        fs::remove_dir_all(from)?;
+    }
+    Ok(())
+}
+
+/// Split a command line into arguments, honoring quotes.
+fn split_args(command: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => current.extend(chars.next()),
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args() {
+        assert_eq!(
+            split_args(r#"-Xss2M  "-Dname=a b" '-Dq="x"' -Dpath="C:\\Java""#),
+            ["-Xss2M", "-Dname=a b", r#"-Dq="x""#, r"-Dpath=C:\Java"]
+        );
+    }
+}
@@ -0,0 +1,632 @@
+//! Import of Prism Launcher and MultiMC instances.
+//!
+//! Their instance folders contain `instance.cfg`, an INI file with the name and settings, and
+//! `mmc-pack.json`, which lists the components with the most basic one first. Settings are only
+//! imported if the instance overrides the global settings of the launcher, e.g. the memory with
+//! `OverrideMemory=true`. The game directory is `.minecraft`, or `minecraft` in older versions.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    fsutil::copy_file,
+    launch::{
+        hooks::{ENV_GAME_DIR, ENV_INSTANCE_DIR, ENV_INSTANCE_NAME},
+        instance::{
+            Component, Instance, InstanceBuilder, InstanceGroup, InstanceLaunchConfig,
+            InstanceMetadata, RamAllocation,
+        },
+    },
+    meta::MetaClient,
+};
+
+use super::{split_args, transfer_game_dir, ImportError, ImportWarning, Transfer};
+
+const CONFIG_NAME: &str = "instance.cfg";
+const PACK_NAME: &str = "mmc-pack.json";
+
+/// Memory settings of Prism Launcher, used if only one of them is set
+const DEFAULT_MIN_MEMORY: u32 = 512;
+const DEFAULT_MAX_MEMORY: u32 = 4096;
+
+/// The variables available to commands in Prism Launcher, and their Helix equivalents
+const COMMAND_VARIABLES: [(&str, &str); 3] = [
+    ("INST_NAME", ENV_INSTANCE_NAME),
+    ("INST_DIR", ENV_INSTANCE_DIR),
+    ("INST_MC_DIR", ENV_GAME_DIR),
+];
+
+#[derive(Deserialize, Debug)]
+struct PackFile {
+    components: Vec<PackComponent>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PackComponent {
+    uid: String,
+    version: Option<String>,
+    /// Set for components that were only added as a dependency of another one
+    #[serde(default)]
+    dependency_only: bool,
+}
+
+/// An instance of Prism Launcher or MultiMC, converted to Helix settings
+#[derive(Debug)]
+pub struct PrismInstance {
+    /// The instance folder
+    pub path: PathBuf,
+    pub name: String,
+    /// In Helix order, the one that takes precedence first
+    pub components: Vec<Component>,
+    pub launch: InstanceLaunchConfig,
+    pub metadata: InstanceMetadata,
+    /// Settings and components that could not be carried over
+    pub warnings: Vec<ImportWarning>,
+    /// A custom icon from the icons folder of the launcher
+    icon_file: Option<PathBuf>,
+}
+
+impl PrismInstance {
+    /// Read the instance folder at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, ImportError> {
+        let path = path.as_ref().to_path_buf();
+        if !is_instance(&path) {
+            return Err(ImportError::NotAnInstance(path.display().to_string()));
+        }
+        let config = parse_ini(&fs::read_to_string(path.join(CONFIG_NAME))?);
+        let pack: PackFile = serde_json::from_slice(&fs::read(path.join(PACK_NAME))?)?;
+
+        let instance_type = config.get("InstanceType").map_or("OneSix", |t| &t[..]);
+        if instance_type != "OneSix" {
+            return Err(ImportError::Unsupported(format!(
+                "{instance_type} instances can't be imported, open them in Prism Launcher once to convert them"
+            )));
+        }
+
+        let mut warnings = vec![];
+        let components = convert_components(&path, pack.components, &mut warnings);
+        let launch = convert_launch(&config, &mut warnings);
+        let (metadata, icon_file) = convert_metadata(&path, &config);
+        let name = config
+            .get("name")
+            .filter(|name| !name.trim().is_empty())
+            .cloned()
+            .unwrap_or_else(|| path.file_name().unwrap().to_string_lossy().into_owned());
+
+        Ok(Self {
+            path,
+            name,
+            components,
+            launch,
+            metadata,
+            warnings,
+            icon_file,
+        })
+    }
+
+    /// The instances in the instances folder of Prism Launcher or MultiMC.
+    pub fn find(instances_dir: &Path) -> Result<Vec<PathBuf>, ImportError> {
+        let mut instances = vec![];
+        for entry in fs::read_dir(instances_dir)? {
+            let path = entry?.path();
+            if is_instance(&path) {
+                instances.push(path);
+            }
+        }
+        instances.sort();
+        Ok(instances)
+    }
+
+    /// The game directory of the instance.
+    pub fn game_dir(&self) -> PathBuf {
+        let game_dir = self.path.join(".minecraft");
+        if game_dir.is_dir() {
+            return game_dir;
+        }
+        let legacy = self.path.join("minecraft");
+        if legacy.is_dir() {
+            legacy
+        } else {
+            game_dir
+        }
+    }
+
+    /// Make the Helix instance, in `group` if set, after checking the components against meta.
+    pub async fn import(
+        self,
+        meta_client: &MetaClient<'_>,
+        instances_dir: &Path,
+        group: Option<&InstanceGroup>,
+        transfer: Transfer,
+    ) -> Result<Instance, ImportError> {
+        let instance = self
+            .builder()
+            .group(group)
+            .build(meta_client, instances_dir)
+            .await?;
+        self.finish(instance, transfer)
+    }
+
+    fn builder(&self) -> InstanceBuilder {
+        Instance::builder(self.name.clone())
+            .components(self.components.clone())
+            .launch(self.launch.clone())
+            .metadata(self.metadata.clone())
+    }
+
+    /// Bring the files over to the newly created `instance`. If that fails, a copied instance is
+    /// deleted, a moved one is kept as it may hold the worlds and mods by now.
+    fn finish(self, mut instance: Instance, transfer: Transfer) -> Result<Instance, ImportError> {
+        match self.transfer_files(&mut instance, transfer) {
+            Ok(()) => Ok(instance),
+            Err(e) => {
+                if transfer == Transfer::Copy {
+                    let _ = fs::remove_dir_all(&instance.path);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn transfer_files(
+        &self,
+        instance: &mut Instance,
+        transfer: Transfer,
+    ) -> Result<(), ImportError> {
+        transfer_game_dir(&self.game_dir(), instance, transfer)?;
+        if let Some(icon_file) = &self.icon_file {
+            let name = match icon_file.extension() {
+                Some(extension) => format!("icon.{}", extension.to_string_lossy()),
+                None => String::from("icon"),
+            };
+            copy_file(icon_file, &instance.path.join(&name))?;
+            instance.config.metadata.icon = Some(name);
+            instance.save_config()?;
+        }
+        Ok(())
+    }
+}
+
+fn is_instance(path: &Path) -> bool {
+    path.join(CONFIG_NAME).is_file() && path.join(PACK_NAME).is_file()
+}
+
+/// Parse an INI file written by Qt. Sections are ignored, all settings are in `[General]`.
+fn parse_ini(ini: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for line in ini.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['[', ';', '#']) {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            values.insert(key.trim().to_string(), unquote(value.trim()));
+        }
+    }
+    values
+}
+
+/// Remove the quotes Qt puts around values with special characters, and resolve their escapes.
+fn unquote(value: &str) -> String {
+    let Some(value) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    else {
+        return value.to_string();
+    };
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+fn is_true(config: &HashMap<String, String>, key: &str) -> bool {
+    config.get(key).is_some_and(|value| value == "true")
+}
+
+/// Whether the settings of `group` are overridden. MultiMC used one group for all Java settings.
+fn overrides(config: &HashMap<String, String>, group: &str) -> bool {
+    is_true(config, group)
+        || (matches!(group, "OverrideJavaLocation" | "OverrideJavaArgs")
+            && is_true(config, "OverrideJava"))
+}
+
+/// The Helix component for the Prism Launcher component `uid`
+fn component_id(uid: &str) -> Option<&'static str> {
+    Some(match uid {
+        "net.minecraft" => "net.minecraft",
+        "org.lwjgl" => "org.lwjgl",
+        "org.lwjgl3" => "org.lwjgl3",
+        "net.fabricmc.fabric-loader" => "net.fabricmc.fabric-loader",
+        "net.fabricmc.intermediary" => "net.fabricmc.intermediary",
+        "org.quiltmc.quilt-loader" => "org.quiltmc.quilt-loader",
+        "org.quiltmc.hashed" => "org.quiltmc.hashed",
+        "net.minecraftforge" => "net.minecraftforge.forge",
+        _ => return None,
+    })
+}
+
+fn convert_components(
+    path: &Path,
+    pack: Vec<PackComponent>,
+    warnings: &mut Vec<ImportWarning>,
+) -> Vec<Component> {
+    let mut components = vec![];
+    // later components build on the earlier ones, in Helix the first one takes precedence
+    for component in pack.into_iter().rev() {
+        if path
+            .join("patches")
+            .join(format!("{}.json", component.uid))
+            .is_file()
+        {
+            warnings.push(ImportWarning::new(
+                &component.uid,
+                "was customized, the unmodified version is used instead",
+            ));
+        }
+        // dependencies are resolved by meta
+        if component.dependency_only {
+            continue;
+        }
+        let Some(id) = component_id(&component.uid) else {
+            warnings.push(ImportWarning::new(
+                &component.uid,
+                "has no Helix equivalent and was left out",
+            ));
+            continue;
+        };
+        let Some(version) = component.version else {
+            warnings.push(ImportWarning::new(
+                &component.uid,
+                "has no version and was left out",
+            ));
+            continue;
+        };
+        components.push(Component {
+            id: id.to_string(),
+            version,
+        });
+    }
+    components
+}
+
+/// Point the Prism Launcher variables in `command` at the Helix ones.
+fn convert_command(setting: &str, command: &str, warnings: &mut Vec<ImportWarning>) -> String {
+    let mut command = command.to_string();
+    for (prism, helix) in COMMAND_VARIABLES {
+        for (from, to) in [
+            (format!("${{{prism}}}"), format!("${{{helix}}}")),
+            (format!("${prism}"), format!("${helix}")),
+            (format!("%{prism}%"), format!("%{helix}%")),
+        ] {
+            command = command.replace(&from, &to);
+        }
+    }
+    for variable in ["INST_ID", "INST_JAVA"] {
+        if command.contains(variable) {
+            warnings.push(ImportWarning::new(
+                setting,
+                format!("uses {variable}, which has no Helix equivalent"),
+            ));
+        }
+    }
+    command
+}
+
+fn convert_launch(
+    config: &HashMap<String, String>,
+    warnings: &mut Vec<ImportWarning>,
+) -> InstanceLaunchConfig {
+    let non_empty = |key: &str| config.get(key).filter(|value| !value.trim().is_empty());
+    let mut launch = InstanceLaunchConfig::default();
+
+    if overrides(config, "OverrideJavaLocation") {
+        launch.javapath = non_empty("JavaPath").cloned();
+    }
+    if overrides(config, "OverrideJavaArgs") {
+        launch.jvm_args = non_empty("JvmArgs").map(|args| split_args(args));
+    }
+    if overrides(config, "OverrideMemory") {
+        let memory = |key, default| {
+            config
+                .get(key)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        launch.allocation = Some(RamAllocation {
+            min: memory("MinMemAlloc", DEFAULT_MIN_MEMORY),
+            max: memory("MaxMemAlloc", DEFAULT_MAX_MEMORY),
+        });
+    }
+
+    let mut wrapper_commands = vec![];
+    if overrides(config, "OverrideCommands") {
+        launch.prelaunch_command = non_empty("PreLaunchCommand")
+            .map(|command| convert_command("PreLaunchCommand", command, warnings));
+        launch.postlaunch_command = non_empty("PostExitCommand")
+            .map(|command| convert_command("PostExitCommand", command, warnings));
+        if let Some(wrapper) = non_empty("WrapperCommand") {
+            wrapper_commands.push(split_args(wrapper));
+        }
+    }
+    if overrides(config, "OverridePerformance") {
+        if is_true(config, "EnableFeralGamemode") {
+            wrapper_commands.push(vec![String::from("gamemoderun")]);
+        }
+        if is_true(config, "EnableMangoHud") {
+            wrapper_commands.push(vec![String::from("mangohud")]);
+        }
+        if is_true(config, "UseDiscreteGpu") {
+            warnings.push(ImportWarning::new(
+                "UseDiscreteGpu",
+                "is not supported, set the environment variables of your driver instead",
+            ));
+        }
+    }
+    if !wrapper_commands.is_empty() {
+        launch.wrapper_commands = Some(wrapper_commands);
+    }
+
+    if overrides(config, "OverrideEnv") {
+        match non_empty("Env").map(|env| serde_json::from_str::<BTreeMap<String, String>>(env)) {
+            Some(Ok(env)) => launch.env = Some(env),
+            Some(Err(_)) => warnings.push(ImportWarning::new("Env", "could not be read")),
+            None => {}
+        }
+    }
+    if overrides(config, "OverrideWindow") {
+        warnings.push(ImportWarning::new(
+            "OverrideWindow",
+            "window size and maximizing are set when launching",
+        ));
+    }
+    if overrides(config, "OverrideNativeWorkaround")
+        && (is_true(config, "UseNativeOpenAL") || is_true(config, "UseNativeGLFW"))
+    {
+        warnings.push(ImportWarning::new(
+            "OverrideNativeWorkaround",
+            "system OpenAL and GLFW are not supported",
+        ));
+    }
+    if is_true(config, "JoinServerOnLaunch") {
+        warnings.push(ImportWarning::new(
+            "JoinServerOnLaunch",
+            "the server to join is chosen when launching",
+        ));
+    }
+    if is_true(config, "ManagedPack") {
+        let pack = config.get("ManagedPackName").map_or("", |name| &name[..]);
+        let platform = config.get("ManagedPackType").map_or("", |name| &name[..]);
+        warnings.push(ImportWarning::new(
+            "ManagedPack",
+            format!("the instance is no longer updated from the {platform} pack {pack}"),
+        ));
+    }
+    launch
+}
+
+/// Convert the name-independent metadata. Also returns the custom icon, if there is one.
+fn convert_metadata(
+    path: &Path,
+    config: &HashMap<String, String>,
+) -> (InstanceMetadata, Option<PathBuf>) {
+    let number = |key: &str| config.get(key).and_then(|value| value.parse::<u64>().ok());
+    let mut metadata = InstanceMetadata {
+        notes: config
+            .get("notes")
+            .filter(|notes| !notes.is_empty())
+            .cloned(),
+        playtime: number("totalTimePlayed").unwrap_or_default(),
+        // in milliseconds
+        last_played: number("lastLaunchTime")
+            .filter(|time| *time > 0)
+            .map(|time| time / 1000),
+        ..Default::default()
+    };
+
+    let mut icon_file = None;
+    if let Some(icon) = config.get("iconKey").filter(|icon| *icon != "default") {
+        // custom icons are in the icons folder next to the instances folder
+        icon_file = path
+            .parent()
+            .and_then(Path::parent)
+            .and_then(|dir| fs::read_dir(dir.join("icons")).ok())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|file| {
+                file.is_file() && file.file_stem().and_then(|stem| stem.to_str()) == Some(icon)
+            });
+        metadata.icon = Some(icon.clone());
+    }
+    (metadata, icon_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsutil::copy_dir;
+
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/prism")
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert_eq!(unquote(r#""say \"hi\"\nbye""#), "say \"hi\"\nbye");
+    }
+
+    #[test]
+    fn read_prism_instance() -> Result<(), Box<dyn std::error::Error>> {
+        let instance = PrismInstance::read(fixtures().join("instances/Fabulous"))?;
+        assert_eq!(instance.name, "Fabulous Pack");
+        let components: Vec<_> = instance
+            .components
+            .iter()
+            .map(|component| (&component.id[..], &component.version[..]))
+            .collect();
+        assert_eq!(
+            components,
+            [
+                ("net.fabricmc.fabric-loader", "0.15.11"),
+                ("net.minecraft", "1.20.1"),
+                ("org.lwjgl3", "3.3.2"),
+            ]
+        );
+
+        let launch = &instance.launch;
+        assert_eq!(
+            launch.javapath.as_deref(),
+            Some("/usr/lib/jvm/java-17/bin/java")
+        );
+        assert_eq!(
+            launch.jvm_args.as_deref(),
+            Some(&[String::from("-XX:+UseG1GC"), String::from("-Dfoo=a b")][..])
+        );
+        let allocation = launch.allocation.as_ref().unwrap();
+        assert_eq!((allocation.min, allocation.max), (1024, 6144));
+        assert_eq!(
+            launch.prelaunch_command.as_deref(),
+            Some("echo \"$HELIX_INSTANCE_NAME\" > \"${HELIX_GAME_DIR}/launched\"")
+        );
+        assert_eq!(
+            launch.wrapper_commands.as_deref(),
+            Some(
+                &[
+                    vec![String::from("prime-run")],
+                    vec![String::from("gamemoderun")]
+                ][..]
+            )
+        );
+
+        assert_eq!(instance.metadata.playtime, 3600);
+        assert_eq!(instance.metadata.last_played, Some(1_700_000_000));
+        assert_eq!(
+            instance.metadata.notes.as_deref(),
+            Some("Our SMP\nSeason 2")
+        );
+        assert_eq!(
+            instance.icon_file,
+            Some(fixtures().join("icons/fabulous.png"))
+        );
+
+        let warnings: Vec<_> = instance.warnings.iter().map(|w| &w.setting[..]).collect();
+        assert_eq!(
+            warnings,
+            [
+                "com.example.custom",
+                "PostExitCommand",
+                "OverrideWindow",
+                "ManagedPack"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_multimc_instance() -> Result<(), Box<dyn std::error::Error>> {
+        let instance = PrismInstance::read(fixtures().join("instances/OldForge"))?;
+        // the name is missing
+        assert_eq!(instance.name, "OldForge");
+        assert_eq!(instance.components[0].id, "net.minecraftforge.forge");
+        assert_eq!(
+            instance.launch.javapath.as_deref(),
+            Some("C:\\Java\\bin\\javaw.exe")
+        );
+        assert_eq!(
+            instance.launch.jvm_args.as_deref(),
+            Some(&[String::from("-Xss4M")][..])
+        );
+        // memory was not overridden
+        assert!(instance.launch.allocation.is_none());
+        assert_eq!(instance.game_dir(), instance.path.join("minecraft"));
+        assert!(instance.warnings.is_empty());
+        assert_eq!(instance.metadata.icon.as_deref(), Some("grass"));
+        assert_eq!(instance.icon_file, None);
+
+        assert_eq!(
+            PrismInstance::find(&fixtures().join("instances"))?,
+            [
+                fixtures().join("instances/Fabulous"),
+                fixtures().join("instances/OldForge")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn import_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let prism_dir = dir.path().join("prism");
+        copy_dir(&fixtures(), &prism_dir, &|_| false)?;
+        let instances_dir = dir.path().join("instances");
+
+        let prism = PrismInstance::read(prism_dir.join("instances/Fabulous"))?;
+        let game_dir = prism.game_dir();
+        let copied = prism.builder().create(&instances_dir)?;
+        let copied = prism.finish(copied, Transfer::Copy)?;
+        assert_eq!(
+            fs::read_to_string(copied.get_game_dir().join("options.txt"))?,
+            fs::read_to_string(game_dir.join("options.txt"))?
+        );
+        assert_eq!(copied.config.metadata.icon.as_deref(), Some("icon.png"));
+        assert!(copied.path.join("icon.png").is_file());
+
+        let prism = PrismInstance::read(prism_dir.join("instances/OldForge"))?;
+        let game_dir = prism.game_dir();
+        let moved = prism.builder().create(&instances_dir)?;
+        let moved = prism.finish(moved, Transfer::Move)?;
+        assert!(moved.get_game_dir().join("options.txt").is_file());
+        assert!(!game_dir.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn failed_imports() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let prism_dir = dir.path().join("prism");
+        copy_dir(&fixtures(), &prism_dir, &|_| false)?;
+        let instances_dir = dir.path().join("instances");
+
+        let prism = PrismInstance::read(prism_dir.join("instances/Fabulous"))?;
+        let copied = prism.builder().create(&instances_dir)?;
+        // in the way of the copy
+        fs::create_dir(copied.get_game_dir().join("options.txt"))?;
+        let path = copied.path.clone();
+        assert!(prism.finish(copied, Transfer::Copy).is_err());
+        assert!(!path.exists());
+
+        let mut prism = PrismInstance::read(prism_dir.join("instances/Fabulous"))?;
+        let game_dir = prism.game_dir();
+        prism.icon_file = Some(prism_dir.join("icons/missing.png"));
+        let moved = prism.builder().create(&instances_dir)?;
+        let path = moved.path.clone();
+        assert!(prism.finish(moved, Transfer::Move).is_err());
+        // the game directory was moved before the icon failed
+        assert!(!game_dir.exists());
+        assert!(Instance::from_path(&path)?
+            .get_game_dir()
+            .join("options.txt")
+            .is_file());
+        Ok(())
+    }
+}
@@ -0,0 +1,729 @@
+//! Import of the profiles of the vanilla launcher.
+//!
+//! The launcher keeps its profiles in `launcher_profiles.json` in its data directory, each
+//! launching a version from `versions/`. Versions installed by modloaders inherit from a
+//! Minecraft version and add the libraries of the modloader, which is how the modloader and its
+//! version are recognized. The launcher stores libraries and assets in the same layout as Helix,
+//! so the files it already downloaded are copied over instead of being downloaded again.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::DateTime;
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    fsutil::{copy_dir, copy_file},
+    launch::instance::{
+        Component, Instance, InstanceBuilder, InstanceGroup, InstanceLaunchConfig,
+        InstanceMetadata, Modloader, RamAllocation,
+    },
+    meta::MetaClient,
+};
+
+use super::{split_args, ImportError, ImportWarning};
+
+const PROFILES_NAME: &str = "launcher_profiles.json";
+
+/// Folders of the launcher in its data directory, which is also the default game directory.
+/// Its files all start with `launcher_`.
+const LAUNCHER_DIRS: [&str; 6] = [
+    "versions",
+    "libraries",
+    "assets",
+    "runtime",
+    "bin",
+    "webcache2",
+];
+
+/// The minimum memory if the Java arguments only set the maximum
+const DEFAULT_MIN_MEMORY: u32 = 512;
+
+#[derive(Deserialize, Debug)]
+struct ProfilesFile {
+    profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Profile {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    last_version_id: Option<String>,
+    game_dir: Option<PathBuf>,
+    java_dir: Option<String>,
+    java_args: Option<String>,
+    icon: Option<String>,
+    created: Option<String>,
+    last_used: Option<String>,
+    resolution: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct VersionFile {
+    inherits_from: Option<String>,
+    /// The Minecraft version of old modloader installs, which don't inherit
+    jar: Option<String>,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    release_time: Option<String>,
+    #[serde(default)]
+    libraries: Vec<Library>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Library {
+    name: String,
+}
+
+/// The data directory of the vanilla launcher, with its profiles converted to Helix settings
+#[derive(Debug)]
+pub struct VanillaLauncher {
+    pub path: PathBuf,
+    pub profiles: Vec<VanillaProfile>,
+    /// Profiles that could not be converted, with the reason
+    pub skipped: Vec<ImportWarning>,
+}
+
+/// A profile of the vanilla launcher, converted to Helix settings
+#[derive(Debug)]
+pub struct VanillaProfile {
+    pub name: String,
+    pub game_dir: PathBuf,
+    /// In Helix order, the one that takes precedence first
+    pub components: Vec<Component>,
+    pub launch: InstanceLaunchConfig,
+    pub metadata: InstanceMetadata,
+    /// Settings and versions that could not be carried over
+    pub warnings: Vec<ImportWarning>,
+    /// Paths in the game directory that are not copied: the files of the launcher and the game
+    /// directories of other profiles
+    skip: Vec<PathBuf>,
+}
+
+impl VanillaLauncher {
+    /// Where the launcher keeps its data by default.
+    pub fn default_dir() -> Option<PathBuf> {
+        if cfg!(windows) {
+            dirs::data_dir().map(|dir| dir.join(".minecraft"))
+        } else if cfg!(target_os = "macos") {
+            dirs::data_dir().map(|dir| dir.join("minecraft"))
+        } else {
+            dirs::home_dir().map(|dir| dir.join(".minecraft"))
+        }
+    }
+
+    /// Read the profiles of the launcher with the data directory at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, ImportError> {
+        let path = path.as_ref().to_path_buf();
+        let profiles_path = path.join(PROFILES_NAME);
+        if !profiles_path.is_file() {
+            return Err(ImportError::NoProfiles(path.display().to_string()));
+        }
+        let file: ProfilesFile = serde_json::from_slice(&fs::read(profiles_path)?)?;
+        let versions = Versions {
+            path: path.join("versions"),
+        };
+
+        let mut profiles = vec![];
+        let mut skipped = vec![];
+        for (id, profile) in file.profiles {
+            let name = profile_name(&id, &profile);
+            match convert_profile(&path, &versions, name.clone(), profile) {
+                Ok(profile) => profiles.push(profile),
+                Err(message) => skipped.push(ImportWarning::new(&name, message)),
+            }
+        }
+
+        // every game directory is copied once, not again as part of another one
+        let game_dirs: Vec<_> = profiles.iter().map(|p| p.game_dir.clone()).collect();
+        for profile in &mut profiles {
+            if profile.game_dir == path {
+                profile.skip.extend(launcher_files(&path)?);
+            }
+            profile.skip.extend(
+                game_dirs
+                    .iter()
+                    .filter(|dir| **dir != profile.game_dir && dir.starts_with(&profile.game_dir))
+                    .cloned(),
+            );
+        }
+
+        Ok(Self {
+            path,
+            profiles,
+            skipped,
+        })
+    }
+
+    /// Copy the libraries and assets the launcher downloaded to the Helix ones, leaving out the
+    /// files Helix already has. Returns the number of files copied.
+    pub fn reuse_downloads(&self, config: &Config) -> io::Result<u64> {
+        let assets = config.get_assets_path();
+        let mut copied = 0;
+        for (from, to) in [
+            (self.path.join("libraries"), config.get_libraries_path()),
+            (self.path.join("assets/indexes"), assets.join("indexes")),
+            (self.path.join("assets/objects"), assets.join("objects")),
+        ] {
+            if from.is_dir() {
+                copied += copy_missing(&from, &to)?;
+            }
+        }
+        Ok(copied)
+    }
+}
+
+impl VanillaProfile {
+    /// Make the Helix instance, in `group` if set, after checking the components against meta.
+    /// The game directory is copied, leaving the profile in the launcher working.
+    pub async fn import(
+        self,
+        meta_client: &MetaClient<'_>,
+        instances_dir: &Path,
+        group: Option<&InstanceGroup>,
+    ) -> Result<Instance, ImportError> {
+        let instance = self
+            .builder()
+            .group(group)
+            .build(meta_client, instances_dir)
+            .await?;
+        self.finish(instance)
+    }
+
+    fn builder(&self) -> InstanceBuilder {
+        Instance::builder(self.name.clone())
+            .components(self.components.clone())
+            .launch(self.launch.clone())
+            .metadata(self.metadata.clone())
+    }
+
+    /// Copy the game directory to the newly created `instance`, deleting it if that fails.
+    fn finish(self, instance: Instance) -> Result<Instance, ImportError> {
+        if self.game_dir.is_dir() {
+            let copied = copy_dir(&self.game_dir, &instance.get_game_dir(), &|path| {
+                self.skip.iter().any(|skip| skip == path)
+            });
+            if let Err(e) = copied {
+                let _ = fs::remove_dir_all(&instance.path);
+                return Err(e.into());
+            }
+        }
+        Ok(instance)
+    }
+}
+
+/// The versions folder of the launcher
+struct Versions {
+    path: PathBuf,
+}
+
+impl Versions {
+    /// Read the version `id`, or `None` if it isn't installed.
+    fn read(&self, id: &str) -> Result<Option<VersionFile>, String> {
+        let error = |e: &dyn std::fmt::Display| format!("version {id} could not be read: {e}");
+        match fs::read(self.path.join(id).join(format!("{id}.json"))) {
+            Ok(json) => serde_json::from_slice(&json)
+                .map(Some)
+                .map_err(|e| error(&e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(error(&e)),
+        }
+    }
+
+    /// The newest installed Minecraft version of `kind`, `release` or `snapshot`.
+    fn latest(&self, kind: &str) -> Option<String> {
+        fs::read_dir(&self.path)
+            .ok()?
+            .flatten()
+            .filter_map(|entry| {
+                let id = entry.file_name().to_string_lossy().into_owned();
+                let version = self.read(&id).ok()??;
+                if version.inherits_from.is_some()
+                    || version.jar.is_some()
+                    || version.kind.as_deref() != Some(kind)
+                {
+                    return None;
+                }
+                let released = DateTime::parse_from_rfc3339(version.release_time.as_deref()?);
+                Some((released.ok()?, id))
+            })
+            .max()
+            .map(|(_, id)| id)
+    }
+}
+
+/// The name of a profile. The profiles for the latest versions are nameless.
+fn profile_name(id: &str, profile: &Profile) -> String {
+    if !profile.name.trim().is_empty() {
+        return profile.name.clone();
+    }
+    match profile.kind.as_deref() {
+        Some("latest-release") => String::from("Latest release"),
+        Some("latest-snapshot") => String::from("Latest snapshot"),
+        _ => id.to_string(),
+    }
+}
+
+/// The files and folders of the launcher in its data directory at `path`.
+fn launcher_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(path)? {
+        let name = entry?.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("launcher_") || LAUNCHER_DIRS.contains(&&name[..]) {
+            files.push(path.join(&*name));
+        }
+    }
+    Ok(files)
+}
+
+/// Copy the files in `from` that `to` doesn't have yet, recursively. Returns how many were
+/// copied.
+fn copy_missing(from: &Path, to: &Path) -> io::Result<u64> {
+    fs::create_dir_all(to)?;
+    let mut copied = 0;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copied += copy_missing(&entry.path(), &target)?;
+        } else if !target.exists() {
+            copy_file(&entry.path(), &target)?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+fn convert_profile(
+    launcher_dir: &Path,
+    versions: &Versions,
+    name: String,
+    profile: Profile,
+) -> Result<VanillaProfile, String> {
+    let mut warnings = vec![];
+    let version = match profile.kind.as_deref() {
+        Some(kind @ ("latest-release" | "latest-snapshot")) => {
+            let kind = &kind["latest-".len()..];
+            versions
+                .latest(kind)
+                .ok_or_else(|| format!("no {kind} is installed to find the latest one"))?
+        }
+        _ => profile
+            .last_version_id
+            .clone()
+            .ok_or_else(|| String::from("has no version"))?,
+    };
+    let components = convert_version(versions, &version, &mut warnings)?;
+    let launch = convert_launch(&profile, &mut warnings);
+    let metadata = convert_metadata(&profile, &mut warnings);
+    // relative game directories are relative to the data directory
+    let game_dir = match profile.game_dir {
+        Some(game_dir) => launcher_dir.join(game_dir),
+        None => launcher_dir.to_path_buf(),
+    };
+
+    Ok(VanillaProfile {
+        name,
+        game_dir,
+        components,
+        launch,
+        metadata,
+        warnings,
+        skip: vec![],
+    })
+}
+
+/// Map the version `id` to Helix components, following the versions it inherits from.
+fn convert_version(
+    versions: &Versions,
+    id: &str,
+    warnings: &mut Vec<ImportWarning>,
+) -> Result<Vec<Component>, String> {
+    let mut libraries = vec![];
+    let mut inherited = vec![];
+    let mut current = id.to_string();
+    let minecraft = loop {
+        if inherited.contains(&current) {
+            return Err(format!("version {id} inherits from itself"));
+        }
+        let Some(version) = versions.read(&current)? else {
+            if current == id {
+                // not installed yet, the launcher downloads it on launch
+                let (minecraft, loader) = parse_version_id(id);
+                return Ok(components(minecraft, loader));
+            }
+            break current;
+        };
+        libraries.extend(version.libraries.into_iter().map(|library| library.name));
+        match version.inherits_from {
+            Some(parent) => inherited.push(std::mem::replace(&mut current, parent)),
+            None => {
+                if version.jar.is_some() {
+                    inherited.push(current.clone());
+                }
+                break version.jar.unwrap_or(current);
+            }
+        }
+    };
+    if inherited.is_empty() {
+        return Ok(components(minecraft, None));
+    }
+
+    let loader = libraries
+        .iter()
+        .find_map(|library| loader_component(library, &minecraft));
+    if loader.is_none() {
+        let modification = libraries
+            .iter()
+            .find_map(|library| unsupported_modification(library))
+            .unwrap_or("this modification");
+        warnings.push(ImportWarning::new(
+            id,
+            format!("{modification} has no Helix component, plain Minecraft {minecraft} is used"),
+        ));
+    }
+    Ok(components(minecraft, loader))
+}
+
+fn components(minecraft: String, loader: Option<Component>) -> Vec<Component> {
+    let mut components: Vec<_> = loader.into_iter().collect();
+    components.push(Component {
+        id: String::from("net.minecraft"),
+        version: minecraft,
+    });
+    components
+}
+
+fn loader(modloader: Modloader, version: &str) -> Component {
+    Component {
+        id: modloader.component_id().unwrap().to_string(),
+        version: version.to_string(),
+    }
+}
+
+/// Get the Minecraft version and modloader from the ID modloader installers give versions.
+fn parse_version_id(id: &str) -> (String, Option<Component>) {
+    for (prefix, modloader) in [
+        ("fabric-loader-", Modloader::Fabric),
+        ("quilt-loader-", Modloader::Quilt),
+    ] {
+        // e.g. fabric-loader-0.15.11-1.20.4
+        if let Some((version, minecraft)) = id
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.split_once('-'))
+        {
+            return (minecraft.to_string(), Some(loader(modloader, version)));
+        }
+    }
+    // e.g. 1.20.1-forge-47.2.0, or 1.12.2-forge1.12.2-14.23.5.2859 for old versions
+    if let Some((minecraft, version)) = id.split_once("-forge") {
+        let version = version.strip_prefix(minecraft).unwrap_or(version);
+        return (
+            minecraft.to_string(),
+            Some(loader(Modloader::Forge, version.trim_start_matches('-'))),
+        );
+    }
+    (id.to_string(), None)
+}
+
+/// The modloader component for a library of a version, given as a Maven coordinate.
+fn loader_component(library: &str, minecraft: &str) -> Option<Component> {
+    let mut parts = library.split(':');
+    let (group, artifact, version) = (parts.next()?, parts.next()?, parts.next()?);
+    Some(match (group, artifact) {
+        ("net.fabricmc", "fabric-loader") => loader(Modloader::Fabric, version),
+        ("org.quiltmc", "quilt-loader") => loader(Modloader::Quilt, version),
+        // the version is prefixed with the Minecraft version
+        ("net.minecraftforge", "forge" | "fmlloader") => loader(
+            Modloader::Forge,
+            version
+                .strip_prefix(minecraft)
+                .map_or(version, |version| version.trim_start_matches('-')),
+        ),
+        _ => return None,
+    })
+}
+
+/// The name of a known modification that has no Helix component, for a library of a version.
+fn unsupported_modification(library: &str) -> Option<&'static str> {
+    let (group, artifact) = library.split_once(':')?;
+    let artifact = artifact.split(':').next()?;
+    match (group, artifact) {
+        ("net.neoforged" | "net.neoforged.fancymodloader", _) => Some("NeoForge"),
+        ("optifine", "OptiFine") => Some("OptiFine"),
+        _ => None,
+    }
+}
+
+fn convert_launch(profile: &Profile, warnings: &mut Vec<ImportWarning>) -> InstanceLaunchConfig {
+    let mut launch = InstanceLaunchConfig {
+        javapath: profile
+            .java_dir
+            .clone()
+            .filter(|path| !path.trim().is_empty()),
+        ..Default::default()
+    };
+
+    if let Some(java_args) = &profile.java_args {
+        let mut jvm_args = vec![];
+        let mut min = None;
+        let mut max = None;
+        for arg in split_args(java_args) {
+            if let Some(size) = arg.strip_prefix("-Xmx").and_then(parse_memory) {
+                max = Some(size);
+            } else if let Some(size) = arg.strip_prefix("-Xms").and_then(parse_memory) {
+                min = Some((size, arg));
+            } else {
+                jvm_args.push(arg);
+            }
+        }
+        match (min, max) {
+            (min, Some(max)) => {
+                launch.allocation = Some(RamAllocation {
+                    min: min.map_or(DEFAULT_MIN_MEMORY.min(max), |(min, _)| min),
+                    max,
+                })
+            }
+            // the maximum is taken from the launcher settings then
+            (Some((_, arg)), None) => jvm_args.push(arg),
+            (None, None) => {}
+        }
+        if !jvm_args.is_empty() {
+            launch.jvm_args = Some(jvm_args);
+        }
+    }
+    if profile.resolution.is_some() {
+        warnings.push(ImportWarning::new(
+            "resolution",
+            "the window size is set when launching",
+        ));
+    }
+    launch
+}
+
+/// Parse a JVM memory size such as `2G` or `512m` to mebibytes.
+fn parse_memory(size: &str) -> Option<u32> {
+    let digits = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(digits);
+    let number: u64 = number.parse().ok()?;
+    let mebibytes = match unit {
+        "" => number >> 20,
+        "k" | "K" => number >> 10,
+        "m" | "M" => number,
+        "g" | "G" => number << 10,
+        "t" | "T" => number << 20,
+        _ => return None,
+    };
+    u32::try_from(mebibytes).ok()
+}
+
+fn convert_metadata(profile: &Profile, warnings: &mut Vec<ImportWarning>) -> InstanceMetadata {
+    // the launcher uses the epoch for profiles that were never used
+    let time = |time: &Option<String>| {
+        DateTime::parse_from_rfc3339(time.as_deref()?)
+            .ok()
+            .and_then(|time| u64::try_from(time.timestamp()).ok())
+            .filter(|time| *time > 0)
+    };
+    let icon = match profile.icon.as_deref() {
+        Some(icon) if icon.starts_with("data:") => {
+            warnings.push(ImportWarning::new("icon", "custom icons are not imported"));
+            None
+        }
+        // the names of the built-in icons, e.g. Crafting_Table
+        icon => icon.map(str::to_lowercase),
+    };
+    InstanceMetadata {
+        icon,
+        created: time(&profile.created),
+        last_played: time(&profile.last_used),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fsutil::copy_dir;
+
+    use super::*;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/vanilla")
+    }
+
+    fn component_versions(profile: &VanillaProfile) -> Vec<(&str, &str)> {
+        profile
+            .components
+            .iter()
+            .map(|component| (&component.id[..], &component.version[..]))
+            .collect()
+    }
+
+    #[test]
+    fn read_profiles() -> Result<(), Box<dyn std::error::Error>> {
+        let launcher = VanillaLauncher::read(fixtures())?;
+        let names: Vec<_> = launcher.profiles.iter().map(|p| &p.name[..]).collect();
+        assert_eq!(
+            names,
+            [
+                "Latest release",
+                "Fabric",
+                "Forge",
+                "OptiFine",
+                "Latest snapshot"
+            ]
+        );
+        assert_eq!(launcher.skipped.len(), 1);
+        assert_eq!(launcher.skipped[0].setting, "Broken");
+
+        let [latest, fabric, forge, optifine, snapshot] = &launcher.profiles[..] else {
+            unreachable!();
+        };
+        assert_eq!(component_versions(latest), [("net.minecraft", "1.20.4")]);
+        assert_eq!(latest.game_dir, fixtures());
+        assert_eq!(latest.metadata.icon.as_deref(), Some("grass"));
+        assert_eq!(latest.metadata.last_played, Some(1_714_557_600));
+        assert_eq!(component_versions(snapshot), [("net.minecraft", "24w14a")]);
+        assert_eq!(snapshot.metadata.icon.as_deref(), Some("crafting_table"));
+
+        assert_eq!(
+            component_versions(fabric),
+            [
+                ("net.fabricmc.fabric-loader", "0.15.11"),
+                ("net.minecraft", "1.20.4")
+            ]
+        );
+        assert_eq!(fabric.game_dir, fixtures().join("profiles/fabric"));
+        assert_eq!(
+            fabric.launch.javapath.as_deref(),
+            Some("/usr/lib/jvm/java-21/bin/java")
+        );
+        assert_eq!(
+            fabric.launch.jvm_args.as_deref(),
+            Some(&[String::from("-XX:+UseG1GC"), String::from("-Dname=a b")][..])
+        );
+        let allocation = fabric.launch.allocation.as_ref().unwrap();
+        assert_eq!((allocation.min, allocation.max), (1024, 4096));
+        assert_eq!(fabric.metadata.icon, None);
+        assert_eq!(fabric.metadata.created, Some(1_705_343_400));
+        let warnings: Vec<_> = fabric.warnings.iter().map(|w| &w.setting[..]).collect();
+        assert_eq!(warnings, ["resolution", "icon"]);
+
+        // not installed, recognized by the ID
+        assert_eq!(
+            component_versions(forge),
+            [
+                ("net.minecraftforge.forge", "47.2.0"),
+                ("net.minecraft", "1.20.1")
+            ]
+        );
+        let allocation = forge.launch.allocation.as_ref().unwrap();
+        assert_eq!((allocation.min, allocation.max), (512, 3072));
+        assert_eq!(forge.launch.jvm_args, None);
+        assert_eq!(forge.metadata.last_played, None);
+
+        assert_eq!(component_versions(optifine), [("net.minecraft", "1.20.4")]);
+        assert_eq!(optifine.warnings.len(), 1);
+        assert!(optifine.warnings[0].message.starts_with("OptiFine"));
+        Ok(())
+    }
+
+    #[test]
+    fn version_ids() {
+        let parse = |id| {
+            let (minecraft, loader) = parse_version_id(id);
+            (minecraft, loader.map(|loader| (loader.id, loader.version)))
+        };
+        assert_eq!(parse("1.20.4"), (String::from("1.20.4"), None));
+        assert_eq!(
+            parse("quilt-loader-0.25.0-1.20.4"),
+            (
+                String::from("1.20.4"),
+                Some((
+                    String::from("org.quiltmc.quilt-loader"),
+                    String::from("0.25.0")
+                ))
+            )
+        );
+        assert_eq!(
+            parse("1.12.2-forge1.12.2-14.23.5.2859"),
+            (
+                String::from("1.12.2"),
+                Some((
+                    String::from("net.minecraftforge.forge"),
+                    String::from("14.23.5.2859")
+                ))
+            )
+        );
+        assert_eq!(parse_memory("2G"), Some(2048));
+        assert_eq!(parse_memory("1536m"), Some(1536));
+        assert_eq!(parse_memory("2 G"), None);
+    }
+
+    #[test]
+    fn import_files() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let launcher_dir = dir.path().join(".minecraft");
+        copy_dir(&fixtures(), &launcher_dir, &|_| false)?;
+        let config = Config::new_with_data_dir(
+            "dev.helixlauncher.HelixLauncher",
+            "HelixLauncher",
+            dir.path().join("helix"),
+        )?;
+        let instances_dir = config.get_instances_path();
+
+        let launcher = VanillaLauncher::read(&launcher_dir)?;
+        let latest = launcher.profiles.into_iter().next().unwrap();
+        let instance = latest.builder().create(&instances_dir)?;
+        // in the way of the copy
+        fs::create_dir(instance.get_game_dir().join("options.txt"))?;
+        let path = instance.path.clone();
+        assert!(latest.finish(instance).is_err());
+        assert!(!path.exists());
+
+        let launcher = VanillaLauncher::read(&launcher_dir)?;
+        let latest = launcher.profiles.into_iter().next().unwrap();
+        let game_dir = latest.game_dir.clone();
+        let instance = latest.builder().create(&instances_dir)?;
+        let instance = latest.finish(instance)?;
+        let copied = instance.get_game_dir();
+        assert!(copied.join("options.txt").is_file());
+        assert!(copied.join("saves/World/level.dat").is_file());
+        // the files of the launcher and the game directory of the Fabric profile are left out
+        for left_out in [
+            "versions",
+            "libraries",
+            "launcher_profiles.json",
+            "launcher_log.txt",
+            "profiles/fabric",
+        ] {
+            assert!(game_dir.join(left_out).exists());
+            assert!(!copied.join(left_out).exists(), "{left_out} was copied");
+        }
+
+        let library = "net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar";
+        fs::create_dir_all(config.get_assets_path().join("indexes"))?;
+        fs::write(config.get_assets_path().join("indexes/12.json"), "{}")?;
+        let launcher = VanillaLauncher::read(&launcher_dir)?;
+        assert_eq!(launcher.reuse_downloads(&config)?, 2);
+        assert!(config.get_libraries_path().join(library).is_file());
+        // files Helix has are kept
+        assert_eq!(
+            fs::read_to_string(config.get_assets_path().join("indexes/12.json"))?,
+            "{}"
+        );
+        assert_eq!(launcher.reuse_downloads(&config)?, 0);
+        Ok(())
+    }
+}
@@ -21,6 +21,7 @@ use crate::{config::Config, meta::MetaClient};
 use super::{
     download_file,
     instance::{self, Instance},
+    java::{self, JAVA_COMPONENT_ID},
 };
 
 #[derive(Debug)]
@@ -34,6 +35,8 @@ pub struct MergedComponents {
     pub jarmods: Vec<GradleSpecifier>,
     pub main_class: String,
     pub arguments: Vec<MinecraftArgument>,
+    /// The Java major version the components need, the highest of their requirements
+    pub java_version: Option<u32>,
 }
 
 impl MergedComponents {
@@ -216,6 +219,7 @@ pub async fn merge_components(
     let mut assets = None;
     let mut main_class = None;
     let mut arguments = vec![];
+    let mut java_version = None;
     let meta_client = MetaClient::new(config);
 
     for component in components {
@@ -224,6 +228,12 @@ pub async fn merge_components(
             traits.insert(trait_);
         }
 
+        for requirement in &meta.requires {
+            if requirement.id == JAVA_COMPONENT_ID {
+                java_version = java_version.max(java::parse_requirement(&requirement.version));
+            }
+        }
+
         for native in meta.natives {
             if platform_matches(native.platform) {
                 natives.push(Native {
@@ -289,6 +299,7 @@ pub async fn merge_components(
         jarmods: jarmods.into_values().collect(),
         main_class: main_class.unwrap(),
         arguments,
+        java_version,
     })
 }
 
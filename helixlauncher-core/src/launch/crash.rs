@@ -0,0 +1,855 @@
+//! Crash analysis.
+//!
+//! After the game exited abnormally, [`diagnose`] picks up the newest crash report or JVM error
+//! log (`hs_err_pid*.log`) from the game directory, parses it and runs it through a
+//! [`RuleDatabase`] of known causes.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+
+use super::process::{self, ExitKind, GameExit};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CrashFileKind {
+    /// `crash-reports/crash-*.txt`, written by the game itself
+    CrashReport,
+    /// `hs_err_pid*.log`, written by the JVM when it crashes fatally
+    JvmErrorLog,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CrashFile {
+    pub kind: CrashFileKind,
+    pub path: PathBuf,
+}
+
+/// Find the newest crash report or JVM error log in `game_dir` modified after `since`. Crash
+/// reports are preferred, as the JVM error log is usually a consequence rather than a cause if
+/// both are present.
+pub fn find_crash_file(game_dir: &Path, since: SystemTime) -> io::Result<Option<CrashFile>> {
+    if let Some(path) = process::find_crash_report(game_dir, since)? {
+        return Ok(Some(CrashFile {
+            kind: CrashFileKind::CrashReport,
+            path,
+        }));
+    }
+
+    let entries = match fs::read_dir(game_dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        r => r,
+    }?;
+    let mut newest = None;
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with("hs_err_pid") || !name.ends_with(".log") {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if modified < since {
+            continue;
+        }
+        match &newest {
+            Some((newest_modified, _)) if *newest_modified >= modified => {}
+            _ => newest = Some((modified, entry.path())),
+        }
+    }
+    Ok(newest.map(|(_, path)| CrashFile {
+        kind: CrashFileKind::JvmErrorLog,
+        path,
+    }))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CrashReportMod {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct CrashReport {
+    pub time: Option<String>,
+    pub description: Option<String>,
+    /// The exception and stack trace leading to the crash.
+    pub stack_trace: String,
+    /// The `-- Name --` sections of the detailed walkthrough, in order.
+    pub sections: IndexMap<String, String>,
+    /// Entries of the `System Details` section.
+    pub details: IndexMap<String, String>,
+    pub mods: Vec<CrashReportMod>,
+}
+
+const WALKTHROUGH_HEADER: &str = "A detailed walkthrough of the error";
+const SYSTEM_DETAILS_SECTION: &str = "System Details";
+
+lazy_static! {
+    static ref SECTION_PATTERN: Regex = Regex::new(r"^-- (?P<name>.+) --$").unwrap();
+    static ref FABRIC_MOD_PATTERN: Regex =
+        Regex::new(r"^(?P<id>[a-z0-9_.\-]+): (?P<name>.+?) (?P<version>\S+)$").unwrap();
+}
+
+impl CrashReport {
+    pub fn parse(text: &str) -> Self {
+        let mut report = Self::default();
+        let (head, walkthrough) = match text.find(WALKTHROUGH_HEADER) {
+            Some(index) => text.split_at(index),
+            None => (text, ""),
+        };
+
+        let mut head_lines = head.lines();
+        let mut stack_trace = vec![];
+        for line in &mut head_lines {
+            if let Some(time) = line.strip_prefix("Time: ") {
+                report.time = Some(time.trim().to_string());
+            } else if let Some(description) = line.strip_prefix("Description: ") {
+                report.description = Some(description.trim().to_string());
+                break;
+            }
+        }
+        for line in head_lines {
+            if line.trim().is_empty() {
+                if stack_trace.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            stack_trace.push(line);
+        }
+        report.stack_trace = stack_trace.join("\n");
+
+        let mut current: Option<(String, Vec<&str>)> = None;
+        for line in walkthrough.lines() {
+            if let Some(captures) = SECTION_PATTERN.captures(line.trim_end()) {
+                if let Some((name, lines)) = current.take() {
+                    report.sections.insert(name, lines.join("\n"));
+                }
+                current = Some((captures["name"].to_string(), vec![]));
+            } else if let Some((_, lines)) = &mut current {
+                lines.push(line);
+            }
+        }
+        if let Some((name, lines)) = current {
+            report.sections.insert(name, lines.join("\n"));
+        }
+
+        if let Some(details) = report.sections.get(SYSTEM_DETAILS_SECTION) {
+            report.details = parse_details(details);
+        }
+        report.mods = parse_mods(&report.details);
+
+        report
+    }
+}
+
+/// `Key: value` lines indented by one tab; lines indented further continue the previous value.
+fn parse_details(section: &str) -> IndexMap<String, String> {
+    let mut details = IndexMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in section.lines() {
+        if line.starts_with("\t\t") {
+            if let Some((_, value)) = &mut current {
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.trim().split_once(':') {
+            if let Some((key, value)) = current.take() {
+                details.insert(key, value);
+            }
+            current = Some((key.to_string(), value.trim().to_string()));
+        }
+    }
+    if let Some((key, value)) = current {
+        details.insert(key, value);
+    }
+    details
+}
+
+fn parse_mods(details: &IndexMap<String, String>) -> Vec<CrashReportMod> {
+    let mut mods = vec![];
+    for (key, value) in details {
+        match &**key {
+            "Fabric Mods" => {
+                for line in value.lines() {
+                    if let Some(captures) = FABRIC_MOD_PATTERN.captures(line) {
+                        mods.push(CrashReportMod {
+                            id: captures["id"].to_string(),
+                            name: Some(captures["name"].to_string()),
+                            version: Some(captures["version"].to_string()),
+                        });
+                    }
+                }
+            }
+            // Forge: `file.jar |Mod Name |modid |version |DONE |Manifest: ...`
+            "Mod List" => {
+                for line in value.lines() {
+                    let columns: Vec<_> = line.split('|').map(str::trim).collect();
+                    if columns.len() >= 4 && !columns[2].is_empty() {
+                        mods.push(CrashReportMod {
+                            id: columns[2].to_string(),
+                            name: Some(columns[1].to_string()),
+                            version: Some(columns[3].to_string()),
+                        });
+                    }
+                }
+            }
+            // Quilt: a markdown table of `| Index | Mod | ID | Version | ... |`
+            "Quilt Mods" => {
+                for line in value.lines() {
+                    let columns: Vec<_> = line.split('|').map(str::trim).collect();
+                    if columns.len() >= 5
+                        && columns[1].parse::<u32>().is_ok()
+                        && !columns[3].is_empty()
+                    {
+                        mods.push(CrashReportMod {
+                            id: columns[3].to_string(),
+                            name: Some(columns[2].to_string()),
+                            version: Some(columns[4].to_string()),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    mods
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct JvmErrorLog {
+    /// The `#`-prefixed summary at the top of the log.
+    pub summary: String,
+    pub java_version: Option<String>,
+    pub problematic_frame: Option<String>,
+}
+
+impl JvmErrorLog {
+    pub fn parse(text: &str) -> Self {
+        let mut log = Self::default();
+        let mut lines = text.lines().skip_while(|line| !line.starts_with('#'));
+        let mut summary = vec![];
+        while let Some(line) = lines.next() {
+            let Some(line) = line.strip_prefix('#') else {
+                break;
+            };
+            let line = line.trim();
+            if let Some(version) = line.strip_prefix("JRE version: ") {
+                log.java_version = Some(version.to_string());
+            } else if line == "Problematic frame:" {
+                if let Some(frame) = lines.next().and_then(|line| line.strip_prefix('#')) {
+                    log.problematic_frame = Some(frame.trim().to_string());
+                    summary.push(line);
+                    summary.push(frame.trim());
+                    continue;
+                }
+            }
+            summary.push(line);
+        }
+        log.summary = summary.join("\n").trim().to_string();
+        log
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCategory {
+    JavaVersion,
+    MissingDependency,
+    ModConflict,
+    OutOfMemory,
+    Graphics,
+    Other,
+}
+
+/// A known cause identified by a [`CrashRule`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KnownIssue {
+    pub rule: String,
+    pub category: IssueCategory,
+    pub message: String,
+}
+
+/// What rules are matched against.
+pub struct CrashInput<'a> {
+    pub exit: Option<&'a GameExit>,
+    pub file: Option<&'a CrashFile>,
+    /// Contents of the crash file, if any.
+    pub text: &'a str,
+    pub report: Option<&'a CrashReport>,
+    pub jvm_error: Option<&'a JvmErrorLog>,
+    /// The game log, if it was captured.
+    pub log: Option<&'a str>,
+}
+
+impl CrashInput<'_> {
+    /// All available text, for rules that don't care where a message came from.
+    pub fn texts(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.text).chain(self.log)
+    }
+}
+
+pub trait CrashRule: Send + Sync {
+    fn id(&self) -> &str;
+
+    fn check(&self, input: &CrashInput<'_>) -> Vec<KnownIssue>;
+}
+
+/// A rule matching a regular expression. The message may refer to capture groups using `$1` or
+/// `${name}` syntax. This is the format used for rule files.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PatternRule {
+    pub id: String,
+    pub category: IssueCategory,
+    #[serde(with = "serde_regex")]
+    pub pattern: Regex,
+    pub message: String,
+}
+
+mod serde_regex {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        Regex::new(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+impl CrashRule for PatternRule {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn check(&self, input: &CrashInput<'_>) -> Vec<KnownIssue> {
+        let mut issues: Vec<KnownIssue> = vec![];
+        for text in input.texts() {
+            for captures in self.pattern.captures_iter(text) {
+                let mut message = String::new();
+                captures.expand(&self.message, &mut message);
+                if !issues.iter().any(|issue| issue.message == message) {
+                    issues.push(KnownIssue {
+                        rule: self.id.clone(),
+                        category: self.category,
+                        message,
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+/// `UnsupportedClassVersionError` and loaders refusing to run on the current Java version.
+struct JavaVersionRule;
+
+lazy_static! {
+    static ref CLASS_VERSION_PATTERN: Regex = Regex::new(
+        r"UnsupportedClassVersionError: (?P<class>\S+) .*class file version (?P<required>\d+)\.\d+\), this version of the Java Runtime only recognizes class file versions up to (?P<current>\d+)\.\d+"
+    )
+    .unwrap();
+    static ref LOADER_JAVA_PATTERN: Regex = Regex::new(
+        r"requires (?:version )?(?P<required>\d+) or later of (?:mod )?'?java'?"
+    )
+    .unwrap();
+}
+
+/// Class file versions are offset by 44 from the Java major version since Java 1.1.
+const CLASS_VERSION_OFFSET: u32 = 44;
+
+impl CrashRule for JavaVersionRule {
+    fn id(&self) -> &str {
+        "java_version"
+    }
+
+    fn check(&self, input: &CrashInput<'_>) -> Vec<KnownIssue> {
+        let issue = |message: String| {
+            vec![KnownIssue {
+                rule: self.id().to_string(),
+                category: IssueCategory::JavaVersion,
+                message,
+            }]
+        };
+        for text in input.texts() {
+            if let Some(captures) = CLASS_VERSION_PATTERN.captures(text) {
+                let version = |name: &str| {
+                    captures[name]
+                        .parse::<u32>()
+                        .map_or(0, |version| version.saturating_sub(CLASS_VERSION_OFFSET))
+                };
+                return issue(format!(
+                    "{} requires Java {} or newer, but the game ran on Java {}",
+                    &captures["class"],
+                    version("required"),
+                    version("current")
+                ));
+            }
+            if let Some(captures) = LOADER_JAVA_PATTERN.captures(text) {
+                return issue(format!(
+                    "A mod requires Java {} or newer",
+                    &captures["required"]
+                ));
+            }
+        }
+        vec![]
+    }
+}
+
+/// Fabric, Quilt and Forge reporting mods that depend on something that isn't installed.
+struct MissingDependencyRule;
+
+lazy_static! {
+    static ref FABRIC_MISSING_PATTERN: Regex = Regex::new(
+        r"Mod '(?P<mod>[^']+)' \([^)]+\) \S+ requires .*? of (?:mod )?'(?P<dependency>[^']+)'(?: \((?P<id>[^)]+)\))?, which is missing"
+    )
+    .unwrap();
+    static ref FORGE_MISSING_PATTERN: Regex =
+        Regex::new(r"Mod ID: '(?P<dependency>[^']+)', Requested by: '(?P<mod>[^']+)'").unwrap();
+}
+
+impl CrashRule for MissingDependencyRule {
+    fn id(&self) -> &str {
+        "missing_dependency"
+    }
+
+    fn check(&self, input: &CrashInput<'_>) -> Vec<KnownIssue> {
+        let mut issues: Vec<KnownIssue> = vec![];
+        for text in input.texts() {
+            for captures in FABRIC_MISSING_PATTERN
+                .captures_iter(text)
+                .chain(FORGE_MISSING_PATTERN.captures_iter(text))
+            {
+                let dependency = &captures["dependency"];
+                // reported by JavaVersionRule
+                if dependency.eq_ignore_ascii_case("java") {
+                    continue;
+                }
+                let message = format!(
+                    "{} requires {dependency}, which is missing",
+                    &captures["mod"]
+                );
+                if !issues.iter().any(|issue| issue.message == message) {
+                    issues.push(KnownIssue {
+                        rule: self.id().to_string(),
+                        category: IssueCategory::MissingDependency,
+                        message,
+                    });
+                }
+            }
+        }
+        issues
+    }
+}
+
+/// Flags an exit the supervisor already classified as out of memory, even if no file mentions it.
+struct OutOfMemoryExitRule;
+
+impl CrashRule for OutOfMemoryExitRule {
+    fn id(&self) -> &str {
+        "out_of_memory_exit"
+    }
+
+    fn check(&self, input: &CrashInput<'_>) -> Vec<KnownIssue> {
+        match input.exit {
+            Some(exit) if exit.kind == ExitKind::OutOfMemory => vec![KnownIssue {
+                rule: self.id().to_string(),
+                category: IssueCategory::OutOfMemory,
+                message: String::from(
+                    "The game ran out of memory, try increasing the maximum memory allocation",
+                ),
+            }],
+            _ => vec![],
+        }
+    }
+}
+
+pub struct RuleDatabase {
+    rules: Vec<Box<dyn CrashRule>>,
+}
+
+impl Default for RuleDatabase {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl RuleDatabase {
+    pub fn empty() -> Self {
+        Self { rules: vec![] }
+    }
+
+    /// The rules shipped with Helix.
+    pub fn builtin() -> Self {
+        let mut database = Self::empty();
+        database.add(JavaVersionRule);
+        database.add(MissingDependencyRule);
+        database.add(OutOfMemoryExitRule);
+        database
+            .load_json(include_str!("crash_rules.json").as_bytes())
+            .expect("built-in crash rules are invalid");
+        database
+    }
+
+    pub fn add<R: CrashRule + 'static>(&mut self, rule: R) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Add [`PatternRule`]s from a JSON array.
+    pub fn load_json<R: io::Read>(&mut self, reader: R) -> Result<(), serde_json::Error> {
+        let rules: Vec<PatternRule> = serde_json::from_reader(reader)?;
+        for rule in rules {
+            self.add(rule);
+        }
+        Ok(())
+    }
+
+    pub fn check(&self, input: &CrashInput<'_>) -> Vec<KnownIssue> {
+        let mut issues: Vec<KnownIssue> = vec![];
+        for rule in &self.rules {
+            for issue in rule.check(input) {
+                // rules may overlap, and one out of memory issue is enough
+                let duplicate = issues.iter().any(|existing| {
+                    existing.category == issue.category
+                        && (existing.message == issue.message
+                            || issue.category == IssueCategory::OutOfMemory)
+                });
+                if !duplicate {
+                    issues.push(issue);
+                }
+            }
+        }
+        issues
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CrashDiagnosis {
+    pub file: Option<CrashFile>,
+    pub report: Option<CrashReport>,
+    pub jvm_error: Option<JvmErrorLog>,
+    pub issues: Vec<KnownIssue>,
+}
+
+impl CrashDiagnosis {
+    /// A one-line description, preferring identified causes.
+    pub fn summary(&self) -> Option<String> {
+        if !self.issues.is_empty() {
+            return Some(
+                self.issues
+                    .iter()
+                    .map(|issue| &*issue.message)
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            );
+        }
+        self.report
+            .as_ref()
+            .and_then(|report| report.description.clone())
+            .or_else(|| {
+                self.jvm_error
+                    .as_ref()
+                    .and_then(|log| log.problematic_frame.clone())
+            })
+    }
+}
+
+/// Diagnose an exited game. Returns `None` if the game exited normally, or was stopped and the
+/// JVM didn't write an error log while stopping. `log` is the captured game output, if any.
+pub fn diagnose(
+    game_dir: &Path,
+    exit: &GameExit,
+    log: Option<&str>,
+    rules: &RuleDatabase,
+) -> io::Result<Option<CrashDiagnosis>> {
+    if exit.kind == ExitKind::Normal {
+        return Ok(None);
+    }
+    let file = find_crash_file(game_dir, exit.started_at)?;
+    if exit.kind == ExitKind::Killed
+        && !file
+            .as_ref()
+            .is_some_and(|file| file.kind == CrashFileKind::JvmErrorLog)
+    {
+        return Ok(None);
+    }
+    let text = match &file {
+        Some(file) => String::from_utf8_lossy(&fs::read(&file.path)?).into_owned(),
+        None => String::new(),
+    };
+    Ok(Some(analyze(Some(exit), file, &text, log, rules)))
+}
+
+/// Analyze a crash file's contents. This doesn't require the file to exist on disk, which
+/// makes it usable for reports users send in.
+pub fn analyze(
+    exit: Option<&GameExit>,
+    file: Option<CrashFile>,
+    text: &str,
+    log: Option<&str>,
+    rules: &RuleDatabase,
+) -> CrashDiagnosis {
+    let (report, jvm_error) = match file.as_ref().map(|file| file.kind) {
+        Some(CrashFileKind::CrashReport) => (Some(CrashReport::parse(text)), None),
+        Some(CrashFileKind::JvmErrorLog) => (None, Some(JvmErrorLog::parse(text))),
+        None => (None, None),
+    };
+    let issues = rules.check(&CrashInput {
+        exit,
+        file: file.as_ref(),
+        text,
+        report: report.as_ref(),
+        jvm_error: jvm_error.as_ref(),
+        log,
+    });
+    CrashDiagnosis {
+        file,
+        report,
+        jvm_error,
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FABRIC_CRASH_REPORT: &str = "---- Minecraft Crash Report ----
+// Who set us up the TNT?
+
+Time: 2024-01-02 03:04:05
+Description: Initializing game
+
+java.lang.RuntimeException: Mixin transformation of net.minecraft.class_310 failed
+\tat net.fabricmc.loader.impl.launch.knot.KnotClassDelegate.getPostMixinClassByteArray(KnotClassDelegate.java:427)
+\tat net.fabricmc.loader.impl.launch.knot.KnotClassDelegate.tryLoadClass(KnotClassDelegate.java:323)
+Caused by: org.spongepowered.asm.mixin.transformer.throwables.MixinTransformerError: An unexpected critical error was encountered
+Caused by: org.spongepowered.asm.mixin.injection.throwables.InjectionError: Critical injection failure: @Inject annotation on onInit could not find any targets matching 'init' in net.minecraft.class_310. [PREINJECT Applicator Phase -> coolmod.mixins.json:MinecraftClientMixin from mod coolmod -> Prepare Injections]
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+---------------------------------------------------------------------------------------
+
+-- Head --
+Thread: Render thread
+Stacktrace:
+\tat net.minecraft.client.main.Main.main(Main.java:215)
+
+-- System Details --
+Details:
+\tMinecraft Version: 1.20.1
+\tJava Version: 17.0.9, Eclipse Adoptium
+\tFabric Mods:
+\t\tcoolmod: Cool Mod 1.2.3
+\t\tfabric-api: Fabric API 0.86.1+1.20.1
+\tLaunched Version: Fabric
+";
+
+    fn crash_report_file() -> CrashFile {
+        CrashFile {
+            kind: CrashFileKind::CrashReport,
+            path: PathBuf::from("crash-reports/crash.txt"),
+        }
+    }
+
+    #[test]
+    fn parse_crash_report() {
+        let report = CrashReport::parse(FABRIC_CRASH_REPORT);
+        assert_eq!(report.time.as_deref(), Some("2024-01-02 03:04:05"));
+        assert_eq!(report.description.as_deref(), Some("Initializing game"));
+        assert!(report
+            .stack_trace
+            .starts_with("java.lang.RuntimeException: Mixin transformation"));
+        assert_eq!(report.stack_trace.lines().count(), 5);
+        assert_eq!(
+            report.sections.keys().collect::<Vec<_>>(),
+            ["Head", "System Details"]
+        );
+        assert_eq!(
+            report.details.get("Minecraft Version").map(|s| &**s),
+            Some("1.20.1")
+        );
+        assert_eq!(
+            report.mods,
+            [
+                CrashReportMod {
+                    id: String::from("coolmod"),
+                    name: Some(String::from("Cool Mod")),
+                    version: Some(String::from("1.2.3")),
+                },
+                CrashReportMod {
+                    id: String::from("fabric-api"),
+                    name: Some(String::from("Fabric API")),
+                    version: Some(String::from("0.86.1+1.20.1")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_jvm_error_log() {
+        let log = JvmErrorLog::parse(
+            "#
+# A fatal error has been detected by the Java Runtime Environment:
+#
+#  EXCEPTION_ACCESS_VIOLATION (0xc0000005) at pc=0x00007ffb1c3d1a2b, pid=1234, tid=5678
+#
+# JRE version: OpenJDK Runtime Environment Temurin-17.0.9+9 (17.0.9+9) (build 17.0.9+9)
+# Problematic frame:
+# C  [atio6axx.dll+0x1a2b]
+#
+
+---------------  S U M M A R Y ------------
+",
+        );
+        assert_eq!(
+            log.java_version.as_deref(),
+            Some("OpenJDK Runtime Environment Temurin-17.0.9+9 (17.0.9+9) (build 17.0.9+9)")
+        );
+        assert_eq!(
+            log.problematic_frame.as_deref(),
+            Some("C  [atio6axx.dll+0x1a2b]")
+        );
+        assert!(log.summary.starts_with("A fatal error has been detected"));
+    }
+
+    #[test]
+    fn detect_mixin_failure() {
+        let diagnosis = analyze(
+            None,
+            Some(crash_report_file()),
+            FABRIC_CRASH_REPORT,
+            None,
+            &RuleDatabase::builtin(),
+        );
+        assert_eq!(diagnosis.issues.len(), 1);
+        assert_eq!(diagnosis.issues[0].category, IssueCategory::ModConflict);
+        assert!(diagnosis.issues[0].message.contains("coolmod"));
+    }
+
+    #[test]
+    fn detect_java_version() {
+        let diagnosis = analyze(
+            None,
+            None,
+            "",
+            Some("Exception in thread \"main\" java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 65.0), this version of the Java Runtime only recognizes class file versions up to 52.0"),
+            &RuleDatabase::builtin(),
+        );
+        assert_eq!(
+            diagnosis.issues,
+            [KnownIssue {
+                rule: String::from("java_version"),
+                category: IssueCategory::JavaVersion,
+                message: String::from(
+                    "net/minecraft/client/main/Main requires Java 21 or newer, but the game ran on Java 8"
+                ),
+            }]
+        );
+    }
+
+    #[test]
+    fn detect_missing_dependency() {
+        let diagnosis = analyze(
+            None,
+            None,
+            "",
+            Some("net.fabricmc.loader.impl.FormattedException: Some of your mods are incompatible with the game or each other!
+A potential solution has been determined:
+\t - Install fabric-api, any version.
+Unmet dependency listing:
+\t - Mod 'Sodium Extra' (sodium-extra) 0.4.18 requires any version of mod 'Sodium' (sodium), which is missing!
+\t - Mod 'Cool Mod' (coolmod) 1.0.0 requires version 17 or later of 'java', which is missing!"),
+            &RuleDatabase::builtin(),
+        );
+        let categories: Vec<_> = diagnosis
+            .issues
+            .iter()
+            .map(|issue| issue.category)
+            .collect();
+        assert_eq!(
+            categories,
+            [IssueCategory::JavaVersion, IssueCategory::MissingDependency]
+        );
+        assert_eq!(
+            diagnosis.issues[1].message,
+            "Sodium Extra requires Sodium, which is missing"
+        );
+    }
+
+    #[test]
+    fn detect_out_of_memory_once() {
+        let diagnosis = analyze(
+            None,
+            Some(crash_report_file()),
+            "java.lang.OutOfMemoryError: Java heap space\njava.lang.OutOfMemoryError: Java heap space",
+            None,
+            &RuleDatabase::builtin(),
+        );
+        assert_eq!(diagnosis.issues.len(), 1);
+        assert_eq!(diagnosis.issues[0].category, IssueCategory::OutOfMemory);
+    }
+
+    #[test]
+    fn custom_rules() -> Result<(), serde_json::Error> {
+        let mut rules = RuleDatabase::empty();
+        rules.load_json(
+            r#"[{"id": "test", "category": "other", "pattern": "Broken (\\w+)", "message": "$1 is broken"}]"#
+                .as_bytes(),
+        )?;
+        let diagnosis = analyze(None, None, "Broken thing", None, &rules);
+        assert_eq!(diagnosis.issues[0].message, "thing is broken");
+        Ok(())
+    }
+
+    #[test]
+    fn diagnose_exits() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let now = SystemTime::now();
+        let exit = |kind| GameExit {
+            kind,
+            code: None,
+            signal: None,
+            crash_report: None,
+            started_at: SystemTime::UNIX_EPOCH,
+            exited_at: now,
+            postlaunch_error: None,
+            playtime_error: None,
+        };
+        let rules = RuleDatabase::builtin();
+        let diagnose = |kind| diagnose(dir.path(), &exit(kind), None, &rules);
+
+        assert!(diagnose(ExitKind::Normal)?.is_none());
+        assert!(diagnose(ExitKind::Killed)?.is_none());
+        assert!(diagnose(ExitKind::Crash)?.is_some_and(|diagnosis| diagnosis.file.is_none()));
+
+        fs::write(dir.path().join("hs_err_pid1234.log"), "#")?;
+        for kind in [ExitKind::Crash, ExitKind::Killed] {
+            let diagnosis = diagnose(kind)?.unwrap();
+            assert_eq!(diagnosis.file.unwrap().kind, CrashFileKind::JvmErrorLog);
+            assert!(diagnosis.jvm_error.is_some());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn find_jvm_error_log() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("hs_err_pid1234.log"), "#")?;
+        assert_eq!(
+            find_crash_file(dir.path(), SystemTime::UNIX_EPOCH)?,
+            Some(CrashFile {
+                kind: CrashFileKind::JvmErrorLog,
+                path: dir.path().join("hs_err_pid1234.log"),
+            })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+//! Pre-launch and post-launch commands.
+//!
+//! The commands configured in [`InstanceLaunchConfig`](super::instance::InstanceLaunchConfig) are
+//! run through the system shell (`sh -c` on Unix, `cmd /C` on Windows) in the instance directory,
+//! with the following environment variables set:
+//!
+//! | Variable                   | Value                                          |
+//! |----------------------------|------------------------------------------------|
+//! | `HELIX_INSTANCE_NAME`      | Display name of the instance                   |
+//! | `HELIX_INSTANCE_DIR`       | Instance directory                             |
+//! | `HELIX_GAME_DIR`           | Game directory (`.minecraft`)                  |
+//! | `HELIX_MINECRAFT_VERSION`  | Minecraft version, if the instance has one     |
+//! | `HELIX_ACCOUNT_NAME`       | Name of the account the game is launched with  |
+//! | `HELIX_EXIT_CODE`          | Exit code of the game, post-launch only. Empty if the game was killed by a signal |
+//!
+//! A failing pre-launch command aborts the launch. Both commands are killed if they run longer
+//! than the configured timeout, together with everything they started.
+
+use std::{path::PathBuf, process::Stdio, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+    process::{Child, Command},
+    time,
+};
+
+pub const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+pub const ENV_INSTANCE_NAME: &str = "HELIX_INSTANCE_NAME";
+pub const ENV_INSTANCE_DIR: &str = "HELIX_INSTANCE_DIR";
+pub const ENV_GAME_DIR: &str = "HELIX_GAME_DIR";
+pub const ENV_MINECRAFT_VERSION: &str = "HELIX_MINECRAFT_VERSION";
+pub const ENV_ACCOUNT_NAME: &str = "HELIX_ACCOUNT_NAME";
+pub const ENV_EXIT_CODE: &str = "HELIX_EXIT_CODE";
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("Could not run {hook} command: {source}")]
+    Spawn {
+        hook: &'static str,
+        source: std::io::Error,
+    },
+    #[error("{hook} command exited with {status}")]
+    Failed {
+        hook: &'static str,
+        status: std::process::ExitStatus,
+    },
+    #[error("{hook} command timed out after {}s", timeout.as_secs())]
+    Timeout {
+        hook: &'static str,
+        timeout: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchHooks {
+    pub prelaunch: Option<String>,
+    pub postlaunch: Option<String>,
+    pub timeout: Duration,
+    pub working_directory: PathBuf,
+    /// The documented variables, excluding [`ENV_EXIT_CODE`]
+    pub env: Vec<(String, String)>,
+}
+
+impl LaunchHooks {
+    pub async fn run_prelaunch(&self) -> Result<(), HookError> {
+        match &self.prelaunch {
+            Some(command) => self.run("Pre-launch", command, &[]).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn run_postlaunch(&self, exit_code: Option<i32>) -> Result<(), HookError> {
+        match &self.postlaunch {
+            Some(command) => {
+                let exit_code = exit_code.map(|code| code.to_string()).unwrap_or_default();
+                self.run("Post-launch", command, &[(ENV_EXIT_CODE, &exit_code)])
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    async fn run(
+        &self,
+        hook: &'static str,
+        command: &str,
+        extra_env: &[(&str, &str)],
+    ) -> Result<(), HookError> {
+        let mut command = shell(command);
+        command
+            .current_dir(&self.working_directory)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .envs(extra_env.iter().copied())
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        // so the processes the command starts can be killed with it
+        #[cfg(unix)]
+        command.process_group(0);
+        let mut child = command
+            .spawn()
+            .map_err(|source| HookError::Spawn { hook, source })?;
+
+        match time::timeout(self.timeout, child.wait()).await {
+            Ok(Ok(status)) if status.success() => Ok(()),
+            Ok(Ok(status)) => Err(HookError::Failed { hook, status }),
+            Ok(Err(source)) => Err(HookError::Spawn { hook, source }),
+            Err(_) => {
+                kill_tree(&mut child).await;
+                Err(HookError::Timeout {
+                    hook,
+                    timeout: self.timeout,
+                })
+            }
+        }
+    }
+}
+
+/// Kill the command and the processes it started.
+#[cfg(unix)]
+async fn kill_tree(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill has no memory safety requirements. The command leads its own process
+        // group, which can't have been reused as the command hasn't been reaped yet.
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+/// Kill the command and the processes it started.
+#[cfg(windows)]
+async fn kill_tree(child: &mut Child) {
+    if let Some(pid) = child.id() {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+    }
+    let _ = child.kill().await;
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hooks(dir: &std::path::Path) -> LaunchHooks {
+        LaunchHooks {
+            prelaunch: None,
+            postlaunch: None,
+            timeout: Duration::from_secs(10),
+            working_directory: dir.to_path_buf(),
+            env: vec![(ENV_INSTANCE_NAME.to_string(), String::from("Test"))],
+        }
+    }
+
+    #[tokio::test]
+    async fn hook_environment() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let hooks = LaunchHooks {
+            postlaunch: Some(format!(
+                "echo \"${ENV_INSTANCE_NAME} ${ENV_EXIT_CODE}\" > out.txt"
+            )),
+            ..hooks(dir.path())
+        };
+        hooks.run_postlaunch(Some(1)).await?;
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out.txt"))?,
+            "Test 1\n"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failing_hook() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let hooks = LaunchHooks {
+            prelaunch: Some(String::from("exit 3")),
+            ..hooks(dir.path())
+        };
+        assert!(matches!(
+            hooks.run_prelaunch().await,
+            Err(HookError::Failed { status, .. }) if status.code() == Some(3)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hook_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let hooks = LaunchHooks {
+            prelaunch: Some(String::from("sleep 10")),
+            timeout: Duration::from_millis(100),
+            ..hooks(dir.path())
+        };
+        assert!(matches!(
+            hooks.run_prelaunch().await,
+            Err(HookError::Timeout { .. })
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn hook_timeout_kills_started_processes() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let hooks = LaunchHooks {
+            prelaunch: Some(String::from("(sleep 1; echo late > late.txt) & wait")),
+            timeout: Duration::from_millis(100),
+            ..hooks(dir.path())
+        };
+        assert!(matches!(
+            hooks.run_prelaunch().await,
+            Err(HookError::Timeout { .. })
+        ));
+        time::sleep(Duration::from_millis(1500)).await;
+        assert!(!dir.path().join("late.txt").exists());
+        Ok(())
+    }
+}
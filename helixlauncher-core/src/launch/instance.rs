@@ -1,14 +1,21 @@
 use std::{
+    cmp::{Ordering, Reverse},
+    collections::BTreeMap,
     fmt::Display,
     fs::{self, File},
     io::{self, BufReader},
     path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use serde::{Deserialize, Serialize};
+use tempfile_fast::Sponge;
 use thiserror::Error;
 
-use crate::meta::{ComponentMetaRetrievalError, MetaClient};
+use crate::{
+    fsutil::{check_path, copy_dir, remove_dir_symlink, symlink},
+    meta::{ComponentMetaRetrievalError, MetaClient},
+};
 
 #[derive(Error, Debug)]
 pub enum InstanceManagerError {
@@ -20,6 +27,36 @@ pub enum InstanceManagerError {
 
     #[error("Path given is not an instance")]
     NotAnInstance,
+
+    #[error("Invalid instance ID: {0}")]
+    InvalidId(String),
+
+    #[error("Instance names must not be empty")]
+    EmptyName,
+
+    #[error("Path given is not an instance group")]
+    NotAGroup,
+
+    #[error("Only empty groups can be deleted")]
+    GroupNotEmpty,
+
+    #[error(transparent)]
+    MetaError(#[from] ComponentMetaRetrievalError),
+
+    #[error("Version {version} of component {id} not found")]
+    UnknownComponentVersion { id: String, version: String },
+
+    #[error("The instance has no component {0}")]
+    ComponentNotFound(String),
+
+    #[error("Instances need the Minecraft component")]
+    MinecraftRequired,
+
+    #[error("Component {0} is listed more than once")]
+    DuplicateComponent(String),
+
+    #[error("A {0} version is required")]
+    ModloaderVersionRequired(Modloader),
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
@@ -37,26 +74,129 @@ impl Display for Modloader {
     }
 }
 
+impl Modloader {
+    const ALL: [Self; 4] = [Self::Quilt, Self::Fabric, Self::Forge, Self::Vanilla];
+
+    /// The ID of the component providing the modloader
+    pub fn component_id(self) -> Option<&'static str> {
+        match self {
+            Self::Fabric => Some("net.fabricmc.fabric-loader"),
+            Self::Quilt => Some("org.quiltmc.quilt-loader"),
+            Self::Forge => Some("net.minecraftforge.forge"),
+            Self::Vanilla => None,
+        }
+    }
+
+    /// The modloader provided by the component with the ID `id`
+    pub fn from_component_id(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|modloader| modloader.component_id() == Some(id))
+    }
+}
+
+/// The ID of the Minecraft component, which every instance has
+pub const MINECRAFT_COMPONENT_ID: &str = "net.minecraft";
+
+/// An instance, stored in a directory of the instances directory or of a [group](InstanceGroup)
+/// in it. The name of that directory is the ID of the instance, which stays the same when the
+/// display name changes or the instance is moved to another group.
 #[derive(Debug)]
 pub struct Instance {
     pub path: PathBuf,
     pub config: InstanceConfig,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InstanceConfig {
     pub name: String,
     pub components: Vec<Component>,
     pub launch: InstanceLaunchConfig,
+    #[serde(default)]
+    pub metadata: InstanceMetadata,
+}
+
+/// Information about an instance that doesn't affect how it is launched
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct InstanceMetadata {
+    /// A built-in icon name, or the name of an image file in the instance directory, see
+    /// [`Instance::icon`]
+    pub icon: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds since the Unix epoch
+    pub created: Option<u64>,
+    /// When the game was last started, in seconds since the Unix epoch
+    pub last_played: Option<u64>,
+    /// Total time the game ran, in seconds
+    #[serde(default)]
+    pub playtime: u64,
+}
+
+impl InstanceMetadata {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// The playtime and when the game was last started, e.g. `2h 5m played, last 3 days ago`.
+    pub fn describe_playtime(&self) -> String {
+        self.describe_playtime_at(unix_time(SystemTime::now()))
+    }
+
+    fn describe_playtime_at(&self, now: u64) -> String {
+        let Some(last_played) = self.last_played else {
+            return String::from("Never played");
+        };
+        let minutes = self.playtime / 60;
+        let playtime = if minutes < 60 {
+            format!("{minutes}m")
+        } else {
+            format!("{}h {}m", minutes / 60, minutes % 60)
+        };
+        let last_played = match now.saturating_sub(last_played) {
+            secs if secs < 60 * 60 => String::from("less than an hour ago"),
+            secs => {
+                let (amount, unit) = if secs < 24 * 60 * 60 {
+                    (secs / (60 * 60), "hour")
+                } else {
+                    (secs / (24 * 60 * 60), "day")
+                };
+                format!("{amount} {unit}{} ago", if amount == 1 { "" } else { "s" })
+            }
+        };
+        format!("{playtime} played, last {last_played}")
+    }
+}
+
+/// The icon of an instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceIcon<'a> {
+    /// The name of an icon shipped with the launcher
+    BuiltIn(&'a str),
+    File(PathBuf),
 }
 
-#[derive(Default, Serialize, Deserialize, Debug)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct InstanceLaunchConfig {
     // Options are taken from launcher settings if absent
     pub args: Option<Vec<String>>,
     pub jvm_args: Option<Vec<String>>,
     pub prelaunch_command: Option<String>,
     pub postlaunch_command: Option<String>,
+    /// Seconds the pre- and post-launch commands may run before they are killed
+    pub hook_timeout: Option<u32>,
+    /// Commands the game is run under, outermost first, e.g. `[["gamemoderun"], ["mangohud"]]`
+    pub wrapper_commands: Option<Vec<Vec<String>>>,
+    /// Extra environment variables for the game
+    pub env: Option<BTreeMap<String, String>>,
+    /// Environment variables removed from the game's environment
+    pub env_remove: Option<Vec<String>>,
+    /// Pass the JVM options and classpath through an `@argfile` instead of the command line, or
+    /// the classpath through a pathing jar before Java 9
+    pub argfile: Option<bool>,
+    /// Launch through helixlauncher-javalaunch if it is available
+    pub javalaunch: Option<bool>,
     pub allocation: Option<RamAllocation>,
     pub javapath: Option<String>, // FIXME: this is less than ideal, needs more settings and better
                                   // defaults
@@ -64,13 +204,13 @@ pub struct InstanceLaunchConfig {
 
 type Mebibytes = u32;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RamAllocation {
     pub min: Mebibytes,
     pub max: Mebibytes,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Component {
     pub id: String,
     pub version: String,
@@ -87,11 +227,115 @@ impl Component {
     }
 }
 
-const INSTANCE_CONFIG_NAME: &str = "instance.helix.json";
-const _SUBDIR_CONFIG_NAME: &str = "directory.helix.json";
+pub(crate) const INSTANCE_CONFIG_NAME: &str = "instance.helix.json";
+/// Marks a directory in the instances directory as an [`InstanceGroup`]
+const GROUP_CONFIG_NAME: &str = "directory.helix.json";
+/// Where [`Instance::adopt`] keeps a config it could not read
+const BROKEN_CONFIG_NAME: &str = "instance.helix.json.broken";
+/// Describes a deleted instance, next to the instance directory in its trash entry
+const TRASH_INFO_NAME: &str = "trash.helix.json";
+/// The worlds, shared with [`Instance::duplicate`] if asked to
+const SAVES_DIR: &str = "saves";
+
+/// How long deleted instances can be restored
+pub const TRASH_UNDO_WINDOW: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Used when nothing of the name can be used in an ID
+const FALLBACK_ID: &str = "instance";
+
+/// Turn a display name into an ID, e.g. `My Modpack (1.20)` into `my-modpack-1-20`.
+fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if check_path(slug) {
+        slug.to_string()
+    } else {
+        // empty, or a reserved name like `con`
+        String::from(FALLBACK_ID)
+    }
+}
+
+/// Whether `id` was derived from `slug`, possibly with a numeric suffix.
+fn id_matches(id: &str, slug: &str) -> bool {
+    id == slug
+        || id
+            .strip_prefix(slug)
+            .and_then(|suffix| suffix.strip_prefix('-'))
+            .is_some_and(|suffix| suffix.parse::<u32>().is_ok())
+}
+
+/// The instances directory `dir` is in, which is `dir` itself unless it is a group.
+fn instances_root(mut dir: &Path) -> &Path {
+    while dir.join(GROUP_CONFIG_NAME).is_file() {
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+    }
+    dir
+}
+
+/// Find the entry named `name` in `dir` or the groups in it.
+fn find_entry(dir: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+    let path = dir.join(name);
+    if path.symlink_metadata().is_ok() {
+        return Ok(Some(path));
+    }
+    for group in group_dirs(dir)? {
+        if let Some(path) = find_entry(&group, name)? {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// The groups directly in `dir`. Symlinks are not followed, so they can't form a loop.
+fn group_dirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        r => r,
+    }?;
+    let mut groups = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && entry.path().join(GROUP_CONFIG_NAME).is_file() {
+            groups.push(entry.path());
+        }
+    }
+    Ok(groups)
+}
+
+/// Create a new instance directory in `dir` named after `name`, with a numeric suffix if the
+/// name is taken, e.g. `my-modpack-2`. Names are unique across all groups, so they can be used
+/// as IDs. Returns its path.
+fn create_instance_dir(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let root = instances_root(dir);
+    let slug = slugify(name);
+    let mut id = slug.clone();
+    for suffix in 2.. {
+        if find_entry(root, &id)?.is_none() {
+            let path = dir.join(&id);
+            match fs::create_dir(&path) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                r => return r.map(|()| path),
+            }
+        }
+        id = format!("{slug}-{suffix}");
+    }
+    unreachable!()
+}
 
 impl Instance {
-    /// Make a new instance.
+    /// Make a new instance of Minecraft `mc_version` with `modloader`. Its ID is derived from the
+    /// name, see [`id`](Self::id). The versions are not checked, use [`builder`](Self::builder)
+    /// for that and for other components.
     ///
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -100,7 +344,8 @@ impl Instance {
     /// # let data_tempdir = tempfile::tempdir()?;
     /// # let data_dir = data_tempdir.path();
     /// let instances_dir = data_dir.join("instances");
-    /// let instance = Instance::new(String::from("New instance"), String::from("1.20"), InstanceLaunchConfig::default(), &instances_dir, Modloader::Vanilla, None);
+    /// let instance = Instance::new(String::from("New instance"), String::from("1.20"), InstanceLaunchConfig::default(), &instances_dir, Modloader::Vanilla, None)?;
+    /// assert_eq!(instance.id(), "new-instance");
     /// # Ok(())
     /// # }
     /// ```
@@ -112,54 +357,37 @@ impl Instance {
         modloader: Modloader,
         modloader_version: Option<String>,
     ) -> Result<Self, InstanceManagerError> {
-        // TODO: maybe make this more generic? and let the caller specify the components
-        let modloader_component_id = match modloader {
-            Modloader::Fabric => Some("net.fabricmc.fabric-loader"),
-            Modloader::Quilt => Some("org.quiltmc.quilt-loader"),
-            Modloader::Forge => Some("net.minecraftforge.forge"),
-            Modloader::Vanilla => None,
-        };
-
         let mut components = vec![Component {
-            id: String::from("net.minecraft"),
+            id: String::from(MINECRAFT_COMPONENT_ID),
             version: mc_version,
         }];
 
-        if let Some(modloader_component_id) = modloader_component_id {
+        if let Some(modloader_component_id) = modloader.component_id() {
             components.insert(
                 0,
                 Component {
                     id: String::from(modloader_component_id),
-                    version: modloader_version.unwrap(),
+                    version: modloader_version
+                        .ok_or(InstanceManagerError::ModloaderVersionRequired(modloader))?,
                 },
             );
         }
 
-        // make instance folder & skeleton (try to avoid collisions)
-        let instance_dir = instances_dir.join(&name);
-        if instance_dir.try_exists()? {
-            todo!("Resolve folder collision (1)");
-        }
-
-        // make the .minecraft dir & instance dir in one line
-        fs::create_dir_all(instance_dir.join(".minecraft"))?;
-
-        let instance_json_path = instance_dir.join(INSTANCE_CONFIG_NAME);
-
-        let instance = Self {
-            path: instance_dir,
-            config: InstanceConfig {
-                name,
-                components,
-                launch,
-            },
-        };
-
-        // create instance config
-        let instance_json = File::create(instance_json_path)?;
-        serde_json::to_writer_pretty(instance_json, &instance.config)?;
+        Self::builder(name)
+            .components(components)
+            .launch(launch)
+            .create(instances_dir)
+    }
 
-        Ok(instance)
+    /// Start making a new instance named `name`, with any components.
+    pub fn builder(name: String) -> InstanceBuilder {
+        InstanceBuilder {
+            name,
+            components: vec![],
+            launch: InstanceLaunchConfig::default(),
+            metadata: InstanceMetadata::default(),
+            group: None,
+        }
     }
 
     /// Fetch instance from its path.
@@ -184,18 +412,334 @@ impl Instance {
         })
     }
 
+    /// Fetch the instance with the ID `id` from the instances directory or one of its groups.
+    pub fn from_id<P: AsRef<Path>>(
+        instances_dir: P,
+        id: &str,
+    ) -> Result<Self, InstanceManagerError> {
+        let instances_dir = instances_dir.as_ref();
+        Self::from_path(find_by_id(instances_dir, id)?)
+    }
+
+    /// The ID of the instance, which is the name of its directory.
+    pub fn id(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    }
+
+    /// Write the config. It is replaced atomically, so it can't be left half-written.
+    pub fn save_config(&self) -> Result<(), InstanceManagerError> {
+        let mut instance_json = Sponge::new_for(self.path.join(INSTANCE_CONFIG_NAME))?;
+        serde_json::to_writer_pretty(&mut instance_json, &self.config)?;
+        instance_json.commit()?;
+        Ok(())
+    }
+
+    /// Change the display name. If `move_dir` is set, the instance also gets a new ID derived
+    /// from the new name, unless the current one already fits.
+    pub fn rename(&mut self, name: String, move_dir: bool) -> Result<(), InstanceManagerError> {
+        if name.trim().is_empty() {
+            return Err(InstanceManagerError::EmptyName);
+        }
+        if move_dir && !id_matches(self.id(), &slugify(&name)) {
+            let path = create_instance_dir(self.path.parent().unwrap(), &name)?;
+            // the directory only reserved the ID, Windows can't move a directory over another
+            fs::remove_dir(&path)?;
+            self.move_dir(path)?;
+        }
+        self.config.name = name;
+        self.save_config()
+    }
+
+    /// Move the instance into `group`, or out of all groups if it is `None`. The ID stays the
+    /// same.
+    pub fn move_to_group(
+        &mut self,
+        group: Option<&InstanceGroup>,
+    ) -> Result<(), InstanceManagerError> {
+        let dir = match group {
+            Some(group) => group.path.clone(),
+            None => instances_root(self.path.parent().unwrap()).to_path_buf(),
+        };
+        let path = dir.join(self.id());
+        if path != self.path {
+            self.move_dir(path)?;
+        }
+        Ok(())
+    }
+
+    /// Move the instance directory to `path`, keeping the instances sharing its worlds working.
+    fn move_dir(&mut self, path: PathBuf) -> Result<(), InstanceManagerError> {
+        let links = self.shared_world_links()?;
+        fs::rename(&self.path, &path)?;
+        self.path = path;
+        if !links.is_empty() {
+            relink(&links, &self.get_game_dir().join(SAVES_DIR).canonicalize()?)?;
+        }
+        Ok(())
+    }
+
+    /// Copy the instance to a new one named `name`. If `share_worlds` is set, the copy links to
+    /// the worlds of this instance instead of copying them.
+    pub fn duplicate(
+        &self,
+        name: String,
+        share_worlds: bool,
+    ) -> Result<Self, InstanceManagerError> {
+        if name.trim().is_empty() {
+            return Err(InstanceManagerError::EmptyName);
+        }
+        let saves = self.get_game_dir().join(SAVES_DIR);
+        let path = create_instance_dir(self.path.parent().unwrap(), &name)?;
+        copy_dir(&self.path, &path, &|entry| share_worlds && entry == saves)?;
+        if share_worlds {
+            // link to the actual worlds if they are shared already
+            let saves = match saves.canonicalize() {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    fs::create_dir_all(&saves)?;
+                    saves.canonicalize()
+                }
+                r => r,
+            }?;
+            symlink(&saves, &path.join(".minecraft").join(SAVES_DIR), true)?;
+        }
+
+        let mut instance = Self::from_path(path)?;
+        instance.config.name = name;
+        instance.save_config()?;
+        Ok(instance)
+    }
+
+    /// Move the instance to `trash_dir`, from where it can be restored during the
+    /// [`TRASH_UNDO_WINDOW`]. Entries past it are purged. Worlds shared with other instances are
+    /// handed over to them instead.
+    pub fn delete(self, trash_dir: &Path) -> Result<TrashedInstance, InstanceManagerError> {
+        fs::create_dir_all(trash_dir)?;
+        TrashedInstance::purge_expired(trash_dir)?;
+
+        if let Some((heir, others)) = self.shared_world_links()?.split_first() {
+            remove_dir_symlink(heir)?;
+            fs::rename(self.get_game_dir().join(SAVES_DIR), heir)?;
+            relink(others, &heir.canonicalize()?)?;
+        }
+
+        let deleted_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let entry = trash_dir.join(format!("{}-{}", self.id(), deleted_at.as_millis()));
+        fs::create_dir(&entry)?;
+        let info = TrashInfo {
+            id: self.id().to_string(),
+            name: self.config.name.clone(),
+            original_path: self.path.clone(),
+            instances_dir: instances_root(self.path.parent().unwrap()).to_path_buf(),
+            deleted_at: deleted_at.as_secs(),
+        };
+        let mut info_json = Sponge::new_for(entry.join(TRASH_INFO_NAME))?;
+        serde_json::to_writer_pretty(&mut info_json, &info)?;
+        info_json.commit()?;
+        fs::rename(&self.path, entry.join(&info.id))?;
+        Ok(TrashedInstance { path: entry, info })
+    }
+
+    /// The links of other instances to the worlds of this instance, made by
+    /// [`duplicate`](Self::duplicate).
+    fn shared_world_links(&self) -> io::Result<Vec<PathBuf>> {
+        let saves = self.get_game_dir().join(SAVES_DIR);
+        if saves.is_symlink() || !saves.is_dir() {
+            return Ok(vec![]);
+        }
+        let saves = saves.canonicalize()?;
+        let mut links = vec![];
+        let mut dirs = vec![instances_root(self.path.parent().unwrap()).to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            dirs.extend(group_dirs(&dir)?);
+            for entry in fs::read_dir(dir)? {
+                let link = entry?.path().join(".minecraft").join(SAVES_DIR);
+                if link.is_symlink() && fs::read_link(&link)? == saves {
+                    links.push(link);
+                }
+            }
+        }
+        Ok(links)
+    }
+
+    /// List the instances in `instances_dir`, and the groups in it recursively. Folders that are
+    /// not valid instances are reported in the [diagnostics](InstanceListing::diagnostics)
+    /// instead of failing the listing, other files are skipped.
     pub fn list_instances<P: AsRef<Path>>(
         instances_dir: P,
-    ) -> Result<Vec<Self>, InstanceManagerError> {
-        fs::read_dir(instances_dir)?
-            .map(|i| Self::from_path(i?.path()))
-            .collect()
+    ) -> Result<InstanceListing, InstanceManagerError> {
+        let mut listing = InstanceListing::default();
+        list_dir(
+            instances_dir.as_ref(),
+            &mut listing.instances,
+            &mut listing.groups,
+            &mut listing.diagnostics,
+        )?;
+        Ok(listing)
+    }
+
+    /// Turn the folder at `path` into an instance of Minecraft `mc_version`, see
+    /// [`ListingDiagnostic::adoptable`]. A broken config is kept as
+    /// `instance.helix.json.broken`, and the name in it is reused if it can be read.
+    pub fn adopt<P: AsRef<Path>>(
+        path: P,
+        mc_version: String,
+    ) -> Result<Self, InstanceManagerError> {
+        let path = path.as_ref();
+        if let Ok(instance) = Self::from_path(path) {
+            return Ok(instance);
+        }
+
+        let config_path = path.join(INSTANCE_CONFIG_NAME);
+        let name = match fs::read(&config_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+            Ok(config) => {
+                fs::rename(&config_path, path.join(BROKEN_CONFIG_NAME))?;
+                serde_json::from_slice::<serde_json::Value>(&config)
+                    .ok()
+                    .and_then(|config| Some(config.get("name")?.as_str()?.to_string()))
+            }
+        };
+        let name = name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| path.file_name().unwrap().to_string_lossy().into_owned());
+
+        fs::create_dir_all(path.join(".minecraft"))?;
+        let instance = Self {
+            path: path.to_path_buf(),
+            config: InstanceConfig {
+                name,
+                components: vec![Component {
+                    id: String::from(MINECRAFT_COMPONENT_ID),
+                    version: mc_version,
+                }],
+                launch: InstanceLaunchConfig::default(),
+                metadata: InstanceMetadata {
+                    created: Some(unix_time(SystemTime::now())),
+                    ..Default::default()
+                },
+            },
+        };
+        instance.save_config()?;
+        Ok(instance)
     }
 
     pub fn get_game_dir(&self) -> PathBuf {
         self.path.join(".minecraft")
     }
 
+    /// The icon set in the [metadata](InstanceMetadata::icon). Names of files in the instance
+    /// directory take precedence over built-in icons.
+    pub fn icon(&self) -> Option<InstanceIcon<'_>> {
+        let icon = self.config.metadata.icon.as_deref()?;
+        let path = self.path.join(icon);
+        if check_path(icon) && Path::new(icon).components().count() == 1 && path.is_file() {
+            Some(InstanceIcon::File(path))
+        } else {
+            Some(InstanceIcon::BuiltIn(icon))
+        }
+    }
+
+    /// Add a play session to the playtime, and mark the instance as last played at
+    /// `started_at`.
+    pub fn record_session(
+        &mut self,
+        started_at: SystemTime,
+        exited_at: SystemTime,
+    ) -> Result<(), InstanceManagerError> {
+        let metadata = &mut self.config.metadata;
+        metadata.last_played = Some(unix_time(started_at));
+        metadata.playtime += exited_at
+            .duration_since(started_at)
+            .unwrap_or_default()
+            .as_secs();
+        self.save_config()
+    }
+
+    /// Set the version of the component with the ID `id`, after checking that the version
+    /// exists. Components the instance doesn't have yet are added in front, so they take
+    /// precedence over the others.
+    pub async fn set_component_version(
+        &mut self,
+        meta_client: &MetaClient<'_>,
+        id: &str,
+        version: String,
+    ) -> Result<(), InstanceManagerError> {
+        check_component_version(meta_client, id, &version).await?;
+        match self
+            .config
+            .components
+            .iter_mut()
+            .find(|component| component.id == id)
+        {
+            Some(component) => component.version = version,
+            None => self.config.components.insert(
+                0,
+                Component {
+                    id: id.to_string(),
+                    version,
+                },
+            ),
+        }
+        self.save_config()
+    }
+
+    /// Remove the component with the ID `id`. Minecraft itself can't be removed.
+    pub fn remove_component(&mut self, id: &str) -> Result<(), InstanceManagerError> {
+        if id == MINECRAFT_COMPONENT_ID {
+            return Err(InstanceManagerError::MinecraftRequired);
+        }
+        let len = self.config.components.len();
+        self.config
+            .components
+            .retain(|component| component.id != id);
+        if self.config.components.len() == len {
+            return Err(InstanceManagerError::ComponentNotFound(id.to_string()));
+        }
+        self.save_config()
+    }
+
+    /// The modloader of the instance, [`Modloader::Vanilla`] if it has none.
+    pub fn modloader(&self) -> Modloader {
+        self.config
+            .components
+            .iter()
+            .find_map(|component| Modloader::from_component_id(&component.id))
+            .unwrap_or(Modloader::Vanilla)
+    }
+
+    /// Replace the modloader with `modloader` in version `version`, which is required unless
+    /// switching to [`Modloader::Vanilla`].
+    pub async fn set_modloader(
+        &mut self,
+        meta_client: &MetaClient<'_>,
+        modloader: Modloader,
+        version: Option<String>,
+    ) -> Result<(), InstanceManagerError> {
+        let component = match modloader.component_id() {
+            Some(id) => {
+                let version =
+                    version.ok_or(InstanceManagerError::ModloaderVersionRequired(modloader))?;
+                check_component_version(meta_client, id, &version).await?;
+                Some(Component {
+                    id: id.to_string(),
+                    version,
+                })
+            }
+            None => None,
+        };
+        self.config
+            .components
+            .retain(|component| Modloader::from_component_id(&component.id).is_none());
+        if let Some(component) = component {
+            self.config.components.insert(0, component);
+        }
+        self.save_config()
+    }
+
     pub fn get_component_version(&self, id: &str) -> Option<&str> {
         self.config
             .components
@@ -205,3 +749,1137 @@ impl Instance {
             .next()
     }
 }
+
+async fn check_component_version(
+    meta_client: &MetaClient<'_>,
+    id: &str,
+    version: &str,
+) -> Result<(), InstanceManagerError> {
+    if meta_client.component_version_exists(id, version).await? {
+        Ok(())
+    } else {
+        Err(InstanceManagerError::UnknownComponentVersion {
+            id: id.to_string(),
+            version: version.to_string(),
+        })
+    }
+}
+
+pub(crate) fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// The order of instances in an [`InstanceListing`]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum InstanceSort {
+    #[default]
+    Name,
+    /// Most recently played first, never played last
+    LastPlayed,
+    /// Most played first
+    Playtime,
+    /// Newest first
+    Created,
+}
+
+impl InstanceSort {
+    pub fn compare(self, a: &Instance, b: &Instance) -> Ordering {
+        let (a_meta, b_meta) = (&a.config.metadata, &b.config.metadata);
+        match self {
+            Self::Name => Ordering::Equal,
+            Self::LastPlayed => Reverse(a_meta.last_played).cmp(&Reverse(b_meta.last_played)),
+            Self::Playtime => Reverse(a_meta.playtime).cmp(&Reverse(b_meta.playtime)),
+            Self::Created => Reverse(a_meta.created).cmp(&Reverse(b_meta.created)),
+        }
+        .then_with(|| {
+            a.config
+                .name
+                .to_lowercase()
+                .cmp(&b.config.name.to_lowercase())
+        })
+    }
+}
+
+/// Which instances to keep in an [`InstanceListing`]
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct InstanceFilter {
+    /// Text to find in the name, ID, notes or tags, ignoring case
+    pub text: Option<String>,
+    /// Tags that all have to be set
+    pub tags: Vec<String>,
+}
+
+impl InstanceFilter {
+    /// Parse a search query, in which words like `tag:modded` are tags and the rest is text.
+    pub fn parse(query: &str) -> Self {
+        let mut text = vec![];
+        let mut tags = vec![];
+        for word in query.split_whitespace() {
+            match word.strip_prefix("tag:") {
+                Some(tag) if !tag.is_empty() => tags.push(tag.to_string()),
+                _ => text.push(word),
+            }
+        }
+        Self {
+            text: (!text.is_empty()).then(|| text.join(" ")),
+            tags,
+        }
+    }
+
+    pub fn matches(&self, instance: &Instance) -> bool {
+        let metadata = &instance.config.metadata;
+        if !self.tags.iter().all(|tag| metadata.has_tag(tag)) {
+            return false;
+        }
+        let Some(text) = &self.text else {
+            return true;
+        };
+        let text = text.to_lowercase();
+        [&instance.config.name[..], instance.id()]
+            .into_iter()
+            .chain(metadata.notes.as_deref())
+            .chain(metadata.tags.iter().map(|tag| &tag[..]))
+            .any(|field| field.to_lowercase().contains(&text))
+    }
+}
+
+/// Makes an instance, see [`Instance::builder`].
+///
+/// ```no_run
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # use helixlauncher_core::{config::Config, launch::instance::Instance, meta::MetaClient};
+/// # let data_tempdir = tempfile::tempdir()?;
+/// # let config = Config::new_with_data_dir("dev.helixlauncher.HelixLauncher", "HelixLauncher", data_tempdir.path().to_path_buf())?;
+/// let instance = Instance::builder(String::from("Custom LWJGL"))
+///     .component("org.lwjgl3", "3.3.3")
+///     .component("net.minecraft", "1.20.4")
+///     .build(&MetaClient::new(&config), &config.get_instances_path())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct InstanceBuilder {
+    name: String,
+    components: Vec<Component>,
+    launch: InstanceLaunchConfig,
+    metadata: InstanceMetadata,
+    group: Option<PathBuf>,
+}
+
+impl InstanceBuilder {
+    /// Add a component. Components added first take precedence over the ones after them.
+    pub fn component(mut self, id: &str, version: &str) -> Self {
+        self.components.push(Component {
+            id: id.to_string(),
+            version: version.to_string(),
+        });
+        self
+    }
+
+    pub fn components(self, components: Vec<Component>) -> Self {
+        Self { components, ..self }
+    }
+
+    pub fn launch(self, launch: InstanceLaunchConfig) -> Self {
+        Self { launch, ..self }
+    }
+
+    /// The creation time is filled in if it isn't set.
+    pub fn metadata(self, metadata: InstanceMetadata) -> Self {
+        Self { metadata, ..self }
+    }
+
+    /// Make the instance in `group` instead of the instances directory.
+    pub fn group(self, group: Option<&InstanceGroup>) -> Self {
+        Self {
+            group: group.map(|group| group.path.clone()),
+            ..self
+        }
+    }
+
+    /// Check the components against meta, then make the instance in `instances_dir`, or in the
+    /// group if one was set.
+    pub async fn build(
+        self,
+        meta_client: &MetaClient<'_>,
+        instances_dir: &Path,
+    ) -> Result<Instance, InstanceManagerError> {
+        self.check()?;
+        for component in &self.components {
+            check_component_version(meta_client, &component.id, &component.version).await?;
+        }
+        self.create(instances_dir)
+    }
+
+    /// Check what can be checked without meta.
+    fn check(&self) -> Result<(), InstanceManagerError> {
+        if self.name.trim().is_empty() {
+            return Err(InstanceManagerError::EmptyName);
+        }
+        if !self
+            .components
+            .iter()
+            .any(|component| component.id == MINECRAFT_COMPONENT_ID)
+        {
+            return Err(InstanceManagerError::MinecraftRequired);
+        }
+        for (i, component) in self.components.iter().enumerate() {
+            if self.components[..i]
+                .iter()
+                .any(|other| other.id == component.id)
+            {
+                return Err(InstanceManagerError::DuplicateComponent(
+                    component.id.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Make the instance without checking the components against meta.
+    pub(crate) fn create(self, instances_dir: &Path) -> Result<Instance, InstanceManagerError> {
+        self.check()?;
+
+        // make instance folder & skeleton
+        let dir = self.group.as_deref().unwrap_or(instances_dir);
+        let instance_dir = create_instance_dir(dir, &self.name)?;
+        fs::create_dir(instance_dir.join(".minecraft"))?;
+
+        let mut metadata = self.metadata;
+        metadata
+            .created
+            .get_or_insert_with(|| unix_time(SystemTime::now()));
+        let instance = Instance {
+            path: instance_dir,
+            config: InstanceConfig {
+                name: self.name,
+                components: self.components,
+                launch: self.launch,
+                metadata,
+            },
+        };
+        instance.save_config()?;
+        Ok(instance)
+    }
+}
+
+/// The result of [`Instance::list_instances`]. Groups are sorted by their sort order and name,
+/// instances by name unless [sorted](Self::sort) otherwise.
+#[derive(Debug, Default)]
+pub struct InstanceListing {
+    /// The instances outside of any group
+    pub instances: Vec<Instance>,
+    pub groups: Vec<GroupListing>,
+    /// Problems in all groups
+    pub diagnostics: Vec<ListingDiagnostic>,
+}
+
+impl InstanceListing {
+    /// Sort the instances in each group.
+    pub fn sort(&mut self, sort: InstanceSort) {
+        fn sort_groups(
+            instances: &mut [Instance],
+            groups: &mut [GroupListing],
+            sort: InstanceSort,
+        ) {
+            instances.sort_by(|a, b| sort.compare(a, b));
+            for group in groups {
+                sort_groups(&mut group.instances, &mut group.groups, sort);
+            }
+        }
+
+        sort_groups(&mut self.instances, &mut self.groups, sort);
+    }
+
+    /// Remove the instances not matching `filter`. Groups are kept even if they end up empty.
+    pub fn filter(&mut self, filter: &InstanceFilter) {
+        fn filter_groups(
+            instances: &mut Vec<Instance>,
+            groups: &mut [GroupListing],
+            filter: &InstanceFilter,
+        ) {
+            instances.retain(|instance| filter.matches(instance));
+            for group in groups {
+                filter_groups(&mut group.instances, &mut group.groups, filter);
+            }
+        }
+
+        filter_groups(&mut self.instances, &mut self.groups, filter);
+    }
+
+    /// All instances in display order, each with the groups it is in, outermost first. The
+    /// instances outside of any group come first.
+    pub fn flatten(self) -> Vec<(Vec<InstanceGroup>, Instance)> {
+        fn flatten_into(
+            parents: &[InstanceGroup],
+            instances: Vec<Instance>,
+            groups: Vec<GroupListing>,
+            result: &mut Vec<(Vec<InstanceGroup>, Instance)>,
+        ) {
+            for instance in instances {
+                result.push((parents.to_vec(), instance));
+            }
+            for group in groups {
+                let mut parents = parents.to_vec();
+                parents.push(group.group);
+                flatten_into(&parents, group.instances, group.groups, result);
+            }
+        }
+
+        let mut result = vec![];
+        flatten_into(&[], self.instances, self.groups, &mut result);
+        result
+    }
+}
+
+/// A group in an [`InstanceListing`], with its content
+#[derive(Debug)]
+pub struct GroupListing {
+    pub group: InstanceGroup,
+    pub instances: Vec<Instance>,
+    pub groups: Vec<GroupListing>,
+}
+
+/// A folder in the instances directory that could not be read as an instance
+#[derive(Debug)]
+pub struct ListingDiagnostic {
+    pub path: PathBuf,
+    pub error: InstanceManagerError,
+    /// Whether the folder looks like an instance with a missing or broken config, which
+    /// [`Instance::adopt`] can fix
+    pub adoptable: bool,
+}
+
+fn looks_like_instance(path: &Path) -> bool {
+    path.join(INSTANCE_CONFIG_NAME).is_file() || path.join(".minecraft").is_dir()
+}
+
+/// List `dir` into `instances` and `groups`, descending into the groups.
+fn list_dir(
+    dir: &Path,
+    instances: &mut Vec<Instance>,
+    groups: &mut Vec<GroupListing>,
+    diagnostics: &mut Vec<ListingDiagnostic>,
+) -> Result<(), InstanceManagerError> {
+    let entries = match fs::read_dir(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        r => r,
+    }?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if entry.file_type()?.is_dir() && path.join(GROUP_CONFIG_NAME).is_file() {
+            let group = InstanceGroup::from_path(&path).unwrap_or_else(|error| {
+                // still show what is in it, under the name of the folder
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                diagnostics.push(ListingDiagnostic {
+                    path: path.clone(),
+                    error,
+                    adoptable: false,
+                });
+                InstanceGroup {
+                    path: path.clone(),
+                    config: GroupConfig::new(name),
+                }
+            });
+            let mut listing = GroupListing {
+                group,
+                instances: vec![],
+                groups: vec![],
+            };
+            list_dir(
+                &path,
+                &mut listing.instances,
+                &mut listing.groups,
+                diagnostics,
+            )?;
+            groups.push(listing);
+            continue;
+        }
+        match Instance::from_path(&path) {
+            Ok(instance) => instances.push(instance),
+            Err(error) => diagnostics.push(ListingDiagnostic {
+                adoptable: looks_like_instance(&path),
+                path,
+                error,
+            }),
+        }
+    }
+    instances.sort_by(|a, b| InstanceSort::Name.compare(a, b));
+    groups.sort_by_cached_key(|listing| {
+        (
+            listing.group.config.sort_order,
+            listing.group.config.name.to_lowercase(),
+        )
+    });
+    Ok(())
+}
+
+/// Find the entry with the ID `id` in `instances_dir` or its groups, or where it would be if it
+/// doesn't exist.
+fn find_by_id(instances_dir: &Path, id: &str) -> Result<PathBuf, InstanceManagerError> {
+    // a single, harmless path component, so the ID can't point outside of the instances
+    // directory
+    if !check_path(id) || Path::new(id).components().count() != 1 {
+        return Err(InstanceManagerError::InvalidId(id.to_string()));
+    }
+    Ok(find_entry(instances_dir, id)?.unwrap_or_else(|| instances_dir.join(id)))
+}
+
+/// The display settings of an [`InstanceGroup`], stored in `directory.helix.json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GroupConfig {
+    pub name: String,
+    /// Groups are sorted by this first, then by name
+    #[serde(default)]
+    pub sort_order: i32,
+    /// Name of a themed icon, or path to an image
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Whether the group is shown collapsed
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+impl GroupConfig {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            sort_order: 0,
+            icon: None,
+            collapsed: false,
+        }
+    }
+}
+
+/// A group of instances, which is a directory in the instances directory or in another group.
+/// Like instances, groups are identified by the name of their directory.
+#[derive(Debug, Clone)]
+pub struct InstanceGroup {
+    pub path: PathBuf,
+    pub config: GroupConfig,
+}
+
+impl InstanceGroup {
+    /// Make a new, empty group in `dir`, which is either the instances directory or a group.
+    pub fn create(dir: &Path, name: String) -> Result<Self, InstanceManagerError> {
+        if name.trim().is_empty() {
+            return Err(InstanceManagerError::EmptyName);
+        }
+        let group = Self {
+            path: create_instance_dir(dir, &name)?,
+            config: GroupConfig::new(name),
+        };
+        group.save_config()?;
+        Ok(group)
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, InstanceManagerError> {
+        let path = path.as_ref().to_path_buf();
+        let config = match File::open(path.join(GROUP_CONFIG_NAME)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(InstanceManagerError::NotAGroup),
+            r => r.map_err(InstanceManagerError::from),
+        }?;
+        Ok(Self {
+            config: serde_json::from_reader(BufReader::new(config))?,
+            path,
+        })
+    }
+
+    /// Fetch the group with the ID `id` from the instances directory or one of its groups.
+    pub fn from_id<P: AsRef<Path>>(
+        instances_dir: P,
+        id: &str,
+    ) -> Result<Self, InstanceManagerError> {
+        Self::from_path(find_by_id(instances_dir.as_ref(), id)?)
+    }
+
+    /// The ID of the group, which is the name of its directory.
+    pub fn id(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    }
+
+    pub fn save_config(&self) -> Result<(), InstanceManagerError> {
+        let mut group_json = Sponge::new_for(self.path.join(GROUP_CONFIG_NAME))?;
+        serde_json::to_writer_pretty(&mut group_json, &self.config)?;
+        group_json.commit()?;
+        Ok(())
+    }
+
+    /// Delete the group, which has to be empty.
+    pub fn delete(self) -> Result<(), InstanceManagerError> {
+        for entry in fs::read_dir(&self.path)? {
+            if entry?.file_name() != GROUP_CONFIG_NAME {
+                return Err(InstanceManagerError::GroupNotEmpty);
+            }
+        }
+        fs::remove_dir_all(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Point the `links` at `target`.
+fn relink(links: &[PathBuf], target: &Path) -> io::Result<()> {
+    for link in links {
+        remove_dir_symlink(link)?;
+        symlink(target, link, true)?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct TrashInfo {
+    id: String,
+    name: String,
+    original_path: PathBuf,
+    /// Where the instance is restored to if its group is gone
+    instances_dir: PathBuf,
+    /// Seconds since the Unix epoch
+    deleted_at: u64,
+}
+
+fn read_trash_info(path: &Path) -> Option<TrashInfo> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+/// An instance moved to the trash by [`Instance::delete`].
+#[derive(Debug, Clone)]
+pub struct TrashedInstance {
+    /// The trash entry, containing the instance directory
+    pub path: PathBuf,
+    info: TrashInfo,
+}
+
+impl TrashedInstance {
+    /// List the deleted instances, most recently deleted first. Entries whose information can't
+    /// be read are left out, so they don't get in the way of deleting more instances.
+    pub fn list(trash_dir: &Path) -> Result<Vec<Self>, InstanceManagerError> {
+        let entries = match fs::read_dir(trash_dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            r => r,
+        }?;
+        let mut trashed = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            // not an entry made by us, one that is being created or a broken one
+            let Some(info) = read_trash_info(&path.join(TRASH_INFO_NAME)) else {
+                continue;
+            };
+            trashed.push(Self { path, info });
+        }
+        trashed.sort_by_key(|trashed| Reverse(trashed.info.deleted_at));
+        Ok(trashed)
+    }
+
+    /// Permanently delete the entries older than [`TRASH_UNDO_WINDOW`].
+    pub fn purge_expired(trash_dir: &Path) -> Result<(), InstanceManagerError> {
+        for trashed in Self::list(trash_dir)? {
+            if trashed.deleted_at() + TRASH_UNDO_WINDOW < SystemTime::now() {
+                trashed.purge()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The ID the instance had
+    pub fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+
+    pub fn deleted_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.info.deleted_at)
+    }
+
+    /// Move the instance back. It gets a new ID if the old one was taken in the meantime, and is
+    /// moved out of its group if the group was deleted.
+    pub fn restore(self) -> Result<Instance, InstanceManagerError> {
+        let mut dir = self.info.original_path.parent().unwrap();
+        if dir != self.info.instances_dir && !dir.join(GROUP_CONFIG_NAME).is_file() {
+            dir = &self.info.instances_dir;
+        }
+        fs::create_dir_all(dir)?;
+        let path = if find_entry(instances_root(dir), &self.info.id)?.is_some() {
+            create_instance_dir(dir, &self.info.name)?
+        } else {
+            let path = dir.join(&self.info.id);
+            match fs::create_dir(&path) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    create_instance_dir(dir, &self.info.name)?
+                }
+                r => r.map(|()| path)?,
+            }
+        };
+        fs::remove_dir(&path)?;
+        fs::rename(self.path.join(&self.info.id), &path)?;
+        fs::remove_dir_all(&self.path)?;
+        Instance::from_path(path)
+    }
+
+    /// Permanently delete the instance.
+    pub fn purge(self) -> Result<(), InstanceManagerError> {
+        fs::remove_dir_all(self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::meta_config;
+
+    fn new_instance(name: &str, instances_dir: &Path) -> Result<Instance, InstanceManagerError> {
+        Instance::new(
+            String::from(name),
+            String::from("1.20.4"),
+            InstanceLaunchConfig::default(),
+            instances_dir,
+            Modloader::Vanilla,
+            None,
+        )
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("My Modpack (1.20)"), "my-modpack-1-20");
+        assert_eq!(slugify("../../etc"), "etc");
+        assert_eq!(slugify("  Über--Pack "), "ber-pack");
+        assert_eq!(slugify("CON"), FALLBACK_ID);
+        assert_eq!(slugify("???"), FALLBACK_ID);
+    }
+
+    #[test]
+    fn colliding_names() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+
+        let first = new_instance("Survival", &instances_dir)?;
+        let second = new_instance("survival", &instances_dir)?;
+        let third = new_instance("Survival/", &instances_dir)?;
+        assert_eq!(first.id(), "survival");
+        assert_eq!(second.id(), "survival-2");
+        assert_eq!(third.id(), "survival-3");
+        assert_eq!(third.config.name, "Survival/");
+
+        let found = Instance::from_id(&instances_dir, "survival-2")?;
+        assert_eq!(found.path, second.path);
+        assert!(matches!(
+            new_instance(" ", &instances_dir),
+            Err(InstanceManagerError::EmptyName)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn rename_duplicate_delete() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+        let trash_dir = dir.path().join("trash");
+
+        let mut instance = new_instance("Survival", &instances_dir)?;
+        fs::create_dir_all(instance.get_game_dir().join("saves/World"))?;
+        fs::write(instance.get_game_dir().join("options.txt"), "fov:0.5")?;
+
+        instance.rename(String::from("SURVIVAL!"), true)?;
+        assert_eq!(instance.id(), "survival");
+        instance.rename(String::from("Hardcore"), false)?;
+        assert_eq!(instance.id(), "survival");
+        instance.rename(String::from("Hardcore"), true)?;
+        assert_eq!(instance.id(), "hardcore");
+        assert_eq!(
+            Instance::from_id(&instances_dir, "hardcore")?.config.name,
+            "Hardcore"
+        );
+
+        let copy = instance.duplicate(String::from("Hardcore"), false)?;
+        assert_eq!(copy.id(), "hardcore-2");
+        assert_eq!(
+            fs::read_to_string(copy.get_game_dir().join("options.txt"))?,
+            "fov:0.5"
+        );
+        assert!(!copy.get_game_dir().join("saves").is_symlink());
+        let shared = instance.duplicate(String::from("Shared"), true)?;
+        assert!(shared.get_game_dir().join("saves").is_symlink());
+        assert!(shared.get_game_dir().join("saves/World").is_dir());
+        instance.rename(String::from("Hardcore Original"), true)?;
+        assert!(shared.get_game_dir().join("saves/World").is_dir());
+
+        let trashed = instance.delete(&trash_dir)?;
+        assert!(Instance::from_id(&instances_dir, "hardcore-original").is_err());
+        let listed = TrashedInstance::list(&trash_dir)?;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name(), "Hardcore Original");
+        // the worlds were handed over to the copy sharing them
+        assert!(!shared.get_game_dir().join("saves").is_symlink());
+        assert!(shared.get_game_dir().join("saves/World").is_dir());
+
+        let restored = trashed.restore()?;
+        assert_eq!(restored.id(), "hardcore-original");
+        assert!(TrashedInstance::list(&trash_dir)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn broken_trash_entries() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+        let trash_dir = dir.path().join("trash");
+
+        let broken = trash_dir.join("broken-1");
+        fs::create_dir_all(&broken)?;
+        fs::write(broken.join(TRASH_INFO_NAME), "{\"id\": \"bro")?;
+        fs::create_dir_all(trash_dir.join("half-2"))?;
+
+        new_instance("Survival", &instances_dir)?.delete(&trash_dir)?;
+        let listed = TrashedInstance::list(&trash_dir)?;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].name(), "Survival");
+        assert!(broken.is_dir());
+        Ok(())
+    }
+
+    #[test]
+    fn rename_without_saves() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+
+        let mut instance = new_instance("Survival", &instances_dir)?;
+        assert!(!instance.get_game_dir().join(SAVES_DIR).exists());
+        instance.rename(String::from("Other"), true)?;
+        assert_eq!(instance.id(), "other");
+        assert_eq!(
+            Instance::from_id(&instances_dir, "other")?.config.name,
+            "Other"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn list_and_adopt() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+        let listing = Instance::list_instances(&instances_dir)?;
+        assert!(listing.instances.is_empty() && listing.diagnostics.is_empty());
+
+        new_instance("Survival", &instances_dir)?;
+        fs::write(instances_dir.join(".DS_Store"), "")?;
+        fs::create_dir(instances_dir.join("screenshots"))?;
+        fs::create_dir_all(instances_dir.join("copied/.minecraft/saves"))?;
+        fs::create_dir(instances_dir.join("broken"))?;
+        fs::write(
+            instances_dir.join("broken").join(INSTANCE_CONFIG_NAME),
+            r#"{"name": "Creative", "components": "1.20.4"}"#,
+        )?;
+
+        let mut listing = Instance::list_instances(&instances_dir)?;
+        assert_eq!(listing.instances.len(), 1);
+        listing.diagnostics.sort_by(|a, b| a.path.cmp(&b.path));
+        let diagnostics: Vec<_> = listing
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.path.file_name().unwrap().to_str().unwrap(),
+                    diagnostic.adoptable,
+                )
+            })
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [("broken", true), ("copied", true), ("screenshots", false)]
+        );
+
+        let broken = Instance::adopt(instances_dir.join("broken"), String::from("1.20.4"))?;
+        assert_eq!(broken.config.name, "Creative");
+        assert!(broken.path.join(BROKEN_CONFIG_NAME).is_file());
+        let copied = Instance::adopt(instances_dir.join("copied"), String::from("1.20.4"))?;
+        assert_eq!(copied.config.name, "copied");
+        assert_eq!(
+            copied.get_component_version("net.minecraft"),
+            Some("1.20.4")
+        );
+
+        let listing = Instance::list_instances(&instances_dir)?;
+        assert_eq!(listing.instances.len(), 3);
+        assert_eq!(listing.diagnostics.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn groups() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+
+        let project = InstanceGroup::create(&instances_dir, String::from("Project"))?;
+        let mut archive = InstanceGroup::create(&instances_dir, String::from("Archive"))?;
+        archive.config.sort_order = 1;
+        archive.save_config()?;
+        let nested = InstanceGroup::create(&project.path, String::from("Modded"))?;
+        assert_eq!(nested.id(), "modded");
+
+        let mut survival = new_instance("Survival", &instances_dir)?;
+        fs::create_dir_all(survival.get_game_dir().join("saves/World"))?;
+        let shared = survival.duplicate(String::from("Shared"), true)?;
+        survival.move_to_group(Some(&nested))?;
+        assert_eq!(survival.id(), "survival");
+        assert!(survival.path.starts_with(&nested.path));
+        assert!(shared.get_game_dir().join("saves/World").is_dir());
+        // IDs are unique across groups
+        assert_eq!(new_instance("Survival", &instances_dir)?.id(), "survival-2");
+        assert_eq!(new_instance("Modded", &project.path)?.id(), "modded-2");
+        assert_eq!(
+            Instance::from_id(&instances_dir, "survival")?.path,
+            survival.path
+        );
+        assert!(matches!(
+            Instance::from_id(&instances_dir, "modded"),
+            Err(InstanceManagerError::NotAnInstance)
+        ));
+
+        let listing = Instance::list_instances(&instances_dir)?;
+        assert!(listing.diagnostics.is_empty());
+        let groups: Vec<_> = listing
+            .groups
+            .iter()
+            .map(|group| group.group.id())
+            .collect();
+        assert_eq!(groups, ["project", "archive"]);
+        let flattened: Vec<_> = listing
+            .flatten()
+            .into_iter()
+            .map(|(groups, instance)| {
+                let groups: Vec<_> = groups.into_iter().map(|group| group.config.name).collect();
+                (groups.join("/"), instance.id().to_string())
+            })
+            .collect();
+        assert_eq!(
+            flattened,
+            [
+                (String::from(""), String::from("shared")),
+                (String::from(""), String::from("survival-2")),
+                (String::from("Project"), String::from("modded-2")),
+                (String::from("Project/Modded"), String::from("survival")),
+            ]
+        );
+
+        let nested = InstanceGroup::from_id(&instances_dir, "modded")?;
+        assert!(matches!(
+            nested.clone().delete(),
+            Err(InstanceManagerError::GroupNotEmpty)
+        ));
+        survival.move_to_group(None)?;
+        assert_eq!(survival.path, instances_dir.join("survival"));
+        assert!(shared.get_game_dir().join("saves/World").is_dir());
+        nested.delete()?;
+        assert!(!project.path.join("modded").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn groups_without_saves() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+        let trash_dir = dir.path().join("trash");
+
+        let group = InstanceGroup::create(&instances_dir, String::from("Project"))?;
+        // never launched, so there is no game directory
+        let mut instance = new_instance("Fresh", &instances_dir)?;
+        instance.move_to_group(Some(&group))?;
+        assert_eq!(instance.path, group.path.join("fresh"));
+        instance.move_to_group(None)?;
+        assert_eq!(instance.path, instances_dir.join("fresh"));
+
+        // restored out of a group deleted in the meantime
+        instance.move_to_group(Some(&group))?;
+        let trashed = instance.delete(&trash_dir)?;
+        group.delete()?;
+        let restored = trashed.restore()?;
+        assert_eq!(restored.path, instances_dir.join("fresh"));
+        assert!(!instances_dir.join("project").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn describe_playtime() {
+        let metadata = |playtime, last_played| InstanceMetadata {
+            playtime,
+            last_played,
+            ..InstanceMetadata::default()
+        };
+        let now = 100 * 24 * 60 * 60;
+        assert_eq!(metadata(0, None).describe_playtime_at(now), "Never played");
+        assert_eq!(
+            metadata(125, Some(now - 60)).describe_playtime_at(now),
+            "2m played, last less than an hour ago"
+        );
+        assert_eq!(
+            metadata(2 * 60 * 60 + 5 * 60, Some(now - 60 * 60)).describe_playtime_at(now),
+            "2h 5m played, last 1 hour ago"
+        );
+        assert_eq!(
+            metadata(60 * 60, Some(now - 3 * 24 * 60 * 60)).describe_playtime_at(now),
+            "1h 0m played, last 3 days ago"
+        );
+    }
+
+    #[test]
+    fn metadata() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let instances_dir = dir.path().join("instances");
+
+        let mut survival = new_instance("Survival", &instances_dir)?;
+        assert!(survival.config.metadata.created.is_some());
+        assert_eq!(survival.icon(), None);
+        survival.config.metadata.icon = Some(String::from("grass"));
+        assert_eq!(survival.icon(), Some(InstanceIcon::BuiltIn("grass")));
+        fs::write(survival.path.join("grass"), "")?;
+        assert_eq!(
+            survival.icon(),
+            Some(InstanceIcon::File(survival.path.join("grass")))
+        );
+        survival.config.metadata.tags = vec![String::from("Vanilla")];
+        survival.save_config()?;
+
+        let mut modded = new_instance("Modded", &instances_dir)?;
+        modded.config.metadata.notes = Some(String::from("Create and friends"));
+        let started_at = UNIX_EPOCH + Duration::from_secs(1000);
+        modded.record_session(started_at, started_at + Duration::from_secs(90))?;
+        modded.record_session(started_at, started_at + Duration::from_secs(30))?;
+        let modded = Instance::from_id(&instances_dir, "modded")?;
+        assert_eq!(modded.config.metadata.playtime, 120);
+        assert_eq!(modded.config.metadata.last_played, Some(1000));
+        assert_eq!(
+            modded.config.metadata.notes.as_deref(),
+            Some("Create and friends")
+        );
+        new_instance("Creative", &instances_dir)?;
+
+        let ids = |sort, query| -> Result<Vec<String>, InstanceManagerError> {
+            let mut listing = Instance::list_instances(&instances_dir)?;
+            listing.filter(&InstanceFilter::parse(query));
+            listing.sort(sort);
+            Ok(listing
+                .instances
+                .iter()
+                .map(|instance| instance.id().to_string())
+                .collect())
+        };
+        assert_eq!(
+            ids(InstanceSort::Name, "")?,
+            ["creative", "modded", "survival"]
+        );
+        assert_eq!(
+            ids(InstanceSort::LastPlayed, "")?,
+            ["modded", "creative", "survival"]
+        );
+        assert_eq!(ids(InstanceSort::Name, "tag:vanilla")?, ["survival"]);
+        assert_eq!(ids(InstanceSort::Name, "CREATE")?, ["modded"]);
+        assert!(ids(InstanceSort::Name, "tag:vanilla create")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn edit_components() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let mut instance = Instance::new(
+            String::from("Modded"),
+            String::from("1.20.1"),
+            InstanceLaunchConfig::default(),
+            dir.path(),
+            Modloader::Fabric,
+            Some(String::from("0.15.11")),
+        )?;
+        assert_eq!(instance.modloader(), Modloader::Fabric);
+        assert!(matches!(
+            instance.remove_component(MINECRAFT_COMPONENT_ID),
+            Err(InstanceManagerError::MinecraftRequired)
+        ));
+        assert!(matches!(
+            instance.remove_component("org.lwjgl3"),
+            Err(InstanceManagerError::ComponentNotFound(_))
+        ));
+        instance.remove_component("net.fabricmc.fabric-loader")?;
+        let instance = Instance::from_path(&instance.path)?;
+        assert_eq!(instance.modloader(), Modloader::Vanilla);
+        assert_eq!(instance.config.components.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn edit_components_with_meta() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let fabric = Modloader::Fabric.component_id().unwrap();
+        let quilt = Modloader::Quilt.component_id().unwrap();
+        let config = meta_config(
+            dir.path(),
+            &[
+                (MINECRAFT_COMPONENT_ID, &["1.20.1", "1.20.4"]),
+                (fabric, &["0.15.11"]),
+                (quilt, &["0.25.0"]),
+            ],
+        )
+        .await?;
+        let meta_client = MetaClient::new(&config);
+        let mut instance = new_instance("Modded", &config.get_instances_path())?;
+
+        instance
+            .set_component_version(&meta_client, MINECRAFT_COMPONENT_ID, String::from("1.20.1"))
+            .await?;
+        assert!(matches!(
+            instance
+                .set_component_version(&meta_client, MINECRAFT_COMPONENT_ID, String::from("1.99"))
+                .await,
+            Err(InstanceManagerError::UnknownComponentVersion { .. })
+        ));
+        assert_eq!(
+            instance.get_component_version(MINECRAFT_COMPONENT_ID),
+            Some("1.20.1")
+        );
+
+        instance
+            .set_modloader(
+                &meta_client,
+                Modloader::Fabric,
+                Some(String::from("0.15.11")),
+            )
+            .await?;
+        instance
+            .set_modloader(&meta_client, Modloader::Quilt, Some(String::from("0.25.0")))
+            .await?;
+        assert!(matches!(
+            instance
+                .set_modloader(&meta_client, Modloader::Fabric, Some(String::from("0.1.0")))
+                .await,
+            Err(InstanceManagerError::UnknownComponentVersion { .. })
+        ));
+        let instance = Instance::from_path(&instance.path)?;
+        assert_eq!(instance.modloader(), Modloader::Quilt);
+        assert_eq!(instance.get_component_version(fabric), None);
+        assert_eq!(instance.get_component_version(quilt), Some("0.25.0"));
+        assert_eq!(instance.config.components[0].id, quilt);
+        assert_eq!(instance.config.components.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn builder() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let group = InstanceGroup::create(dir.path(), String::from("Experiments"))?;
+
+        let instance = Instance::builder(String::from("Custom LWJGL"))
+            .component("org.lwjgl3", "3.3.3")
+            .component(MINECRAFT_COMPONENT_ID, "1.20.4")
+            .group(Some(&group))
+            .metadata(InstanceMetadata {
+                tags: vec![String::from("testing")],
+                ..Default::default()
+            })
+            .create(dir.path())?;
+        assert_eq!(instance.path, group.path.join("custom-lwjgl"));
+        let instance = Instance::from_id(dir.path(), "custom-lwjgl")?;
+        assert_eq!(instance.config.components[0].id, "org.lwjgl3");
+        assert!(instance.config.metadata.has_tag("testing"));
+        assert!(instance.config.metadata.created.is_some());
+
+        assert!(matches!(
+            Instance::builder(String::from("No Minecraft"))
+                .component("org.lwjgl3", "3.3.3")
+                .create(dir.path()),
+            Err(InstanceManagerError::MinecraftRequired)
+        ));
+        assert!(matches!(
+            Instance::builder(String::from("Twice"))
+                .component(MINECRAFT_COMPONENT_ID, "1.20.4")
+                .component(MINECRAFT_COMPONENT_ID, "1.20.1")
+                .create(dir.path()),
+            Err(InstanceManagerError::DuplicateComponent(_))
+        ));
+        assert!(matches!(
+            Instance::new(
+                String::from("Fabric"),
+                String::from("1.20.4"),
+                InstanceLaunchConfig::default(),
+                dir.path(),
+                Modloader::Fabric,
+                None,
+            ),
+            Err(InstanceManagerError::ModloaderVersionRequired(
+                Modloader::Fabric
+            ))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn build_with_meta() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let fabric = Modloader::Fabric.component_id().unwrap();
+        let config = meta_config(
+            dir.path(),
+            &[
+                (MINECRAFT_COMPONENT_ID, &["1.20.4"]),
+                (fabric, &["0.15.11"]),
+            ],
+        )
+        .await?;
+        let meta_client = MetaClient::new(&config);
+        let instances_dir = config.get_instances_path();
+
+        let instance = Instance::builder(String::from("Modded"))
+            .component(fabric, "0.15.11")
+            .component(MINECRAFT_COMPONENT_ID, "1.20.4")
+            .build(&meta_client, &instances_dir)
+            .await?;
+        assert_eq!(instance.modloader(), Modloader::Fabric);
+        assert_eq!(
+            Instance::from_id(&instances_dir, "modded")?.path,
+            instance.path
+        );
+
+        for (id, version) in [(fabric, "0.1.0"), (MINECRAFT_COMPONENT_ID, "1.99")] {
+            let mut builder = Instance::builder(String::from("Unknown")).component(id, version);
+            if id != MINECRAFT_COMPONENT_ID {
+                builder = builder.component(MINECRAFT_COMPONENT_ID, "1.20.4");
+            }
+            assert!(matches!(
+                builder.build(&meta_client, &instances_dir).await,
+                Err(InstanceManagerError::UnknownComponentVersion { .. })
+            ));
+        }
+        assert!(matches!(
+            Instance::builder(String::from("Unknown"))
+                .component("org.example.missing", "1.0")
+                .component(MINECRAFT_COMPONENT_ID, "1.20.4")
+                .build(&meta_client, &instances_dir)
+                .await,
+            Err(InstanceManagerError::MetaError(
+                ComponentMetaRetrievalError::IndexNotFound { .. }
+            ))
+        ));
+        assert!(!instances_dir.join("unknown").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_ids() {
+        for id in ["", "..", "../instances/survival", "a/b", "con"] {
+            assert!(matches!(
+                Instance::from_id("instances", id),
+                Err(InstanceManagerError::InvalidId(_))
+            ));
+        }
+    }
+}
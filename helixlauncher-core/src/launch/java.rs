@@ -0,0 +1,206 @@
+//! Selection of the Java install an instance is launched with.
+//!
+//! Components declare the Java version they need as a requirement on the [`JAVA_COMPONENT_ID`]
+//! pseudo-component, with the major version as its version, e.g. `{"id": "java", "version":
+//! "21"}`. Unless the instance sets its own Java path, the installs found by
+//! [`helixlauncher_java::search`] and the [managed runtimes](super::runtime) are probed and the
+//! best match is picked: the required version on the current architecture if possible, otherwise
+//! the closest newer version. The choice is remembered in the instance directory and reused as
+//! long as it still fits.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use helixlauncher_java::{
+    info::{parse_major_version, Architecture},
+    search::{java_info, search_java_in, JavaInstallInfo, SearchError},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{fs, task};
+
+use crate::config::Config;
+
+use super::instance::Instance;
+
+/// The id components use to require a Java version.
+pub const JAVA_COMPONENT_ID: &str = "java";
+
+const SELECTION_NAME: &str = "java.helix.json";
+
+#[derive(Error, Debug)]
+pub enum JavaSelectionError {
+    #[error("This instance requires Java {required}, but no install of Java {required} or newer was found")]
+    NotFound { required: u32 },
+    #[error(transparent)]
+    Search(#[from] SearchError),
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+}
+
+/// The Java install picked for an instance.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SelectedJava {
+    pub path: PathBuf,
+    /// The Java version that was required when the install was picked
+    pub required: u32,
+}
+
+/// Get the Java major version from a requirement version such as `17` or `>=17`.
+pub fn parse_requirement(version: &Option<String>) -> Option<u32> {
+    parse_major_version(
+        version
+            .as_deref()?
+            .trim_start_matches(|c: char| !c.is_ascii_digit()),
+    )
+}
+
+/// Get the path of the `java` executable to launch `instance` with, given the Java version its
+/// components require.
+pub async fn select_java(
+    config: &Config,
+    instance: &Instance,
+    required: Option<u32>,
+) -> Result<String, JavaSelectionError> {
+    if let Some(path) = &instance.config.launch.javapath {
+        return Ok(path.clone());
+    }
+    let Some(required) = required else {
+        return Ok(String::from("java"));
+    };
+
+    let selection_path = instance.path.join(SELECTION_NAME);
+    if let Some(selected) = read_selection(&selection_path).await {
+        if let Some(path) = selected.path.to_str() {
+            if selected.required == required && fits(&selected.path, required).await {
+                return Ok(path.to_string());
+            }
+        }
+    }
+
+    let mut search_dirs = vec![config.get_java_path()];
+    search_dirs.extend_from_slice(config.get_java_search_dirs());
+    let paths = task::spawn_blocking(move || search_java_in(&search_dirs))
+        .await
+        .unwrap()?;
+    let mut installs = Vec::with_capacity(paths.len());
+    for path in paths {
+        // broken installs are skipped
+        if let Ok(info) = java_info(&path).await {
+            installs.push(info);
+        }
+    }
+
+    let install = best_match(&installs, required, &Architecture::current())
+        .ok_or(JavaSelectionError::NotFound { required })?;
+    let selected = SelectedJava {
+        path: install.path.clone(),
+        required,
+    };
+    fs::write(
+        &selection_path,
+        serde_json::to_vec_pretty(&selected).unwrap(),
+    )
+    .await?;
+    // best_match only picks UTF-8 paths, so this is lossless
+    Ok(selected.path.to_string_lossy().into_owned())
+}
+
+async fn read_selection(path: &Path) -> Option<SelectedJava> {
+    serde_json::from_slice(&fs::read(path).await.ok()?).ok()
+}
+
+/// Whether the install at `path` still exists and is new enough.
+async fn fits(path: &Path, required: u32) -> bool {
+    matches!(java_info(path).await, Ok(info) if info.major_version >= required)
+}
+
+/// Pick the install to use: matching the architecture comes first, then the required version,
+/// then the oldest of the newer versions. Installs whose path isn't UTF-8 are skipped, the path
+/// is passed on as a string.
+fn best_match<'a>(
+    installs: &'a [JavaInstallInfo],
+    required: u32,
+    architecture: &Architecture,
+) -> Option<&'a JavaInstallInfo> {
+    installs
+        .iter()
+        .filter(|install| install.major_version >= required && install.path.to_str().is_some())
+        .min_by_key(|install| {
+            (
+                install.architecture != *architecture,
+                install.major_version,
+                // keep the choice stable between searches
+                install.path.clone(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use helixlauncher_java::info::JavaKind;
+
+    use super::*;
+
+    fn install(path: &str, major_version: u32, architecture: Architecture) -> JavaInstallInfo {
+        JavaInstallInfo {
+            path: PathBuf::from(path),
+            java_home: PathBuf::new(),
+            version: major_version.to_string(),
+            major_version,
+            vendor: None,
+            architecture,
+            kind: JavaKind::Jre,
+        }
+    }
+
+    #[test]
+    fn requirements() {
+        assert_eq!(parse_requirement(&Some(String::from("17"))), Some(17));
+        assert_eq!(parse_requirement(&Some(String::from(">=21"))), Some(21));
+        assert_eq!(parse_requirement(&Some(String::from("1.8"))), Some(8));
+        assert_eq!(parse_requirement(&None), None);
+    }
+
+    #[test]
+    fn pick_best_match() {
+        let installs = [
+            install("/jvm/8", 8, Architecture::X86_64),
+            install("/jvm/17-arm", 17, Architecture::Aarch64),
+            install("/jvm/21", 21, Architecture::X86_64),
+            install("/jvm/22", 22, Architecture::X86_64),
+        ];
+        let pick = |required| {
+            best_match(&installs, required, &Architecture::X86_64).map(|java| &java.path)
+        };
+        assert_eq!(pick(8), Some(&PathBuf::from("/jvm/8")));
+        // the closest newer version on the same architecture
+        assert_eq!(pick(17), Some(&PathBuf::from("/jvm/21")));
+        assert_eq!(pick(21), Some(&PathBuf::from("/jvm/21")));
+        assert_eq!(pick(25), None);
+        assert_eq!(
+            best_match(&installs, 17, &Architecture::Aarch64).map(|java| &java.path),
+            Some(&PathBuf::from("/jvm/17-arm"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_non_utf8_paths() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let mut invalid = install("", 21, Architecture::X86_64);
+        invalid.path = PathBuf::from(OsStr::from_bytes(b"/jvm/\xff"));
+        let installs = [invalid, install("/jvm/22", 22, Architecture::X86_64)];
+        assert_eq!(
+            best_match(&installs, 21, &Architecture::X86_64).map(|java| &java.path),
+            Some(&PathBuf::from("/jvm/22"))
+        );
+        assert_eq!(
+            best_match(&installs[..1], 21, &Architecture::X86_64).map(|java| &java.path),
+            None
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! Launching through `helixlauncher-javalaunch`.
+//!
+//! Instead of running `java`, the game can be started by the javalaunch wrapper, which loads the
+//! JLI library of the Java install and calls `JLI_Launch` itself. The game process is then named
+//! after the launcher, and the arguments are passed over stdin, so they aren't subject to
+//! command-line length limits.
+//!
+//! The launch is described by a [`LaunchRequest`], see [`helixlauncher_javalaunch_protocol`] for
+//! the wire format. The wrapper acknowledges the request in the status file at [`STATUS_NAME`] in
+//! the instance directory, and records there the [`ErrorCode`] of a failure before starting Java.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub use helixlauncher_javalaunch_protocol::{ErrorCode, LaunchMode, LaunchRequest, Status};
+
+/// Name of the status file the wrapper writes to, in the instance directory.
+pub const STATUS_NAME: &str = "javalaunch.status";
+
+/// Overrides the location of the javalaunch executable.
+pub const JAVALAUNCH_ENV: &str = "HELIX_JAVALAUNCH";
+
+#[cfg(windows)]
+const JAVALAUNCH_NAME: &str = "helixlauncher-javalaunch.exe";
+#[cfg(not(windows))]
+const JAVALAUNCH_NAME: &str = "helixlauncher-javalaunch";
+
+#[cfg(windows)]
+const JAVA_NAME: &str = "java.exe";
+#[cfg(not(windows))]
+const JAVA_NAME: &str = "java";
+
+#[cfg(windows)]
+const JLI_NAME: &str = "jli.dll";
+#[cfg(target_os = "macos")]
+const JLI_NAME: &str = "libjli.dylib";
+#[cfg(not(any(windows, target_os = "macos")))]
+const JLI_NAME: &str = "libjli.so";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LaunchBackend {
+    /// Run the `java` executable
+    Java,
+    /// Run the javalaunch wrapper at `executable` with the JLI library at `jli`
+    JavaLaunch { executable: PathBuf, jli: PathBuf },
+}
+
+impl LaunchBackend {
+    /// Use javalaunch for the Java install of `java_path` if both the wrapper and the JLI library
+    /// can be found, plain `java` otherwise.
+    pub fn javalaunch_or_java(java_path: &str) -> Self {
+        match (find_javalaunch(), find_jli(java_path)) {
+            (Some(executable), Some(jli)) => Self::JavaLaunch { executable, jli },
+            _ => Self::Java,
+        }
+    }
+}
+
+/// Find the javalaunch executable, either through [`JAVALAUNCH_ENV`] or next to the current
+/// executable.
+pub fn find_javalaunch() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(JAVALAUNCH_ENV) {
+        return Some(PathBuf::from(path)).filter(|path| path.is_file());
+    }
+    let path = env::current_exe().ok()?.parent()?.join(JAVALAUNCH_NAME);
+    path.is_file().then_some(path)
+}
+
+/// Find the JLI library belonging to the `java` executable at `java_path`, which may also be a
+/// bare command name looked up on `PATH`.
+pub fn find_jli(java_path: &str) -> Option<PathBuf> {
+    let java = resolve_executable(java_path)?.canonicalize().ok()?;
+    // <java home>/bin/java
+    let java_home = java.parent()?.parent()?;
+    jli_candidates(java_home)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+fn resolve_executable(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return Some(path.to_path_buf());
+    }
+    let name = if program == "java" {
+        JAVA_NAME
+    } else {
+        program
+    };
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+fn jli_candidates(java_home: &Path) -> Vec<PathBuf> {
+    let mut candidates = if cfg!(windows) {
+        vec![java_home.join("bin").join(JLI_NAME)]
+    } else {
+        vec![
+            java_home.join("lib").join(JLI_NAME),
+            java_home.join("lib/jli").join(JLI_NAME),
+        ]
+    };
+    if cfg!(target_os = "macos") {
+        // <bundle>/Contents/Home/bin/java, with the library in <bundle>/Contents/MacOS
+        candidates.push(java_home.join("../MacOS").join(JLI_NAME));
+    }
+    // Java 8 keeps it in an architecture-specific directory, lib/amd64/jli on a JRE and
+    // jre/lib/amd64/jli on a JDK
+    for lib in [java_home.join("lib"), java_home.join("jre/lib")] {
+        if let Ok(entries) = lib.read_dir() {
+            candidates.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path().join("jli").join(JLI_NAME)),
+            );
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn find_jli_in_java_home() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let java_home = dir.path().canonicalize()?;
+
+        fs::create_dir_all(java_home.join("bin"))?;
+        fs::write(java_home.join("bin/java"), "")?;
+        let java = java_home.join("bin/java");
+        assert_eq!(find_jli(java.to_str().unwrap()), None);
+
+        // Java 8
+        fs::create_dir_all(java_home.join("jre/lib/amd64/jli"))?;
+        fs::write(java_home.join("jre/lib/amd64/jli/libjli.so"), "")?;
+        assert_eq!(
+            find_jli(java.to_str().unwrap()),
+            Some(java_home.join("jre/lib/amd64/jli/libjli.so"))
+        );
+
+        // Java 9+
+        fs::create_dir_all(java_home.join("lib"))?;
+        fs::write(java_home.join("lib/libjli.so"), "")?;
+        assert_eq!(
+            find_jli(java.to_str().unwrap()),
+            Some(java_home.join("lib/libjli.so"))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+//! Captured game output.
+//!
+//! When a game is launched with captured output, its stdout and stderr are read line by line,
+//! parsed into [`LogLine`]s and teed into `logs/launcher-<timestamp>.log` in the instance
+//! directory. Both log4j's XML layout and the usual plain-text layout are understood.
+
+use std::{
+    fs::{self, File},
+    io::{self, LineWriter, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::Stream;
+use lazy_static::lazy_static;
+use regex::Regex;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::mpsc,
+};
+
+/// Number of launcher logs kept per instance, including the current one.
+const MAX_LOG_FILES: usize = 10;
+const LOG_FILE_PREFIX: &str = "launcher-";
+const LOG_FILE_SUFFIX: &str = ".log";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LogStreamKind {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    pub fn parse(level: &str) -> Option<Self> {
+        Some(match &*level.to_ascii_uppercase() {
+            "TRACE" | "FINEST" | "FINER" => Self::Trace,
+            "DEBUG" | "FINE" => Self::Debug,
+            "INFO" | "CONFIG" => Self::Info,
+            "WARN" | "WARNING" => Self::Warn,
+            "ERROR" | "SEVERE" => Self::Error,
+            "FATAL" => Self::Fatal,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LogLine {
+    pub stream: LogStreamKind,
+    /// `None` if the line doesn't follow a known layout, e.g. a stack trace printed directly to
+    /// stderr.
+    pub level: Option<LogLevel>,
+    pub thread: Option<String>,
+    pub logger: Option<String>,
+    /// Milliseconds since the Unix epoch, only available with the XML layout.
+    pub timestamp: Option<u64>,
+    pub message: String,
+}
+
+/// Streams the lines logged by a running game. Ends once both stdout and stderr are closed.
+pub struct GameLog {
+    receiver: mpsc::UnboundedReceiver<LogLine>,
+    log_file: PathBuf,
+}
+
+impl GameLog {
+    /// Start capturing `stdout` and `stderr`, teeing them into a new log file in `log_dir`.
+    pub fn capture<O, E>(stdout: O, stderr: E, log_dir: &Path) -> io::Result<Self>
+    where
+        O: AsyncRead + Unpin + Send + 'static,
+        E: AsyncRead + Unpin + Send + 'static,
+    {
+        let log_file = create_log_file(log_dir)?;
+        let writer = Arc::new(Mutex::new(LineWriter::new(File::create(&log_file)?)));
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(read_stream(
+            stdout,
+            LogStreamKind::Stdout,
+            sender.clone(),
+            writer.clone(),
+        ));
+        tokio::spawn(read_stream(stderr, LogStreamKind::Stderr, sender, writer));
+
+        Ok(Self { receiver, log_file })
+    }
+
+    pub async fn next_line(&mut self) -> Option<LogLine> {
+        self.receiver.recv().await
+    }
+
+    /// The file the raw output is written to.
+    pub fn log_file(&self) -> &Path {
+        &self.log_file
+    }
+}
+
+impl Stream for GameLog {
+    type Item = LogLine;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+fn create_log_file(log_dir: &Path) -> io::Result<PathBuf> {
+    fs::create_dir_all(log_dir)?;
+
+    let mut old_logs = fs::read_dir(log_dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX))
+        .collect::<Vec<_>>();
+    // the timestamp format sorts chronologically
+    old_logs.sort();
+    if old_logs.len() >= MAX_LOG_FILES {
+        for name in &old_logs[..=old_logs.len() - MAX_LOG_FILES] {
+            match fs::remove_file(log_dir.join(name)) {
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                r => r?,
+            }
+        }
+    }
+
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let mut path = log_dir.join(format!("{LOG_FILE_PREFIX}{timestamp}{LOG_FILE_SUFFIX}"));
+    let mut counter = 1;
+    while path.try_exists()? {
+        path = log_dir.join(format!(
+            "{LOG_FILE_PREFIX}{timestamp}-{counter}{LOG_FILE_SUFFIX}"
+        ));
+        counter += 1;
+    }
+    Ok(path)
+}
+
+async fn read_stream<R: AsyncRead + Unpin>(
+    stream: R,
+    kind: LogStreamKind,
+    sender: mpsc::UnboundedSender<LogLine>,
+    writer: Arc<Mutex<LineWriter<File>>>,
+) {
+    let mut reader = BufReader::new(stream);
+    let mut parser = LogParser::new(kind);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        // a failing log file shouldn't stop the game output from being delivered
+        let _ = writeln!(writer.lock().unwrap(), "{line}");
+
+        if let Some(parsed) = parser.push(line) {
+            // the receiver may have been dropped, keep draining the pipe so the game doesn't block
+            let _ = sender.send(parsed);
+        }
+    }
+    if let Some(parsed) = parser.finish() {
+        let _ = sender.send(parsed);
+    }
+    let _ = writer.lock().unwrap().flush();
+}
+
+lazy_static! {
+    static ref PLAIN_PATTERN: Regex =
+        Regex::new(r"^\[[^\]]*\] \[(?P<thread>[^\]]*)/(?P<level>[A-Z]+)\](?: \[(?P<logger>[^\]]*)\])?:? ?(?P<message>.*)$")
+            .unwrap();
+    static ref XML_ATTRIBUTE_PATTERN: Regex =
+        Regex::new(r#"(?P<name>[a-z]+)="(?P<value>[^"]*)""#).unwrap();
+    static ref XML_CDATA_PATTERN: Regex = Regex::new(
+        r"(?s)<log4j:(?:Message|Throwable)><!\[CDATA\[(?P<content>.*?)\]\]></log4j:(?:Message|Throwable)>"
+    )
+    .unwrap();
+}
+
+/// Turns raw output lines into [`LogLine`]s. XML events span multiple lines, so it needs to
+/// buffer until the event is complete.
+pub struct LogParser {
+    stream: LogStreamKind,
+    xml_event: Option<String>,
+}
+
+impl LogParser {
+    pub fn new(stream: LogStreamKind) -> Self {
+        Self {
+            stream,
+            xml_event: None,
+        }
+    }
+
+    pub fn push(&mut self, line: &str) -> Option<LogLine> {
+        if let Some(event) = &mut self.xml_event {
+            event.push('\n');
+            event.push_str(line);
+            if line.contains("</log4j:Event>") {
+                let event = self.xml_event.take().unwrap();
+                return Some(self.parse_xml_event(&event));
+            }
+            return None;
+        }
+
+        if line.trim_start().starts_with("<log4j:Event") {
+            if line.contains("</log4j:Event>") {
+                return Some(self.parse_xml_event(line));
+            }
+            self.xml_event = Some(line.to_string());
+            return None;
+        }
+
+        Some(self.parse_plain(line))
+    }
+
+    /// Flushes an incomplete XML event, e.g. when the game got killed while logging.
+    pub fn finish(&mut self) -> Option<LogLine> {
+        self.xml_event
+            .take()
+            .map(|event| self.plain_line(None, None, None, event))
+    }
+
+    fn parse_plain(&self, line: &str) -> LogLine {
+        match PLAIN_PATTERN.captures(line) {
+            Some(captures) => match LogLevel::parse(&captures["level"]) {
+                Some(level) => self.plain_line(
+                    Some(level),
+                    Some(captures["thread"].to_string()),
+                    captures.name("logger").map(|m| m.as_str().to_string()),
+                    captures["message"].to_string(),
+                ),
+                None => self.plain_line(None, None, None, line.to_string()),
+            },
+            None => self.plain_line(None, None, None, line.to_string()),
+        }
+    }
+
+    fn plain_line(
+        &self,
+        level: Option<LogLevel>,
+        thread: Option<String>,
+        logger: Option<String>,
+        message: String,
+    ) -> LogLine {
+        LogLine {
+            stream: self.stream,
+            level,
+            thread,
+            logger,
+            timestamp: None,
+            message,
+        }
+    }
+
+    fn parse_xml_event(&self, event: &str) -> LogLine {
+        let header = event.split_once('>').map_or(event, |(header, _)| header);
+        let mut line = self.plain_line(None, None, None, String::new());
+        for captures in XML_ATTRIBUTE_PATTERN.captures_iter(header) {
+            let value = unescape_xml(&captures["value"]);
+            match &captures["name"] {
+                "logger" => line.logger = Some(value),
+                "timestamp" => line.timestamp = value.parse().ok(),
+                "level" => line.level = LogLevel::parse(&value),
+                "thread" => line.thread = Some(value),
+                _ => {}
+            }
+        }
+        for captures in XML_CDATA_PATTERN.captures_iter(event) {
+            if !line.message.is_empty() {
+                line.message.push('\n');
+            }
+            line.message.push_str(&captures["content"]);
+        }
+        line
+    }
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_plain_line() {
+        let mut parser = LogParser::new(LogStreamKind::Stdout);
+        let line = parser
+            .push("[12:34:56] [Render thread/WARN]: Missing sound for event")
+            .unwrap();
+        assert_eq!(line.level, Some(LogLevel::Warn));
+        assert_eq!(line.thread.as_deref(), Some("Render thread"));
+        assert_eq!(line.message, "Missing sound for event");
+
+        let line = parser
+            .push("[12:34:56] [main/INFO] [FML]: Forge Mod Loader version")
+            .unwrap();
+        assert_eq!(line.level, Some(LogLevel::Info));
+        assert_eq!(line.logger.as_deref(), Some("FML"));
+        assert_eq!(line.message, "Forge Mod Loader version");
+    }
+
+    #[test]
+    fn parse_unstructured_line() {
+        let mut parser = LogParser::new(LogStreamKind::Stderr);
+        let line = parser.push("\tat java.base/java.lang.Thread.run").unwrap();
+        assert_eq!(line.stream, LogStreamKind::Stderr);
+        assert_eq!(line.level, None);
+        assert_eq!(line.message, "\tat java.base/java.lang.Thread.run");
+    }
+
+    #[test]
+    fn parse_xml_event() {
+        let mut parser = LogParser::new(LogStreamKind::Stdout);
+        assert!(parser
+            .push(r#"<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1700000000000" level="ERROR" thread="Render thread">"#)
+            .is_none());
+        assert!(parser
+            .push("  <log4j:Message><![CDATA[Couldn't load <thing> & stuff]]></log4j:Message>")
+            .is_none());
+        let line = parser.push("</log4j:Event>").unwrap();
+        assert_eq!(line.level, Some(LogLevel::Error));
+        assert_eq!(line.timestamp, Some(1700000000000));
+        assert_eq!(
+            line.logger.as_deref(),
+            Some("net.minecraft.client.Minecraft")
+        );
+        assert_eq!(line.message, "Couldn't load <thing> & stuff");
+    }
+
+    #[test]
+    fn flush_incomplete_xml_event() {
+        let mut parser = LogParser::new(LogStreamKind::Stdout);
+        assert!(parser.push(r#"<log4j:Event level="INFO">"#).is_none());
+        assert!(parser.finish().is_some());
+        assert!(parser.finish().is_none());
+    }
+
+    #[test]
+    fn rotate_log_files() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        for i in 0..MAX_LOG_FILES + 3 {
+            File::create(
+                dir.path()
+                    .join(format!("launcher-2000-01-01_00-00-{i:02}.log")),
+            )?;
+        }
+        File::create(dir.path().join("latest.log"))?;
+
+        let path = create_log_file(dir.path())?;
+        File::create(&path)?;
+
+        let remaining = fs::read_dir(dir.path())?.count();
+        assert_eq!(remaining, MAX_LOG_FILES + 1);
+        assert!(!dir.path().join("launcher-2000-01-01_00-00-00.log").exists());
+        Ok(())
+    }
+}
@@ -2,8 +2,16 @@
 //! This module crafts system calls to launch a new Minecraft instance.
 
 pub mod asset;
+pub mod crash;
+pub mod hooks;
 pub mod instance;
+pub mod java;
+pub mod javalaunch;
+pub mod log;
 pub mod prepared;
+pub mod process;
+pub mod runtime;
+pub mod script;
 
 // TODO: Make C API
 
@@ -39,6 +47,12 @@ pub enum LaunchError {
     InvalidFilename { name: String },
     #[error("Feature not supported by the instance: {name}")]
     UnsupportedFeature { name: String },
+    #[error("Launch options cannot be combined: {first} and {second}")]
+    IncompatibleOptions { first: String, second: String },
+    #[error(transparent)]
+    HookFailed(#[from] hooks::HookError),
+    #[error(transparent)]
+    NoJava(#[from] java::JavaSelectionError),
     #[error("{0}")]
     IoError(#[from] io::Error),
 }
@@ -1,16 +1,28 @@
-use std::{collections::HashMap, fs::File, io, path::PathBuf, process::Stdio};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
 
 use anyhow::Result;
 use futures::stream::{self, StreamExt, TryStreamExt};
+use helixlauncher_java::search::java_info;
 use helixlauncher_meta::component::{self, Hash, MinecraftArgument};
 
 use lazy_static::lazy_static;
 use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 use tokio::{
     fs,
+    io::AsyncWriteExt,
     process::{Child, Command},
+    sync::mpsc,
     task,
 };
+use zip::{write::FileOptions, ZipWriter};
 
 use crate::{
     auth::account::Account,
@@ -21,38 +33,313 @@ use crate::{
 use super::{
     asset::MergedComponents,
     asset::{Asset, AssetIndex},
-    download_file, generate_classpath, instance, LaunchError,
+    download_file, generate_classpath,
+    hooks::{self, LaunchHooks},
+    instance, java,
+    javalaunch::{self, LaunchBackend, LaunchMode, LaunchRequest, Status},
+    log::GameLog,
+    process::{GameProcess, LifecycleEvent},
+    LaunchError,
 };
 
-#[derive(Debug)]
+const ARGFILE_NAME: &str = "launch.args";
+const PATHING_JAR_NAME: &str = "classpath.jar";
+
+/// Stands in for the access token in [`PreparedLaunch::args`], so it doesn't end up in
+/// serialized or exported launches.
+pub const ACCESS_TOKEN_PLACEHOLDER: &str = "${user.token}";
+
+/// How the JVM options and the classpath reach the game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArgumentPassing {
+    CommandLine,
+    /// An `@argfile` in the instance directory, supported on Java 9+.
+    Argfile(String),
+    /// A jar in the instance directory whose manifest `Class-Path` holds the classpath, the JVM
+    /// options stay on the command line. Used instead of an argfile before Java 9.
+    PathingJar(String),
+}
+
+/// The access token of the launching account. Never printed or serialized.
+#[derive(Clone, Default)]
+pub struct AccessToken(String);
+
+impl AccessToken {
+    pub fn new(token: String) -> Self {
+        Self(token)
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for AccessToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AccessToken(<redacted>)")
+    }
+}
+
+/// A launch ready to be spawned. Serializing it leaves out the access token, which has to be set
+/// again through [`access_token`](Self::access_token) after deserializing.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PreparedLaunch {
+    pub instance_directory: PathBuf,
     pub working_directory: PathBuf,
     pub java_path: String,
     pub jvm_args: Vec<String>,
     pub classpath: Vec<String>,
     pub main_class: String,
+    /// Game arguments, with the access token replaced by [`ACCESS_TOKEN_PLACEHOLDER`]
     pub args: Vec<String>,
+    #[serde(skip)]
+    pub access_token: AccessToken,
+    /// Commands the game is run under, outermost first
+    pub wrapper_commands: Vec<Vec<String>>,
+    pub env: Vec<(String, String)>,
+    pub env_remove: Vec<String>,
+    pub argument_passing: ArgumentPassing,
+    /// The Java version couldn't be determined, so the classpath is passed through a pathing jar
+    /// instead of an argfile in case it is older than 9
+    #[serde(default)]
+    pub java_version_unknown: bool,
+    pub backend: LaunchBackend,
+    pub hooks: LaunchHooks,
 }
 
 impl PreparedLaunch {
-    // TODO: add better API for log output
+    /// Spawn the game after running the pre-launch command. If `inherit_out` is false, stdout
+    /// and stderr are piped, see [`launch_captured`](Self::launch_captured) for a ready-made way
+    /// of reading them.
     pub async fn launch(&self, inherit_out: bool) -> Result<Child, LaunchError> {
-        if !inherit_out {
-            todo!();
+        self.hooks.run_prelaunch().await?;
+        let output = || {
+            if inherit_out {
+                Stdio::inherit()
+            } else {
+                Stdio::piped()
+            }
+        };
+        let (mut command, stdin) = match &self.backend {
+            LaunchBackend::Java => (self.command(), None),
+            LaunchBackend::JavaLaunch { executable, jli } => {
+                let request = LaunchRequest {
+                    library: jli.to_str().unwrap().to_string(),
+                    mode: LaunchMode::Jli,
+                    working_directory: Some(self.working_directory.to_str().unwrap().to_string()),
+                    env: self.env.clone(),
+                    env_remove: self.env_remove.clone(),
+                    jvm_options: self.jvm_args.clone(),
+                    classpath: self.classpath.clone(),
+                    main_class: self.main_class.clone(),
+                    args: self
+                        .args
+                        .iter()
+                        .map(|arg| self.fill_access_token(arg.clone()))
+                        .collect(),
+                };
+                let status_path = self.javalaunch_status_path();
+                match fs::remove_file(&status_path).await {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+                let mut command = self.wrapped_command(executable.to_str().unwrap());
+                command.arg(status_path);
+                (command, Some(request.encode()?))
+            }
+        };
+        let mut child = command
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(output())
+            .stderr(output())
+            .spawn()?;
+        if let Some(stdin) = stdin {
+            let mut pipe = child.stdin.take().unwrap(); // piped above
+            let written = pipe.write_all(&stdin).await;
+            drop(pipe); // javalaunch starts the game once stdin is closed
+            if let Err(e) = written {
+                let _ = child.start_kill();
+                return Err(e.into());
+            }
+        }
+        Ok(child)
+    }
+
+    /// The command running the game with plain `java`, including wrapper commands and environment
+    /// changes.
+    fn command(&self) -> Command {
+        let mut command = self.wrapped_command(&self.java_path);
+        command.args(
+            self.java_args(&self.argument_passing)
+                .into_iter()
+                .map(|arg| self.fill_access_token(arg)),
+        );
+        command
+    }
+
+    /// `program` behind the wrapper commands, in the working directory and environment of the game.
+    fn wrapped_command(&self, program: &str) -> Command {
+        let mut argv = self
+            .wrapper_commands
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .chain([program]);
+        let mut command = Command::new(argv.next().unwrap()); // there's always the program
+        command.args(argv).current_dir(&self.working_directory);
+        for key in &self.env_remove {
+            command.env_remove(key);
+        }
+        command.envs(self.env_vars());
+        command
+    }
+
+    fn fill_access_token(&self, arg: String) -> String {
+        arg.replace(ACCESS_TOKEN_PLACEHOLDER, self.access_token.secret())
+    }
+
+    /// The program and arguments running the game with plain `java`, including wrapper commands.
+    /// The access token is left as [`ACCESS_TOKEN_PLACEHOLDER`]. The JVM options and classpath are
+    /// always on the command line, so the arguments don't refer to the argfile or pathing jar.
+    pub(crate) fn argv(&self) -> Vec<String> {
+        let mut argv: Vec<String> = self.wrapper_commands.iter().flatten().cloned().collect();
+        argv.push(self.java_path.clone());
+        argv.extend(self.java_args(&ArgumentPassing::CommandLine));
+        argv
+    }
+
+    /// The arguments following the `java` executable.
+    fn java_args(&self, argument_passing: &ArgumentPassing) -> Vec<String> {
+        let mut args = Vec::new();
+        match argument_passing {
+            ArgumentPassing::CommandLine => {
+                args.extend(self.jvm_args.iter().cloned());
+                args.push(String::from("-classpath"));
+                args.push(generate_classpath(&self.classpath));
+            }
+            ArgumentPassing::Argfile(path) => {
+                args.push(format!("@{path}"));
+            }
+            ArgumentPassing::PathingJar(path) => {
+                args.extend(self.jvm_args.iter().cloned());
+                args.push(String::from("-classpath"));
+                args.push(path.clone());
+            }
         }
+        args.push(self.main_class.clone());
+        args.extend(self.args.iter().cloned());
+        args
+    }
+
+    /// The environment variables set for the game, in addition to the inherited ones.
+    pub(crate) fn env_vars(&self) -> Vec<(String, String)> {
+        self.env.clone()
+    }
+
+    /// The contents of the `@argfile`: the JVM options followed by the classpath, each quoted.
+    pub fn argfile_contents(&self) -> String {
         let classpath = generate_classpath(&self.classpath);
-        // TODO: hook up javalaunch
-        Ok(Command::new(&self.java_path)
-            .current_dir(&self.working_directory)
-            .args(&self.jvm_args)
-            .arg("-classpath")
-            .arg(classpath)
-            .arg(&self.main_class)
-            .args(&self.args)
-            .stdin(Stdio::null())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .spawn()?)
+        self.jvm_args
+            .iter()
+            .map(String::as_str)
+            .chain(["-classpath", &classpath])
+            .map(|arg| {
+                let escaped = arg
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r");
+                format!("\"{escaped}\"\n")
+            })
+            .collect()
+    }
+
+    /// The manifest of the pathing jar: the classpath as `file:` URLs, wrapped at 72 bytes as the
+    /// jar specification requires.
+    pub fn pathing_jar_manifest(&self) -> String {
+        let class_path = self
+            .classpath
+            .iter()
+            .map(|entry| file_url(entry))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut manifest = String::from("Manifest-Version: 1.0\r\n");
+        let line = format!("Class-Path: {class_path}");
+        // the URLs are ASCII, so splitting at any byte is fine
+        let (first, mut rest) = line.split_at(line.len().min(72));
+        manifest.push_str(first);
+        manifest.push_str("\r\n");
+        while !rest.is_empty() {
+            let (chunk, remaining) = rest.split_at(rest.len().min(71));
+            manifest.push(' ');
+            manifest.push_str(chunk);
+            manifest.push_str("\r\n");
+            rest = remaining;
+        }
+        manifest.push_str("\r\n");
+        manifest
+    }
+
+    fn pathing_jar(&self) -> Result<Vec<u8>> {
+        let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+        zip.start_file("META-INF/MANIFEST.MF", FileOptions::default())?;
+        zip.write_all(self.pathing_jar_manifest().as_bytes())?;
+        Ok(zip.finish()?.into_inner())
+    }
+
+    /// Spawn the game with its output captured into a [`GameLog`], which is also written to
+    /// `logs/` in the instance directory.
+    pub async fn launch_captured(&self) -> Result<(Child, GameLog), LaunchError> {
+        let mut child = self.launch(false).await?;
+        let stdout = child.stdout.take().unwrap(); // both are piped by launch
+        let stderr = child.stderr.take().unwrap();
+        match GameLog::capture(stdout, stderr, &self.log_directory()) {
+            Ok(log) => Ok((child, log)),
+            Err(e) => {
+                let _ = child.start_kill();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Hand a game spawned from this launch over to a [`GameProcess`] supervisor, which also
+    /// runs the post-launch command and records the playtime of the instance.
+    pub fn supervise(
+        &self,
+        child: Child,
+    ) -> (GameProcess, mpsc::UnboundedReceiver<LifecycleEvent>) {
+        GameProcess::supervise_instance(
+            child,
+            &self.working_directory,
+            Some(self.hooks.clone()),
+            Some(self.instance_directory.clone()),
+        )
+    }
+
+    fn javalaunch_status_path(&self) -> PathBuf {
+        self.instance_directory.join(javalaunch::STATUS_NAME)
+    }
+
+    /// Why javalaunch failed to start the game, if it did. Call this after the game has exited;
+    /// always `None` for the `java` backend.
+    pub async fn javalaunch_failure(&self) -> Option<&'static str> {
+        let LaunchBackend::JavaLaunch { .. } = self.backend else {
+            return None;
+        };
+        let status = fs::read_to_string(self.javalaunch_status_path()).await;
+        match status.ok().as_deref().and_then(Status::decode) {
+            Some(Status::Accepted) => None,
+            Some(Status::Failed(code)) => Some(code.description()),
+            None => Some("javalaunch did not acknowledge the launch request"),
+        }
+    }
+
+    pub fn log_directory(&self) -> PathBuf {
+        self.instance_directory.join("logs")
     }
 }
 
@@ -60,6 +347,11 @@ impl PreparedLaunch {
 #[non_exhaustive]
 pub struct LaunchOptions<'a> {
     world: Option<String>,
+    server: Option<String>,
+    realm: Option<String>,
+    resolution: Option<(u32, u32)>,
+    fullscreen: bool,
+    demo: bool,
     account: Option<&'a Account>, // TODO: should this be a reference?
 }
 
@@ -68,6 +360,30 @@ impl LaunchOptions<'_> {
         Self { world, ..self }
     }
 
+    /// Server to connect to after startup, in `host` or `host:port` form.
+    pub fn server(self, server: Option<String>) -> Self {
+        Self { server, ..self }
+    }
+
+    /// ID of the realm to join after startup.
+    pub fn realm(self, realm: Option<String>) -> Self {
+        Self { realm, ..self }
+    }
+
+    /// Initial window size as `(width, height)`.
+    pub fn resolution(self, resolution: Option<(u32, u32)>) -> Self {
+        Self { resolution, ..self }
+    }
+
+    pub fn fullscreen(self, fullscreen: bool) -> Self {
+        Self { fullscreen, ..self }
+    }
+
+    /// Launch in demo mode, even if an account is given.
+    pub fn demo(self, demo: bool) -> Self {
+        Self { demo, ..self }
+    }
+
     pub fn account(self, account: Option<&Account>) -> LaunchOptions<'_> {
         LaunchOptions { account, ..self }
     }
@@ -76,6 +392,81 @@ impl LaunchOptions<'_> {
         self.world.is_some()
     }
 
+    pub fn has_server(&self) -> bool {
+        self.server.is_some()
+    }
+
+    pub fn has_realm(&self) -> bool {
+        self.realm.is_some()
+    }
+
+    pub fn is_demo(&self) -> bool {
+        self.demo || self.account.is_none()
+    }
+
+    fn check_supported(&self, traits: &BTreeSet<component::Trait>) -> Result<(), LaunchError> {
+        let quick_play = [
+            ("Launching into world", self.has_world()),
+            ("Connecting to server", self.has_server()),
+            ("Joining realm", self.has_realm()),
+        ];
+        let mut requested = quick_play
+            .iter()
+            .filter(|(_, set)| *set)
+            .map(|(name, _)| name);
+        if let (Some(first), Some(second)) = (requested.next(), requested.next()) {
+            return Err(LaunchError::IncompatibleOptions {
+                first: first.to_string(),
+                second: second.to_string(),
+            });
+        }
+
+        if self.is_demo() && self.has_realm() {
+            return Err(LaunchError::IncompatibleOptions {
+                first: String::from("Demo mode"),
+                second: String::from("Joining realm"),
+            });
+        }
+
+        let required_traits = [
+            (
+                self.has_world(),
+                component::Trait::SupportsQuickPlayWorld,
+                "Launching into world",
+            ),
+            (
+                self.has_server(),
+                component::Trait::SupportsQuickPlayServer,
+                "Connecting to server",
+            ),
+            (
+                self.has_realm(),
+                component::Trait::SupportsQuickPlayRealms,
+                "Joining realm",
+            ),
+            (
+                self.resolution.is_some(),
+                component::Trait::SupportsCustomResolution,
+                "Custom window size",
+            ),
+            // --fullscreen came with the same argument parser as --width and --height
+            (
+                self.fullscreen,
+                component::Trait::SupportsCustomResolution,
+                "Fullscreen",
+            ),
+        ];
+        for (requested, required_trait, name) in required_traits {
+            if requested && !traits.contains(&required_trait) {
+                return Err(LaunchError::UnsupportedFeature {
+                    name: String::from(name),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn account_or_default(&self) -> (String, String, String) {
         if let Some(account) = self.account {
             (
@@ -100,22 +491,13 @@ pub async fn prepare_launch(
     launch_options: LaunchOptions<'_>,
 ) -> Result<PreparedLaunch> {
     // TODO: global default config
-    let java_path = instance
-        .config
-        .launch
-        .javapath
-        .as_ref()
-        .map_or("java", |s| s)
-        .to_string(); // TODO
+    let java_path = java::select_java(config, instance, components.java_version)
+        .await
+        .map_err(LaunchError::from)?;
     let game_dir = instance.get_game_dir();
     let natives_path = instance.path.join("natives");
 
-    if launch_options.has_world() && !components.has_trait(component::Trait::SupportsQuickPlayWorld)
-    {
-        return Err(LaunchError::UnsupportedFeature {
-            name: String::from("Launching into world"),
-        })?;
-    }
+    launch_options.check_supported(&components.traits)?;
 
     // Set locale to English to make Java's String.toUpperCase/toLowerCase return predictable results if mods forgot to pass a locale
     let mut jvm_args = vec![
@@ -134,16 +516,22 @@ pub async fn prepare_launch(
         jvm_args.append(&mut instance_jvm_args.clone());
     }
 
-    let mut args = vec![];
+    let mut args: Vec<&str> = vec![];
 
     for argument in &components.arguments {
         let arg = match argument {
             MinecraftArgument::Always(arg) => arg,
             MinecraftArgument::Conditional { value, feature } => {
                 if !match feature {
-                    component::ConditionFeature::Demo => launch_options.account.is_none(),
-                    component::ConditionFeature::QuickPlayWorld => launch_options.world.is_some(),
-                    _ => false, // TODO
+                    component::ConditionFeature::Demo => launch_options.is_demo(),
+                    component::ConditionFeature::CustomResolution => {
+                        launch_options.resolution.is_some()
+                    }
+                    component::ConditionFeature::QuickPlayWorld => launch_options.has_world(),
+                    component::ConditionFeature::QuickPlayServer => launch_options.has_server(),
+                    component::ConditionFeature::QuickPlayRealms => launch_options.has_realm(),
+                    #[allow(unreachable_patterns)]
+                    _ => false,
                 } {
                     continue;
                 }
@@ -154,23 +542,54 @@ pub async fn prepare_launch(
         args.push(arg);
     }
 
+    if launch_options.fullscreen {
+        args.push("--fullscreen");
+    }
+
     let (username, uuid, token) = launch_options.account_or_default();
 
     let mut props = HashMap::new();
     props.insert("user.name", username.as_str());
     props.insert("user.uuid", uuid.as_str());
-    props.insert("user.token", token.as_str());
     props.insert("user.type", "msa");
     props.insert("instance.game_dir", game_dir.to_str().unwrap());
 
+    let mut hook_env = vec![
+        (hooks::ENV_INSTANCE_NAME, instance.config.name.clone()),
+        (
+            hooks::ENV_INSTANCE_DIR,
+            instance.path.to_str().unwrap().to_string(),
+        ),
+        (hooks::ENV_GAME_DIR, game_dir.to_str().unwrap().to_string()),
+        (hooks::ENV_ACCOUNT_NAME, username.clone()),
+    ];
+
     if let Some(minecraft_version) = instance.get_component_version("net.minecraft") {
         props.insert("instance.minecraft_version", minecraft_version);
+        hook_env.push((hooks::ENV_MINECRAFT_VERSION, minecraft_version.to_string()));
     }
 
     if let Some(world) = &launch_options.world {
         props.insert("launch.world", world);
     }
 
+    if let Some(server) = &launch_options.server {
+        props.insert("launch.server", server);
+    }
+
+    if let Some(realm) = &launch_options.realm {
+        props.insert("launch.realm", realm);
+    }
+
+    let resolution_width;
+    let resolution_height;
+    if let Some((width, height)) = launch_options.resolution {
+        resolution_width = width.to_string();
+        resolution_height = height.to_string();
+        props.insert("launch.resolution_width", &resolution_width);
+        props.insert("launch.resolution_height", &resolution_height);
+    }
+
     let paths = components.get_all(config, instance).await?;
 
     let game_jar = components.get_jar(&paths, &game_dir)?;
@@ -296,11 +715,42 @@ pub async fn prepare_launch(
         .unwrap()?; // the unwrap here triggers when the inner closure has panicked
     }
 
+    let mut java_version_unknown = false;
+    let argument_passing = if instance.config.launch.argfile.unwrap_or(false) {
+        let version = java_info(Path::new(&java_path))
+            .await
+            .map(|info| info.major_version)
+            .ok();
+        let (name, passing): (_, fn(String) -> ArgumentPassing) = match version {
+            Some(version) if version >= 9 => (ARGFILE_NAME, ArgumentPassing::Argfile),
+            _ => (PATHING_JAR_NAME, ArgumentPassing::PathingJar),
+        };
+        java_version_unknown = version.is_none();
+        let path = instance.path.join(name);
+        let path = path.to_str().ok_or_else(|| LaunchError::InvalidFilename {
+            name: path.display().to_string(),
+        })?;
+        passing(path.to_string())
+    } else {
+        ArgumentPassing::CommandLine
+    };
+
+    let use_javalaunch = instance
+        .config
+        .launch
+        .javalaunch
+        .unwrap_or(config.get_use_javalaunch());
+    let backend = if use_javalaunch {
+        LaunchBackend::javalaunch_or_java(&java_path)
+    } else {
+        LaunchBackend::Java
+    };
+
     lazy_static! {
         static ref VAR_PATTERN: Regex = Regex::new(r"\$\{([a-zA-Z0-9_.]+)\}").unwrap();
     }
 
-    Ok(PreparedLaunch {
+    let prepared = PreparedLaunch {
         java_path,
         jvm_args,
         classpath,
@@ -311,13 +761,87 @@ pub async fn prepare_launch(
                 VAR_PATTERN
                     .replace_all(arg, |captures: &Captures<'_>| {
                         println!("{captures:?}");
-                        props[captures.get(1).unwrap().as_str()]
+                        match captures.get(1).unwrap().as_str() {
+                            // filled in when spawning the game
+                            "user.token" => ACCESS_TOKEN_PLACEHOLDER,
+                            name => props[name],
+                        }
                     })
                     .into_owned()
             })
             .collect(),
+        access_token: AccessToken::new(token),
+        wrapper_commands: instance
+            .config
+            .launch
+            .wrapper_commands
+            .clone()
+            .unwrap_or_else(|| config.get_wrapper_commands().to_vec()),
+        env: instance
+            .config
+            .launch
+            .env
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect(),
+        env_remove: instance
+            .config
+            .launch
+            .env_remove
+            .clone()
+            .unwrap_or_default(),
+        argument_passing,
+        java_version_unknown,
+        backend,
+        hooks: LaunchHooks {
+            prelaunch: instance.config.launch.prelaunch_command.clone(),
+            postlaunch: instance.config.launch.postlaunch_command.clone(),
+            timeout: instance
+                .config
+                .launch
+                .hook_timeout
+                .map_or(hooks::DEFAULT_HOOK_TIMEOUT, |timeout| {
+                    Duration::from_secs(timeout.into())
+                }),
+            working_directory: instance.path.clone(),
+            env: hook_env
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        },
+        instance_directory: instance.path.clone(),
         working_directory: game_dir,
-    })
+    };
+
+    match &prepared.argument_passing {
+        ArgumentPassing::CommandLine => {}
+        ArgumentPassing::Argfile(path) => fs::write(path, prepared.argfile_contents()).await?,
+        ArgumentPassing::PathingJar(path) => fs::write(path, prepared.pathing_jar()?).await?,
+    }
+
+    Ok(prepared)
+}
+
+/// The `file:` URL of an absolute path, as used in a manifest `Class-Path`.
+fn file_url(path: &str) -> String {
+    let mut url = String::from("file:");
+    if cfg!(windows) {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'\\' if cfg!(windows) => url.push('/'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    if std::path::Path::new(path).is_dir() {
+        url.push('/');
+    }
+    url
 }
 
 /*pub async fn mc_version_exists(version: String) -> bool {
@@ -331,3 +855,179 @@ pub async fn prepare_launch(
     }
     found
 }*/
+
+#[cfg(test)]
+mod tests {
+    use super::super::CLASSPATH_SEPARATOR;
+    use super::*;
+
+    fn prepared() -> PreparedLaunch {
+        PreparedLaunch {
+            instance_directory: PathBuf::from("instance"),
+            working_directory: PathBuf::from("instance/.minecraft"),
+            java_path: String::from("java"),
+            jvm_args: vec![String::from("-Xmx2G")],
+            classpath: vec![String::from("a.jar")],
+            main_class: String::from("net.minecraft.client.main.Main"),
+            args: vec![String::from("--demo")],
+            access_token: AccessToken::default(),
+            wrapper_commands: vec![],
+            env: vec![],
+            env_remove: vec![],
+            argument_passing: ArgumentPassing::CommandLine,
+            java_version_unknown: false,
+            backend: LaunchBackend::Java,
+            hooks: LaunchHooks {
+                prelaunch: None,
+                postlaunch: None,
+                timeout: hooks::DEFAULT_HOOK_TIMEOUT,
+                working_directory: PathBuf::from("instance"),
+                env: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn supported_options() {
+        let traits = BTreeSet::from([component::Trait::SupportsCustomResolution]);
+        let unsupported =
+            |options: LaunchOptions<'_>| match options.check_supported(&BTreeSet::new()) {
+                Err(LaunchError::UnsupportedFeature { name }) => name,
+                result => panic!("{result:?}"),
+            };
+        let fullscreen = LaunchOptions::default().fullscreen(true);
+        assert!(fullscreen.check_supported(&traits).is_ok());
+        assert_eq!(unsupported(fullscreen), "Fullscreen");
+        assert_eq!(
+            unsupported(LaunchOptions::default().resolution(Some((854, 480)))),
+            "Custom window size"
+        );
+        assert_eq!(
+            unsupported(LaunchOptions::default().world(Some(String::from("World")))),
+            "Launching into world"
+        );
+        assert!(LaunchOptions::default()
+            .check_supported(&BTreeSet::new())
+            .is_ok());
+    }
+
+    #[test]
+    fn wrapper_commands() {
+        let prepared = PreparedLaunch {
+            wrapper_commands: vec![
+                vec![String::from("gamemoderun")],
+                vec![],
+                vec![String::from("mangohud"), String::from("--dlsym")],
+            ],
+            env: vec![(String::from("MANGOHUD"), String::from("1"))],
+            env_remove: vec![String::from("_JAVA_OPTIONS")],
+            ..prepared()
+        };
+        let command = prepared.command();
+        let command = command.as_std();
+        assert_eq!(command.get_program(), "gamemoderun");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            [
+                "mangohud",
+                "--dlsym",
+                "java",
+                "-Xmx2G",
+                "-classpath",
+                "a.jar",
+                "net.minecraft.client.main.Main",
+                "--demo"
+            ]
+        );
+        let envs = command.get_envs().collect::<HashMap<_, _>>();
+        assert_eq!(envs[std::ffi::OsStr::new("MANGOHUD")], Some("1".as_ref()));
+        assert_eq!(envs[std::ffi::OsStr::new("_JAVA_OPTIONS")], None);
+    }
+
+    #[test]
+    fn argfile() {
+        let prepared = PreparedLaunch {
+            jvm_args: vec![String::from("-Dpath=C:\\Games \"Minecraft\"")],
+            classpath: vec![String::from("a.jar"), String::from("b.jar")],
+            argument_passing: ArgumentPassing::Argfile(String::from("instance/launch.args")),
+            ..prepared()
+        };
+        assert_eq!(
+            prepared.argfile_contents(),
+            format!(
+                "\"-Dpath=C:\\\\Games \\\"Minecraft\\\"\"\n\"-classpath\"\n\"a.jar{CLASSPATH_SEPARATOR}b.jar\"\n"
+            )
+        );
+        let command = prepared.command();
+        assert_eq!(
+            command.as_std().get_args().collect::<Vec<_>>(),
+            [
+                "@instance/launch.args",
+                "net.minecraft.client.main.Main",
+                "--demo"
+            ]
+        );
+    }
+
+    #[test]
+    fn pathing_jar() {
+        let long = format!("/games/{}/client.jar", "a".repeat(80));
+        let prepared = PreparedLaunch {
+            jvm_args: vec![String::from("-Xmx2G")],
+            classpath: vec![String::from("/games/lib folder/a.jar"), long.clone()],
+            argument_passing: ArgumentPassing::PathingJar(String::from("instance/classpath.jar")),
+            ..prepared()
+        };
+        let manifest = prepared.pathing_jar_manifest();
+        assert!(manifest.lines().all(|line| line.len() <= 72));
+        let class_path = manifest
+            .trim_start_matches("Manifest-Version: 1.0\r\nClass-Path: ")
+            .replace("\r\n ", "");
+        if cfg!(windows) {
+            assert!(class_path.starts_with("file://games/lib%20folder/a.jar file://games/"));
+        } else {
+            assert_eq!(
+                class_path,
+                format!("file:/games/lib%20folder/a.jar file:{long}\r\n\r\n")
+            );
+        }
+        let command = prepared.command();
+        assert_eq!(
+            command.as_std().get_args().collect::<Vec<_>>(),
+            [
+                "-Xmx2G",
+                "-classpath",
+                "instance/classpath.jar",
+                "net.minecraft.client.main.Main",
+                "--demo"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn javalaunch_failure() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let prepared = PreparedLaunch {
+            instance_directory: dir.path().to_path_buf(),
+            backend: LaunchBackend::JavaLaunch {
+                executable: PathBuf::from("helixlauncher-javalaunch"),
+                jli: PathBuf::from("libjli.so"),
+            },
+            ..prepared()
+        };
+        let status_path = dir.path().join(javalaunch::STATUS_NAME);
+        assert_eq!(
+            prepared.javalaunch_failure().await,
+            Some("javalaunch did not acknowledge the launch request")
+        );
+        fs::write(&status_path, Status::Accepted.encode()).await?;
+        assert_eq!(prepared.javalaunch_failure().await, None);
+        let code = javalaunch::ErrorCode::LibraryLoad;
+        fs::write(&status_path, Status::Failed(code).encode()).await?;
+        assert_eq!(
+            prepared.javalaunch_failure().await,
+            Some(code.description())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,463 @@
+//! Supervision of a running game process.
+//!
+//! [`GameProcess::supervise`] takes ownership of a spawned game and watches it from a background
+//! task. The returned handle can be cloned freely to query the state or stop the game, while
+//! [`LifecycleEvent`]s are delivered through a separate receiver.
+
+use std::{
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::{Duration, SystemTime},
+};
+
+use super::{hooks::LaunchHooks, instance::Instance};
+use tokio::{
+    process::Child,
+    sync::{mpsc, watch},
+    time::{self, Instant},
+};
+
+/// Exit code used by the JVM when `-XX:+ExitOnOutOfMemoryError` is set.
+const JVM_OOM_EXIT_CODE: i32 = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ExitKind {
+    Normal,
+    Crash,
+    /// Stopped through [`GameProcess::stop`]/[`GameProcess::kill`], or by a signal asking it to
+    /// stop, e.g. `SIGTERM`.
+    Killed,
+    OutOfMemory,
+}
+
+impl std::fmt::Display for ExitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Normal => "exited normally",
+            Self::Crash => "crashed",
+            Self::Killed => "was killed",
+            Self::OutOfMemory => "ran out of memory",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameExit {
+    pub kind: ExitKind,
+    pub code: Option<i32>,
+    /// The signal that terminated the process. Always `None` on non-Unix platforms.
+    pub signal: Option<i32>,
+    /// The newest crash report written to `crash-reports/` while the game was running.
+    pub crash_report: Option<PathBuf>,
+    pub started_at: SystemTime,
+    pub exited_at: SystemTime,
+    /// Set if the post-launch command failed.
+    pub postlaunch_error: Option<String>,
+    /// Set if the playtime could not be added to the instance.
+    pub playtime_error: Option<String>,
+}
+
+impl GameExit {
+    pub fn duration(&self) -> Duration {
+        self.exited_at
+            .duration_since(self.started_at)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ProcessState {
+    Running,
+    Stopping,
+    Exited(GameExit),
+}
+
+#[derive(Clone, Debug)]
+pub enum LifecycleEvent {
+    Started {
+        pid: Option<u32>,
+        started_at: SystemTime,
+    },
+    /// A stop was requested. `forced` is set once the process is killed instead of being asked
+    /// to terminate.
+    Stopping {
+        forced: bool,
+    },
+    Exited(GameExit),
+}
+
+enum Control {
+    Stop(Duration),
+    Kill,
+}
+
+/// What the supervisor does after the game exited, before reporting the exit
+struct ExitTasks {
+    hooks: Option<LaunchHooks>,
+    /// The instance to add the playtime to
+    instance_dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GameProcess {
+    pid: Option<u32>,
+    started_at: SystemTime,
+    control: mpsc::UnboundedSender<Control>,
+    state: watch::Receiver<ProcessState>,
+}
+
+impl GameProcess {
+    /// Start supervising `child`. `game_dir` is searched for crash reports once it exits.
+    ///
+    /// Must be called from within a Tokio runtime, which needs to be kept alive until the game
+    /// exits.
+    pub fn supervise(
+        child: Child,
+        game_dir: &Path,
+    ) -> (Self, mpsc::UnboundedReceiver<LifecycleEvent>) {
+        Self::supervise_with_hooks(child, game_dir, None)
+    }
+
+    /// Like [`supervise`](Self::supervise), but also runs the post-launch command of `hooks`
+    /// after the game exited. The exit is only reported once the command finished.
+    pub fn supervise_with_hooks(
+        child: Child,
+        game_dir: &Path,
+        hooks: Option<LaunchHooks>,
+    ) -> (Self, mpsc::UnboundedReceiver<LifecycleEvent>) {
+        Self::supervise_instance(child, game_dir, hooks, None)
+    }
+
+    /// Like [`supervise_with_hooks`](Self::supervise_with_hooks), but also adds the time the
+    /// game ran to the playtime of the instance in `instance_dir` once it exits.
+    pub(crate) fn supervise_instance(
+        child: Child,
+        game_dir: &Path,
+        hooks: Option<LaunchHooks>,
+        instance_dir: Option<PathBuf>,
+    ) -> (Self, mpsc::UnboundedReceiver<LifecycleEvent>) {
+        let pid = child.id();
+        let started_at = SystemTime::now();
+        let (control, control_receiver) = mpsc::unbounded_channel();
+        let (state_sender, state) = watch::channel(ProcessState::Running);
+        let (events, event_receiver) = mpsc::unbounded_channel();
+
+        // the receiver is still in scope, this can't fail
+        events
+            .send(LifecycleEvent::Started { pid, started_at })
+            .unwrap();
+
+        tokio::spawn(supervise(
+            child,
+            game_dir.to_path_buf(),
+            ExitTasks {
+                hooks,
+                instance_dir,
+            },
+            started_at,
+            control_receiver,
+            state_sender,
+            events,
+        ));
+
+        (
+            Self {
+                pid,
+                started_at,
+                control,
+                state,
+            },
+            event_receiver,
+        )
+    }
+
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    pub fn state(&self) -> ProcessState {
+        self.state.borrow().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !matches!(*self.state.borrow(), ProcessState::Exited(_))
+    }
+
+    /// Ask the game to terminate, killing it if it is still running after `timeout`.
+    pub fn stop(&self, timeout: Duration) {
+        // if the supervisor is gone, the game has exited already
+        let _ = self.control.send(Control::Stop(timeout));
+    }
+
+    /// Kill the game immediately.
+    pub fn kill(&self) {
+        let _ = self.control.send(Control::Kill);
+    }
+
+    /// Wait for the game to exit.
+    pub async fn wait(&self) -> GameExit {
+        let mut state = self.state.clone();
+        loop {
+            if let ProcessState::Exited(exit) = &*state.borrow_and_update() {
+                return exit.clone();
+            }
+            if state.changed().await.is_err() {
+                // the supervisor always publishes the exit before going away
+                if let ProcessState::Exited(exit) = &*state.borrow() {
+                    return exit.clone();
+                }
+                unreachable!("game supervisor stopped without an exit status");
+            }
+        }
+    }
+}
+
+async fn supervise(
+    mut child: Child,
+    game_dir: PathBuf,
+    exit_tasks: ExitTasks,
+    started_at: SystemTime,
+    mut control: mpsc::UnboundedReceiver<Control>,
+    state: watch::Sender<ProcessState>,
+    events: mpsc::UnboundedSender<LifecycleEvent>,
+) {
+    let pid = child.id();
+    let mut stop_requested = false;
+    let mut kill_deadline = None;
+
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status,
+            Some(command) = control.recv() => {
+                stop_requested = true;
+                state.send_replace(ProcessState::Stopping);
+                match command {
+                    Control::Stop(timeout) => {
+                        let _ = events.send(LifecycleEvent::Stopping { forced: false });
+                        if let Some(pid) = pid {
+                            terminate(pid);
+                        }
+                        kill_deadline = Some(Instant::now() + timeout);
+                    }
+                    Control::Kill => {
+                        let _ = events.send(LifecycleEvent::Stopping { forced: true });
+                        let _ = child.start_kill();
+                    }
+                }
+            }
+            _ = time::sleep_until(kill_deadline.unwrap_or_else(Instant::now)), if kill_deadline.is_some() => {
+                kill_deadline = None;
+                let _ = events.send(LifecycleEvent::Stopping { forced: true });
+                let _ = child.start_kill();
+            }
+        }
+    };
+
+    let exited_at = SystemTime::now();
+    let crash_report = find_crash_report(&game_dir, started_at).ok().flatten();
+    let mut exit = classify_exit(status, stop_requested, crash_report, started_at, exited_at);
+
+    if let Some(hooks) = exit_tasks.hooks {
+        if let Err(e) = hooks.run_postlaunch(exit.code).await {
+            exit.postlaunch_error = Some(e.to_string());
+        }
+    }
+
+    if let Some(instance_dir) = exit_tasks.instance_dir {
+        // read the config again, it may have been changed while the game was running
+        if let Err(e) = Instance::from_path(instance_dir)
+            .and_then(|mut instance| instance.record_session(started_at, exited_at))
+        {
+            exit.playtime_error = Some(e.to_string());
+        }
+    }
+
+    state.send_replace(ProcessState::Exited(exit.clone()));
+    let _ = events.send(LifecycleEvent::Exited(exit));
+}
+
+fn classify_exit(
+    status: io::Result<ExitStatus>,
+    stop_requested: bool,
+    crash_report: Option<PathBuf>,
+    started_at: SystemTime,
+    exited_at: SystemTime,
+) -> GameExit {
+    let (code, signal) = match &status {
+        Ok(status) => (status.code(), exit_signal(status)),
+        Err(_) => (None, None),
+    };
+
+    let out_of_memory = code == Some(JVM_OOM_EXIT_CODE)
+        || crash_report.as_deref().is_some_and(mentions_out_of_memory);
+
+    let kind = if stop_requested || signal.is_some_and(is_termination_signal) {
+        ExitKind::Killed
+    } else if code == Some(0) {
+        ExitKind::Normal
+    } else if out_of_memory {
+        ExitKind::OutOfMemory
+    } else {
+        ExitKind::Crash
+    };
+
+    GameExit {
+        kind,
+        code,
+        signal,
+        crash_report,
+        started_at,
+        exited_at,
+        postlaunch_error: None,
+        playtime_error: None,
+    }
+}
+
+fn mentions_out_of_memory(report: &Path) -> bool {
+    fs::read(report)
+        .is_ok_and(|report| String::from_utf8_lossy(&report).contains("java.lang.OutOfMemoryError"))
+}
+
+/// Find the newest crash report in `game_dir` that was modified after `since`.
+pub fn find_crash_report(game_dir: &Path, since: SystemTime) -> io::Result<Option<PathBuf>> {
+    let entries = match fs::read_dir(game_dir.join("crash-reports")) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        r => r,
+    }?;
+
+    let mut newest = None;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension() != Some(OsStr::new("txt")) {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if modified < since {
+            continue;
+        }
+        match &newest {
+            Some((newest_modified, _)) if *newest_modified >= modified => {}
+            _ => newest = Some((modified, path)),
+        }
+    }
+    Ok(newest.map(|(_, path)| path))
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Whether `signal` asks a process to stop, as opposed to signals like `SIGABRT` or `SIGSEGV`
+/// the JVM dies of when it crashes.
+#[cfg(unix)]
+fn is_termination_signal(signal: i32) -> bool {
+    matches!(
+        signal,
+        libc::SIGKILL | libc::SIGTERM | libc::SIGINT | libc::SIGHUP
+    )
+}
+
+#[cfg(not(unix))]
+fn is_termination_signal(_signal: i32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) {
+    // SAFETY: kill has no memory safety requirements. The pid can't have been reused yet, as the
+    // child hasn't been reaped.
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGTERM);
+    }
+}
+
+#[cfg(windows)]
+fn terminate(pid: u32) {
+    // without /F, taskkill asks the game window to close
+    let _ = std::process::Command::new("taskkill")
+        .arg("/PID")
+        .arg(pid.to_string())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn exit_status(code: i32) -> ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classify_exit_codes() {
+        let now = SystemTime::now();
+        let classify = |code, stop_requested| {
+            classify_exit(Ok(exit_status(code)), stop_requested, None, now, now).kind
+        };
+        assert_eq!(classify(0, false), ExitKind::Normal);
+        assert_eq!(classify(1, false), ExitKind::Crash);
+        assert_eq!(classify(JVM_OOM_EXIT_CODE, false), ExitKind::OutOfMemory);
+        assert_eq!(classify(0, true), ExitKind::Killed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classify_exit_signals() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let now = SystemTime::now();
+        let classify = |signal, stop_requested| {
+            let status = ExitStatus::from_raw(signal);
+            classify_exit(Ok(status), stop_requested, None, now, now).kind
+        };
+        assert_eq!(classify(libc::SIGTERM, false), ExitKind::Killed);
+        assert_eq!(classify(libc::SIGKILL, false), ExitKind::Killed);
+        assert_eq!(classify(libc::SIGINT, false), ExitKind::Killed);
+        // fatal JVM errors and crashes in natives
+        assert_eq!(classify(libc::SIGABRT, false), ExitKind::Crash);
+        assert_eq!(classify(libc::SIGSEGV, false), ExitKind::Crash);
+        assert_eq!(classify(libc::SIGSEGV, true), ExitKind::Killed);
+    }
+
+    #[test]
+    fn find_newest_crash_report() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(find_crash_report(dir.path(), SystemTime::UNIX_EPOCH)?, None);
+
+        let reports = dir.path().join("crash-reports");
+        fs::create_dir(&reports)?;
+        fs::write(reports.join("crash-old-client.txt"), "old")?;
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(reports.join("crash-new-client.txt"), "new")?;
+        fs::write(reports.join("notes.md"), "not a report")?;
+
+        assert_eq!(
+            find_crash_report(dir.path(), SystemTime::UNIX_EPOCH)?,
+            Some(reports.join("crash-new-client.txt"))
+        );
+        assert_eq!(
+            find_crash_report(dir.path(), SystemTime::now() + Duration::from_secs(60))?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,501 @@
+//! Java runtimes managed by the launcher.
+//!
+//! Runtimes are downloaded from an Adoptium-style API (see [`Config::get_java_runtime_api`]) and
+//! unpacked into the Java directory of the data dir, one directory per major version, e.g.
+//! `java/java-21`. [`select_java`](super::java::select_java) searches that directory, so managed
+//! runtimes are picked like any other install. Downloads are unpacked in a staging directory next
+//! to it first, so a half-unpacked runtime is never found.
+
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use helixlauncher_java::info::Architecture;
+use helixlauncher_meta::component::Hash;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{fs, task};
+
+use crate::{
+    config::{self, Config},
+    fsutil::check_path,
+};
+
+use super::download_file;
+
+/// Describes the runtime, inside its directory
+const RUNTIME_INFO_NAME: &str = "runtime.helix.json";
+/// Downloads and unpacking in progress, in the data dir
+const STAGING_DIR: &str = ".java-staging";
+const DOWNLOADS_DIR: &str = "downloads";
+const IMAGE_TYPE: &str = "jre";
+
+#[derive(Error, Debug)]
+pub enum RuntimeError {
+    #[error("No Java {major_version} runtime is available for this platform")]
+    NotAvailable { major_version: u32 },
+    #[error("Java {major_version} is not installed")]
+    NotInstalled { major_version: u32 },
+    #[error("Invalid filename found: {name}")]
+    InvalidFilename { name: String },
+    #[error("Download failed: {0}")]
+    Download(anyhow::Error),
+    #[error(transparent)]
+    ReqwestError(#[from] reqwest::Error),
+    #[error(transparent)]
+    ParseError(#[from] serde_json::Error),
+    #[error(transparent)]
+    ZipError(#[from] zip::result::ZipError),
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManagedRuntime {
+    pub major_version: u32,
+    /// The name of the release, e.g. `jdk-21.0.2+13`
+    pub release: String,
+    /// The directory the runtime is unpacked to
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize, Debug)]
+struct RuntimeAsset {
+    binary: RuntimeBinary,
+    release_name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RuntimeBinary {
+    package: RuntimePackage,
+}
+
+#[derive(Deserialize, Debug)]
+struct RuntimePackage {
+    /// SHA-256 of the archive
+    checksum: String,
+    link: String,
+    name: String,
+    size: u32,
+}
+
+pub struct RuntimeManager<'a> {
+    client: reqwest::Client,
+    config: &'a Config,
+}
+
+impl<'a> RuntimeManager<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// List the installed runtimes, ordered by major version.
+    pub async fn list(&self) -> Result<Vec<ManagedRuntime>, RuntimeError> {
+        let mut entries = match fs::read_dir(self.config.get_java_path()).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            r => r,
+        }?;
+        let mut runtimes = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            // unfinished downloads and directories not made by us are skipped
+            if let Some(runtime) = read_runtime(&entry.path()).await {
+                runtimes.push(runtime);
+            }
+        }
+        runtimes.sort_by_key(|runtime| runtime.major_version);
+        Ok(runtimes)
+    }
+
+    pub async fn get(&self, major_version: u32) -> Option<ManagedRuntime> {
+        read_runtime(&self.runtime_path(major_version)).await
+    }
+
+    /// Download the latest runtime for `major_version`, unless it is installed already.
+    pub async fn install(&self, major_version: u32) -> Result<ManagedRuntime, RuntimeError> {
+        if let Some(runtime) = self.get(major_version).await {
+            return Ok(runtime);
+        }
+        let asset = self.latest(major_version).await?;
+        self.download(major_version, asset).await
+    }
+
+    /// Replace the runtime for `major_version` with the latest release. Returns the new runtime,
+    /// or `None` if it was up to date.
+    pub async fn update(&self, major_version: u32) -> Result<Option<ManagedRuntime>, RuntimeError> {
+        let installed = self
+            .get(major_version)
+            .await
+            .ok_or(RuntimeError::NotInstalled { major_version })?;
+        let asset = self.latest(major_version).await?;
+        if asset.release_name == installed.release {
+            return Ok(None);
+        }
+        Ok(Some(self.download(major_version, asset).await?))
+    }
+
+    pub async fn remove(&self, major_version: u32) -> Result<(), RuntimeError> {
+        let path = self.runtime_path(major_version);
+        if read_runtime(&path).await.is_none() {
+            return Err(RuntimeError::NotInstalled { major_version });
+        }
+        fs::remove_dir_all(path).await?;
+        Ok(())
+    }
+
+    fn runtime_path(&self, major_version: u32) -> PathBuf {
+        self.config
+            .get_java_path()
+            .join(format!("java-{major_version}"))
+    }
+
+    async fn latest(&self, major_version: u32) -> Result<RuntimeAsset, RuntimeError> {
+        let Some(architecture) = api_architecture(&Architecture::current()) else {
+            return Err(RuntimeError::NotAvailable { major_version });
+        };
+        let url = self
+            .config
+            .get_java_runtime_api()
+            .map_or_else(config::java_runtime_api_default, String::from);
+        let assets: Vec<RuntimeAsset> = self
+            .client
+            .get(format!("{url}v3/assets/latest/{major_version}/hotspot"))
+            .query(&[
+                ("architecture", architecture),
+                ("image_type", IMAGE_TYPE),
+                ("os", api_os()),
+                ("vendor", "eclipse"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        assets
+            .into_iter()
+            .next()
+            .ok_or(RuntimeError::NotAvailable { major_version })
+    }
+
+    async fn download(
+        &self,
+        major_version: u32,
+        asset: RuntimeAsset,
+    ) -> Result<ManagedRuntime, RuntimeError> {
+        let package = asset.binary.package;
+        if !check_path(&package.name) {
+            return Err(RuntimeError::InvalidFilename { name: package.name });
+        }
+        let java_path = self.config.get_java_path();
+        let staging_path = self.config.get_base_path().join(STAGING_DIR);
+        let archive = staging_path.join(DOWNLOADS_DIR).join(&package.name);
+        download_file(
+            &self.client,
+            &archive,
+            &package.link,
+            package.size,
+            &Hash::SHA256(package.checksum),
+        )
+        .await
+        .map_err(RuntimeError::Download)?;
+
+        // unpack next to the old version first, so a failed update leaves it intact
+        let path = self.runtime_path(major_version);
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        let staging = staging_path.join(&name);
+        if fs::try_exists(&staging).await? {
+            fs::remove_dir_all(&staging).await?;
+        }
+        let unpacked = {
+            let archive = archive.clone();
+            let staging = staging.clone();
+            task::spawn_blocking(move || unpack(&archive, &staging))
+                .await
+                .unwrap()?
+        };
+        let runtime = ManagedRuntime {
+            major_version,
+            release: asset.release_name,
+            path,
+        };
+        fs::write(
+            unpacked.join(RUNTIME_INFO_NAME),
+            serde_json::to_vec_pretty(&runtime)?,
+        )
+        .await?;
+
+        if fs::try_exists(&runtime.path).await? {
+            let old = staging_path.join(format!("{name}.old"));
+            if fs::try_exists(&old).await? {
+                fs::remove_dir_all(&old).await?;
+            }
+            fs::rename(&runtime.path, &old).await?;
+            fs::rename(&unpacked, &runtime.path).await?;
+            fs::remove_dir_all(old).await?;
+        } else {
+            fs::create_dir_all(&java_path).await?;
+            fs::rename(&unpacked, &runtime.path).await?;
+        }
+        fs::remove_dir_all(staging).await?;
+        fs::remove_file(archive).await?;
+        Ok(runtime)
+    }
+}
+
+async fn read_runtime(path: &Path) -> Option<ManagedRuntime> {
+    let info = fs::read(path.join(RUNTIME_INFO_NAME)).await.ok()?;
+    let runtime: ManagedRuntime = serde_json::from_slice(&info).ok()?;
+    Some(ManagedRuntime {
+        path: path.to_path_buf(),
+        ..runtime
+    })
+}
+
+fn api_os() -> &'static str {
+    if cfg!(windows) {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "mac"
+    } else {
+        "linux"
+    }
+}
+
+fn api_architecture(architecture: &Architecture) -> Option<&'static str> {
+    match architecture {
+        Architecture::X86 => Some("x86"),
+        Architecture::X86_64 => Some("x64"),
+        Architecture::Arm => Some("arm"),
+        Architecture::Aarch64 => Some("aarch64"),
+        Architecture::Other(_) => None,
+    }
+}
+
+/// Unpack a `.zip` or `.tar.gz` runtime archive into `destination`. Returns the top-level
+/// directory all runtimes are packed in, e.g. `jdk-21.0.2+13-jre`.
+fn unpack(archive: &Path, destination: &Path) -> Result<PathBuf, RuntimeError> {
+    let file = File::open(archive)?;
+    std::fs::create_dir_all(destination)?;
+    let mut root = None;
+    if archive
+        .extension()
+        .is_some_and(|extension| extension == "zip")
+    {
+        let mut zip = zip::ZipArchive::new(file)?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+            let path = entry_path(Path::new(entry.name()), destination, &mut root)?;
+            if entry.is_dir() {
+                continue;
+            }
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            io::copy(&mut entry, &mut File::create(path)?)?;
+        }
+    } else {
+        let mut tar = tar::Archive::new(GzDecoder::new(file));
+        tar.set_preserve_permissions(true);
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            entry_path(&entry.path()?, destination, &mut root)?;
+            // refuses to write through symlinks leading out of the destination
+            entry.unpack_in(destination)?;
+        }
+    }
+    root.map(|root| destination.join(root))
+        .ok_or_else(|| RuntimeError::InvalidFilename {
+            name: archive.to_string_lossy().into_owned(),
+        })
+}
+
+/// Where an archive entry goes. All entries have to be in the same top-level directory, `root`.
+fn entry_path(
+    name: &Path,
+    destination: &Path,
+    root: &mut Option<PathBuf>,
+) -> Result<PathBuf, RuntimeError> {
+    let invalid = || RuntimeError::InvalidFilename {
+        name: name.to_string_lossy().into_owned(),
+    };
+    let name_str = name.to_str().ok_or_else(invalid)?;
+    if !check_path(name_str) {
+        return Err(invalid());
+    }
+    let top = PathBuf::from(name.components().next().ok_or_else(invalid)?.as_os_str());
+    match root {
+        Some(root) if *root != top => return Err(invalid()),
+        Some(_) => {}
+        None => *root = Some(top),
+    }
+    Ok(destination.join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest;
+    use flate2::{write::GzEncoder, Compression};
+    use helixlauncher_java::search::search_java_in;
+    use hex::ToHex;
+
+    use super::*;
+    use crate::testutil::{serve, Files};
+
+    fn runtime_archive(release: &str, version: &str) -> io::Result<Vec<u8>> {
+        let mut tar = tar::Builder::new(GzEncoder::new(vec![], Compression::fast()));
+        let mut add = |path: String, contents: &[u8], mode| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(mode);
+            tar.append_data(&mut header, path, contents)
+        };
+        add(format!("{release}-jre/bin/java"), b"", 0o755)?;
+        add(
+            format!("{release}-jre/release"),
+            format!(
+                "JAVA_VERSION=\"{version}\"\nOS_ARCH=\"{}\"\n",
+                std::env::consts::ARCH
+            )
+            .as_bytes(),
+            0o644,
+        )?;
+        tar.into_inner()?.finish()
+    }
+
+    /// Publish `release` as the latest Java 17 runtime.
+    fn publish(files: &Files, url: &str, release: &str, version: &str, checksum: Option<&str>) {
+        let archive = runtime_archive(release, version).unwrap();
+        let name = format!("{release}.tar.gz");
+        let asset = serde_json::json!([{
+            "binary": {
+                "package": {
+                    "checksum": checksum.map_or_else(
+                        || sha2::Sha256::digest(&archive).encode_hex::<String>(),
+                        String::from,
+                    ),
+                    "link": format!("{url}{name}"),
+                    "name": name,
+                    "size": archive.len(),
+                },
+            },
+            "release_name": release,
+        }]);
+        let mut files = files.lock().unwrap();
+        files.insert(
+            String::from("/v3/assets/latest/17/hotspot"),
+            serde_json::to_vec(&asset).unwrap(),
+        );
+        files.insert(format!("/{name}"), archive);
+    }
+
+    #[tokio::test]
+    async fn install_update_remove() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let files = Files::default();
+        let url = serve(files.clone()).await?;
+        let mut config = Config::new_with_data_dir(
+            "dev.helixlauncher.HelixLauncher",
+            "HelixLauncher",
+            dir.path().join("data"),
+        )?;
+        config.set_java_runtime_api(Some(url.clone()));
+        let manager = RuntimeManager::new(&config);
+
+        assert!(matches!(
+            manager.install(17).await,
+            Err(RuntimeError::ReqwestError(_))
+        ));
+
+        publish(&files, &url, "jdk-17.0.9+9", "17.0.9", None);
+        let runtime = manager.install(17).await?;
+        assert_eq!(runtime.release, "jdk-17.0.9+9");
+        assert_eq!(manager.list().await?, vec![runtime.clone()]);
+        let java = runtime.path.join("bin/java").canonicalize()?;
+        assert!(search_java_in(&[config.get_java_path()])?.contains(&java));
+        // nothing is left behind in the searched directory
+        assert_eq!(std::fs::read_dir(config.get_java_path())?.count(), 1);
+        assert_eq!(manager.update(17).await?, None);
+
+        // a corrupted download keeps the installed version
+        publish(&files, &url, "jdk-17.0.10+7", "17.0.10", Some("00"));
+        assert!(matches!(
+            manager.update(17).await,
+            Err(RuntimeError::Download(_))
+        ));
+        assert_eq!(manager.get(17).await, Some(runtime));
+
+        publish(&files, &url, "jdk-17.0.10+7", "17.0.10", None);
+        let updated = manager.update(17).await?.unwrap();
+        assert_eq!(updated.release, "jdk-17.0.10+7");
+        assert_eq!(
+            std::fs::read_to_string(updated.path.join("release"))?,
+            format!(
+                "JAVA_VERSION=\"17.0.10\"\nOS_ARCH=\"{}\"\n",
+                std::env::consts::ARCH
+            )
+        );
+
+        manager.remove(17).await?;
+        assert_eq!(manager.list().await?, vec![]);
+        assert!(matches!(
+            manager.remove(17).await,
+            Err(RuntimeError::NotInstalled { major_version: 17 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn entry_paths() -> Result<(), RuntimeError> {
+        let destination = Path::new("/data/.java-staging/java-21");
+        let mut root = None;
+        assert_eq!(
+            entry_path(Path::new("jdk-21.0.2+13-jre/"), destination, &mut root)?,
+            destination.join("jdk-21.0.2+13-jre")
+        );
+        assert_eq!(
+            entry_path(
+                Path::new("jdk-21.0.2+13-jre/bin/java"),
+                destination,
+                &mut root
+            )?,
+            destination.join("jdk-21.0.2+13-jre/bin/java")
+        );
+        assert_eq!(root, Some(PathBuf::from("jdk-21.0.2+13-jre")));
+        assert!(entry_path(
+            Path::new("jdk-21.0.2+13-jre/../../evil"),
+            destination,
+            &mut root
+        )
+        .is_err());
+        assert!(entry_path(Path::new("other/bin/java"), destination, &mut root).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_through_symlink() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let outside = dir.path().join("outside");
+        std::fs::create_dir(&outside)?;
+
+        let mut tar = tar::Builder::new(GzEncoder::new(vec![], Compression::fast()));
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        tar.append_link(&mut header, "jdk-17-jre/lib", &outside)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "jdk-17-jre/lib/evil", &b"evil"[..])?;
+        let archive = dir.path().join("jdk-17.tar.gz");
+        std::fs::write(&archive, tar.into_inner()?.finish()?)?;
+
+        assert!(unpack(&archive, &dir.path().join("java-17")).is_err());
+        assert!(!outside.join("evil").exists());
+        Ok(())
+    }
+}
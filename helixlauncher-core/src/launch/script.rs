@@ -0,0 +1,246 @@
+//! Export of a [`PreparedLaunch`] as a standalone launch script.
+//!
+//! The script reproduces the game invocation, including wrapper commands and environment changes,
+//! but not the pre-launch and post-launch commands. It always runs plain `java`, even if the launch
+//! uses javalaunch, and passes the JVM options and classpath on the command line, even if the
+//! launch uses an argfile or a pathing jar. The access token is never written to the script; it is
+//! read from the `HELIX_ACCESS_TOKEN` environment variable instead.
+
+use std::{ffi::OsStr, io, path::Path};
+
+use tokio::fs;
+
+use super::prepared::{PreparedLaunch, ACCESS_TOKEN_PLACEHOLDER};
+
+pub const ACCESS_TOKEN_VAR: &str = "HELIX_ACCESS_TOKEN";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScriptKind {
+    /// POSIX shell script
+    Shell,
+    /// Windows batch file
+    Batch,
+}
+
+impl ScriptKind {
+    /// Batch for `.bat` and `.cmd` files, shell otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(OsStr::to_str) {
+            Some(extension)
+                if extension.eq_ignore_ascii_case("bat")
+                    || extension.eq_ignore_ascii_case("cmd") =>
+            {
+                Self::Batch
+            }
+            _ => Self::Shell,
+        }
+    }
+}
+
+impl PreparedLaunch {
+    pub fn launch_script(&self, kind: ScriptKind) -> String {
+        match kind {
+            ScriptKind::Shell => self.shell_script(),
+            ScriptKind::Batch => self.batch_script(),
+        }
+    }
+
+    /// Write the launch script to `path`. Shell scripts are made executable.
+    pub async fn export_script(&self, path: &Path, kind: ScriptKind) -> io::Result<()> {
+        fs::write(path, self.launch_script(kind)).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if kind == ScriptKind::Shell {
+                fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn shell_script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\n");
+        script += &format!("# Reads the access token from ${ACCESS_TOKEN_VAR}\n");
+        script += &format!(
+            "cd {} || exit 1\n",
+            shell_quote(self.working_directory.to_str().unwrap())
+        );
+        for key in &self.env_remove {
+            script += &format!("unset {key}\n");
+        }
+        for (key, value) in self.env_vars() {
+            script += &format!("export {key}={}\n", shell_quote(&value));
+        }
+        script += "exec";
+        for arg in self.argv() {
+            let arg = arg
+                .split(ACCESS_TOKEN_PLACEHOLDER)
+                .map(shell_quote)
+                .collect::<Vec<_>>()
+                .join(&format!("\"${ACCESS_TOKEN_VAR}\""));
+            script += &format!(" \\\n  {arg}");
+        }
+        script.push('\n');
+        script
+    }
+
+    fn batch_script(&self) -> String {
+        let mut script = String::from("@echo off\r\n");
+        script += &format!("rem Reads the access token from %{ACCESS_TOKEN_VAR}%\r\n");
+        script += "setlocal\r\n";
+        script += &format!(
+            "cd /d {} || exit /b 1\r\n",
+            batch_quote(self.working_directory.to_str().unwrap())
+        );
+        for key in &self.env_remove {
+            script += &format!("set \"{key}=\"\r\n");
+        }
+        for (key, value) in self.env_vars() {
+            script += &format!("set \"{key}={}\"\r\n", value.replace('%', "%%"));
+        }
+        let args: Vec<_> = self
+            .argv()
+            .iter()
+            .map(|arg| {
+                batch_quote(arg).replace(ACCESS_TOKEN_PLACEHOLDER, &format!("%{ACCESS_TOKEN_VAR}%"))
+            })
+            .collect();
+        script += &args.join(" ^\r\n  ");
+        script += "\r\nexit /b %ERRORLEVEL%\r\n";
+        script
+    }
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Quote for the Microsoft C runtime argument parser, which is what the Java launcher uses, and
+/// escape `%` for cmd.
+fn batch_quote(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // backslashes before a quote have to be escaped, as well as the quote itself
+                quoted += &"\\".repeat(backslashes * 2 + 1);
+                backslashes = 0;
+            }
+            _ => {
+                quoted += &"\\".repeat(backslashes);
+                backslashes = 0;
+            }
+        }
+        match c {
+            '\\' => {}
+            '%' => quoted += "%%",
+            c => quoted.push(c),
+        }
+    }
+    // the closing quote must not be escaped
+    quoted += &"\\".repeat(backslashes * 2);
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::launch::{
+        hooks::{LaunchHooks, DEFAULT_HOOK_TIMEOUT},
+        javalaunch::LaunchBackend,
+        prepared::{AccessToken, ArgumentPassing},
+    };
+
+    fn prepared() -> PreparedLaunch {
+        PreparedLaunch {
+            instance_directory: PathBuf::from("/instances/test"),
+            working_directory: PathBuf::from("/instances/test/.minecraft"),
+            java_path: String::from("java"),
+            jvm_args: vec![String::from("-Xmx2G")],
+            classpath: vec![String::from("a.jar")],
+            main_class: String::from("net.minecraft.client.main.Main"),
+            args: vec![
+                String::from("--username"),
+                String::from("O'Brien"),
+                String::from("--accessToken"),
+                String::from(ACCESS_TOKEN_PLACEHOLDER),
+            ],
+            access_token: AccessToken::new(String::from("secret")),
+            wrapper_commands: vec![],
+            env: vec![(String::from("MANGOHUD"), String::from("1"))],
+            env_remove: vec![String::from("_JAVA_OPTIONS")],
+            argument_passing: ArgumentPassing::CommandLine,
+            java_version_unknown: false,
+            backend: LaunchBackend::Java,
+            hooks: LaunchHooks {
+                prelaunch: None,
+                postlaunch: None,
+                timeout: DEFAULT_HOOK_TIMEOUT,
+                working_directory: PathBuf::from("/instances/test"),
+                env: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn shell_script() {
+        let script = prepared().launch_script(ScriptKind::Shell);
+        assert!(!script.contains("secret"));
+        assert_eq!(
+            script,
+            "#!/bin/sh\n\
+             # Reads the access token from $HELIX_ACCESS_TOKEN\n\
+             cd '/instances/test/.minecraft' || exit 1\n\
+             unset _JAVA_OPTIONS\n\
+             export MANGOHUD='1'\n\
+             exec \\\n  'java' \\\n  '-Xmx2G' \\\n  '-classpath' \\\n  'a.jar' \\\n  \
+             'net.minecraft.client.main.Main' \\\n  '--username' \\\n  'O'\\''Brien' \\\n  \
+             '--accessToken' \\\n  ''\"$HELIX_ACCESS_TOKEN\"''\n"
+        );
+    }
+
+    #[test]
+    fn batch_script() {
+        let script = prepared().launch_script(ScriptKind::Batch);
+        assert!(!script.contains("secret"));
+        assert!(script.contains("set \"_JAVA_OPTIONS=\"\r\n"));
+        assert!(script.contains("\"--accessToken\" ^\r\n  \"%HELIX_ACCESS_TOKEN%\"\r\n"));
+    }
+
+    #[test]
+    fn standalone_with_argfile() {
+        let prepared = PreparedLaunch {
+            argument_passing: ArgumentPassing::Argfile(String::from("/instances/test/launch.args")),
+            ..prepared()
+        };
+        for kind in [ScriptKind::Shell, ScriptKind::Batch] {
+            let script = prepared.launch_script(kind);
+            assert!(!script.contains("launch.args"));
+            assert!(script.contains("-Xmx2G"));
+            assert!(script.contains("a.jar"));
+        }
+    }
+
+    #[test]
+    fn batch_quoting() {
+        assert_eq!(batch_quote(r"C:\Games\"), r#""C:\Games\\""#);
+        assert_eq!(batch_quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(batch_quote(r#"a\"b"#), r#""a\\\"b""#);
+        assert_eq!(batch_quote("100%"), "\"100%%\"");
+    }
+
+    #[test]
+    fn serialize_without_token() -> Result<(), serde_json::Error> {
+        let json = serde_json::to_string(&prepared())?;
+        assert!(!json.contains("secret"));
+        let prepared: PreparedLaunch = serde_json::from_str(&json)?;
+        assert_eq!(prepared.access_token.secret(), "");
+        assert_eq!(prepared.args[3], ACCESS_TOKEN_PLACEHOLDER);
+        Ok(())
+    }
+}
@@ -3,11 +3,15 @@
 #![warn(clippy::uninlined_format_args)]
 
 pub mod addon;
+pub mod archive;
 pub mod auth;
 pub mod config;
 mod fsutil;
+pub mod import;
 pub mod launch;
 pub mod meta;
+#[cfg(test)]
+mod testutil;
 
 #[no_mangle]
 pub extern "C" fn test(i: std::ffi::c_int) -> std::ffi::c_int {
@@ -1,5 +1,6 @@
 use std::io;
 
+use serde::Deserialize;
 use thiserror::Error;
 
 use crate::config::{self, Config};
@@ -75,6 +76,13 @@ impl<'a> MetaClient<'a> {
         &self,
         component_id: &str,
     ) -> Result<helixlauncher_meta::index::Index, ComponentMetaRetrievalError> {
+        Ok(self.fetch_index(component_id).await?.json().await?)
+    }
+
+    async fn fetch_index(
+        &self,
+        component_id: &str,
+    ) -> Result<reqwest::Response, ComponentMetaRetrievalError> {
         let config_meta = self.config.get_meta_url();
         let meta_default = config::meta_url_default();
         let url = if let Some(configured_url) = config_meta {
@@ -100,7 +108,7 @@ impl<'a> MetaClient<'a> {
                 }
             }
         };
-        Ok(response.json().await?)
+        Ok(response)
     }
 
     pub async fn component_version_exists(
@@ -108,11 +116,14 @@ impl<'a> MetaClient<'a> {
         component_id: &str,
         component_version: &str,
     ) -> Result<bool, ComponentMetaRetrievalError> {
-        Ok(self
-            .get_component_index(component_id)
-            .await?
-            .iter()
-            .any(|item| item.version == component_version))
+        /// Only the version of the index entries is needed here
+        #[derive(Deserialize)]
+        struct IndexVersion {
+            version: String,
+        }
+
+        let index: Vec<IndexVersion> = self.fetch_index(component_id).await?.json().await?;
+        Ok(index.iter().any(|item| item.version == component_version))
     }
 }
 
@@ -268,6 +279,56 @@ mod tests {
         Ok(())
     }
 
+    /// The traits and argument features the launch options are checked against, as meta
+    /// publishes them
+    #[tokio::test]
+    async fn launch_traits() -> Result<(), Box<dyn std::error::Error>> {
+        use std::mem::discriminant;
+
+        use helixlauncher_meta::component::{ConditionFeature, MinecraftArgument, Trait};
+
+        let dir = tempfile::tempdir()?;
+        let config = Config::new_with_data_dir(
+            "dev.helixlauncher.HelixLauncher",
+            "HelixLauncher",
+            dir.path().join("abc"),
+        )?;
+        let client = MetaClient::new(&config);
+
+        let quick_play = client.get_component_meta("net.minecraft", "1.20.4").await?;
+        for required in [
+            Trait::SupportsCustomResolution,
+            Trait::SupportsQuickPlayWorld,
+            Trait::SupportsQuickPlayServer,
+            Trait::SupportsQuickPlayRealms,
+        ] {
+            assert!(quick_play.traits.contains(&required), "{required:?}");
+        }
+        let features: Vec<_> = quick_play
+            .game_arguments
+            .iter()
+            .filter_map(|argument| match argument {
+                MinecraftArgument::Conditional { feature, .. } => Some(discriminant(feature)),
+                MinecraftArgument::Always(_) => None,
+            })
+            .collect();
+        for feature in [
+            ConditionFeature::Demo,
+            ConditionFeature::CustomResolution,
+            ConditionFeature::QuickPlayWorld,
+            ConditionFeature::QuickPlayServer,
+            ConditionFeature::QuickPlayRealms,
+        ] {
+            assert!(features.contains(&discriminant(&feature)), "{feature:?}");
+        }
+
+        let old = client.get_component_meta("net.minecraft", "1.16.5").await?;
+        assert!(old.traits.contains(&Trait::SupportsCustomResolution));
+        assert!(!old.traits.contains(&Trait::SupportsQuickPlayWorld));
+
+        Ok(())
+    }
+
     const INVALID_TEST_SET: [(&str, [&str; 3]); 2] = [
         // first should have invalid index, rest only invalid versions
         ("com.example", ["1.0.0", "1.0.1", "1.0.2"]),
@@ -0,0 +1,85 @@
+//! Helpers shared by the tests.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::config::Config;
+
+pub(crate) type Files = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+/// Serve `files` by path over HTTP, ignoring the query.
+pub(crate) async fn serve(files: Files) -> io::Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/", listener.local_addr()?);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let files = files.clone();
+            tokio::spawn(async move {
+                let mut request = vec![];
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await?;
+                    if n == 0 {
+                        return Ok(());
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request);
+                let target = request.split(' ').nth(1).unwrap_or_default();
+                let path = target.split('?').next().unwrap();
+                let response = match files.lock().unwrap().get(path) {
+                    Some(body) => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            body.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(body);
+                        response
+                    }
+                    None => {
+                        b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_vec()
+                    }
+                };
+                stream.write_all(&response).await?;
+                stream.shutdown().await
+            });
+        }
+    });
+    Ok(url)
+}
+
+/// A config using a meta server that offers `versions` of the components.
+pub(crate) async fn meta_config(
+    dir: &Path,
+    versions: &[(&str, &[&str])],
+) -> Result<Config, Box<dyn std::error::Error>> {
+    let files = Files::default();
+    for (id, versions) in versions {
+        let index: Vec<_> = versions
+            .iter()
+            .map(|version| serde_json::json!({ "version": version }))
+            .collect();
+        files
+            .lock()
+            .unwrap()
+            .insert(format!("/{id}/index.json"), serde_json::to_vec(&index)?);
+    }
+    let mut config = Config::new_with_data_dir(
+        "dev.helixlauncher.HelixLauncher",
+        "HelixLauncher",
+        dir.join("data"),
+    )?;
+    config.set_meta_url(Some(serve(files).await?));
+    Ok(config)
+}
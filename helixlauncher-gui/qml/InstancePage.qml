@@ -5,20 +5,144 @@ import org.kde.kirigami 2.13 as Kirigami
 import dev.helixlauncher.qml 1.0
 
 Kirigami.Page {
+    id: root
+
     property string name
-    property int index
+    property string instanceId
+    property string notes
 
     title: name
 
-    Kirigami.Heading {
-        level: 1
-        text: name
+    ColumnLayout {
+        anchors.fill: parent
+
+        Kirigami.Heading {
+            level: 1
+            text: name
+        }
+
+        Label {
+            Layout.fillWidth: true
+            visible: notes !== ""
+            text: notes
+            wrapMode: Text.Wrap
+        }
     }
 
     actions.main: Kirigami.Action {
         text: "Launch"
         icon.name: "media-playback-start"
-        onTriggered: InstancesModel.launch(index)
+        onTriggered: InstancesModel.launch(instanceId)
+    }
+
+    actions.contextualActions: [
+        Kirigami.Action {
+            text: "Rename"
+            icon.name: "edit-rename"
+            onTriggered: {
+                renameField.text = name
+                renameSheet.open()
+            }
+        },
+        Kirigami.Action {
+            text: "Duplicate"
+            icon.name: "edit-copy"
+            onTriggered: {
+                duplicateField.text = name + " (copy)"
+                duplicateSheet.open()
+            }
+        },
+        Kirigami.Action {
+            text: "Delete"
+            icon.name: "edit-delete"
+            onTriggered: {
+                const id = instanceId
+                const error = InstancesModel.delete_instance(id)
+                if (error) {
+                    applicationWindow().showPassiveNotification(error)
+                    return
+                }
+                applicationWindow().showPassiveNotification(name + " deleted", "long", "Undo", function() {
+                    const error = InstancesModel.restore_instance(id)
+                    if (error) {
+                        applicationWindow().showPassiveNotification(error)
+                    }
+                })
+                applicationWindow().pageStack.pop()
+            }
+        }
+    ]
+
+    Kirigami.OverlaySheet {
+        id: renameSheet
+
+        header: Kirigami.Heading {
+            text: "Rename " + name
+        }
+
+        Kirigami.FormLayout {
+            TextField {
+                id: renameField
+                Kirigami.FormData.label: "Name:"
+            }
+
+            CheckBox {
+                id: renameMoveDir
+                text: "Also rename the instance folder"
+            }
+        }
+
+        footer: DialogButtonBox {
+            standardButtons: DialogButtonBox.Ok | DialogButtonBox.Cancel
+
+            onAccepted: {
+                const result = InstancesModel.rename_instance(instanceId, renameField.text, renameMoveDir.checked)
+                if (result.error) {
+                    applicationWindow().showPassiveNotification(result.error)
+                    return
+                }
+                instanceId = result.id
+                name = renameField.text
+                renameSheet.close()
+            }
+
+            onRejected: renameSheet.close()
+        }
+    }
+
+    Kirigami.OverlaySheet {
+        id: duplicateSheet
+
+        header: Kirigami.Heading {
+            text: "Duplicate " + name
+        }
+
+        Kirigami.FormLayout {
+            TextField {
+                id: duplicateField
+                Kirigami.FormData.label: "Name:"
+            }
+
+            CheckBox {
+                id: duplicateShareWorlds
+                text: "Share worlds with " + name
+            }
+        }
+
+        footer: DialogButtonBox {
+            standardButtons: DialogButtonBox.Ok | DialogButtonBox.Cancel
+
+            onAccepted: {
+                const error = InstancesModel.duplicate_instance(instanceId, duplicateField.text, duplicateShareWorlds.checked)
+                if (error) {
+                    applicationWindow().showPassiveNotification(error)
+                    return
+                }
+                duplicateSheet.close()
+            }
+
+            onRejected: duplicateSheet.close()
+        }
     }
 
     Shortcut {
@@ -26,14 +26,56 @@ Kirigami.ScrollablePage {
         })
     }
 
+    header: RowLayout {
+        Kirigami.SearchField {
+            Layout.fillWidth: true
+            placeholderText: "Search, e.g. tag:modded"
+            onTextChanged: InstancesModel.set_filter(text)
+        }
+
+        ComboBox {
+            textRole: "text"
+            valueRole: "value"
+            model: [
+                { text: "Name", value: "name" },
+                { text: "Last played", value: "lastPlayed" },
+                { text: "Playtime", value: "playtime" },
+                { text: "Created", value: "created" }
+            ]
+            onActivated: InstancesModel.set_sort(currentValue)
+        }
+    }
+
     Kirigami.CardsListView {
         model: InstancesModel
 
+        section.property: "groupId"
+        section.delegate: Kirigami.ListSectionHeader {
+            // instances outside of any group come first, without a header
+            visible: section !== ""
+            height: visible ? implicitHeight : 0
+            label: InstancesModel.group_label(section)
+
+            ToolButton {
+                readonly property bool collapsed: InstancesModel.group_collapsed(section)
+
+                icon.name: collapsed ? "go-next" : "go-down"
+                onClicked: {
+                    const error = InstancesModel.set_group_collapsed(section, !collapsed)
+                    if (error) {
+                        applicationWindow().showPassiveNotification(error)
+                    }
+                }
+            }
+        }
+
         delegate: Kirigami.AbstractCard {
+            visible: !collapsed
+            height: collapsed ? 0 : implicitHeight
             showClickFeedback: true
 
             onClicked: {
-                applicationWindow().pageStack.push('qrc:/qml/InstancePage.qml', { name, index })
+                applicationWindow().pageStack.push('qrc:/qml/InstancePage.qml', { name, instanceId, notes })
             }
 
             contentItem: Item {
@@ -48,6 +90,13 @@ Kirigami.ScrollablePage {
                     spacing: 0
 
                     RowLayout {
+                        Kirigami.Icon {
+                            visible: icon !== undefined
+                            source: icon !== undefined ? icon : ""
+                            implicitWidth: Kirigami.Units.iconSizes.medium
+                            implicitHeight: Kirigami.Units.iconSizes.medium
+                        }
+
                         Kirigami.Heading {
                             Layout.fillWidth: true
 
@@ -80,7 +129,7 @@ Kirigami.ScrollablePage {
                         Label {
                             Layout.fillWidth: true
                             Layout.alignment: Qt.AlignTop
-                            text: "Playtime unknown"
+                            text: tags ? playtime + " · " + tags : playtime
                             opacity: 0.6
                             font: Kirigami.Theme.smallFont
                             elide: Text.ElideRight
@@ -88,8 +137,17 @@ Kirigami.ScrollablePage {
 
                         Button {
                             Layout.alignment: Qt.AlignVCenter | Qt.AlignRight
-                            text: "Launch"
-                            onClicked: InstancesModel.launch(index)
+                            text: running ? "Stop" : "Launch"
+                            onClicked: {
+                                if (!running) {
+                                    InstancesModel.launch(instanceId)
+                                    return
+                                }
+                                const error = InstancesModel.stop(instanceId)
+                                if (error) {
+                                    applicationWindow().showPassiveNotification(error)
+                                }
+                            }
                         }
                     }
                 }
@@ -43,13 +43,17 @@ Kirigami.Page {
         position: DialogButtonBox.Footer
 
         onAccepted: {
-            InstancesModel.create_instance(
+            const error = InstancesModel.create_instance(
                 instanceName.text,
                 instanceVersion.text,
                 instanceLoader.Kirigami.FormData.checked ? instanceLoader.currentText : "",
                 instanceLoaderVersion.text
             )
-            root.closeDialog()
+            if (error) {
+                applicationWindow().showPassiveNotification(error)
+            } else {
+                root.closeDialog()
+            }
         }
 
         onRejected: root.closeDialog()
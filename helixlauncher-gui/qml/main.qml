@@ -2,6 +2,7 @@ import QtQuick 2.15
 import QtQuick.Controls 2.15
 import QtQuick.Layouts 1.15
 import org.kde.kirigami 2.13 as Kirigami
+import dev.helixlauncher.qml 1.0
 
 Kirigami.ApplicationWindow {
     id: root
@@ -9,4 +10,19 @@ Kirigami.ApplicationWindow {
     title: "Helix Launcher"
 
     pageStack.initialPage: "qrc:/qml/InstancesPage.qml"
+
+    Connections {
+        target: InstancesModel
+
+        function onGame_exited(name, status, crash_report, diagnosis) {
+            let message = name + " " + status
+            if (diagnosis !== "") {
+                message += ": " + diagnosis
+            }
+            if (crash_report !== "") {
+                message += ", crash report: " + crash_report
+            }
+            root.showPassiveNotification(message)
+        }
+    }
 }
@@ -3,19 +3,73 @@ use helixlauncher_core::auth::DEFAULT_ACCOUNT_JSON;
 use helixlauncher_core::config::Config;
 use helixlauncher_core::launch::{
     asset::merge_components,
-    instance::{Instance, InstanceLaunchConfig, Modloader},
+    crash::{diagnose, RuleDatabase},
+    instance::{
+        Instance, InstanceFilter, InstanceGroup, InstanceIcon, InstanceLaunchConfig, InstanceSort,
+        Modloader, TrashedInstance,
+    },
     prepared::{prepare_launch, LaunchOptions},
+    process::{GameProcess, LifecycleEvent},
 };
-use qmetaobject::USER_ROLE;
-use qmetaobject::{prelude::*, QSingletonInit};
+use qmetaobject::{prelude::*, queued_callback, QPointer, QSingletonInit};
+use qmetaobject::{QVariantMap, USER_ROLE};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
+/// Instance name, event and crash diagnosis summary
+type GameEvent = (String, LifecycleEvent, Option<String>);
+
+/// How long the game gets to shut down when stopped from the UI before it is killed
+const GAME_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The instances in display order, with the groups they are in
+fn list_instances(
+    config: &Config,
+    sort: InstanceSort,
+    filter: &InstanceFilter,
+) -> Vec<(Vec<InstanceGroup>, Instance)> {
+    Instance::list_instances(config.get_instances_path())
+        .map(|mut listing| {
+            listing.filter(filter);
+            listing.sort(sort);
+            listing.flatten()
+        })
+        .unwrap_or_default()
+}
+
 #[derive(Default, QObject)]
 pub struct InstancesModel {
     base: qt_base_class!(trait QAbstractListModel),
 
-    launch: qt_method!(fn(&self, item: usize)),
+    /// Running games, by instance path
+    processes: Arc<Mutex<HashMap<PathBuf, GameProcess>>>,
+    sort: InstanceSort,
+    filter: InstanceFilter,
+
+    game_started: qt_signal!(name: QString, pid: QString),
+    game_exited: qt_signal!(
+        name: QString,
+        status: QString,
+        crash_report: QString,
+        diagnosis: QString
+    ),
+
+    launch: qt_method!(fn(&self, id: String)),
+    stop: qt_method!(fn(&self, id: String) -> QString),
+    rename_instance:
+        qt_method!(fn(&mut self, id: String, name: String, move_dir: bool) -> QVariantMap),
+    duplicate_instance:
+        qt_method!(fn(&mut self, id: String, name: String, share_worlds: bool) -> QString),
+    delete_instance: qt_method!(fn(&mut self, id: String) -> QString),
+    restore_instance: qt_method!(fn(&mut self, id: String) -> QString),
+    group_label: qt_method!(fn(&self, id: String) -> QString),
+    group_collapsed: qt_method!(fn(&self, id: String) -> bool),
+    set_group_collapsed: qt_method!(fn(&mut self, id: String, collapsed: bool) -> QString),
+    set_sort: qt_method!(fn(&mut self, sort: String)),
+    set_filter: qt_method!(fn(&mut self, query: String)),
     create_instance: qt_method!(
         fn(
             &mut self,
@@ -23,12 +77,41 @@ pub struct InstancesModel {
             version: String,
             modloader_string: String,
             modloader_version: String,
-        )
+        ) -> QString
     ),
 }
 
 impl InstancesModel {
-    fn launch(&self, item: usize) {
+    fn launch(&self, id: String) {
+        let processes = self.processes.clone();
+        let qptr = QPointer::from(&*self);
+        let on_event = queued_callback(move |(name, event, diagnosis): GameEvent| {
+            if let Some(this) = qptr.as_pinned() {
+                let this = this.borrow();
+                match event {
+                    LifecycleEvent::Started { pid, .. } => this.game_started(
+                        name.into(),
+                        pid.map(|pid| pid.to_string()).unwrap_or_default().into(),
+                    ),
+                    LifecycleEvent::Stopping { .. } => {}
+                    LifecycleEvent::Exited(exit) => {
+                        let crash_report = exit
+                            .crash_report
+                            .map(|path| path.display().to_string())
+                            .unwrap_or_default();
+                        this.game_exited(
+                            name.into(),
+                            exit.kind.to_string().into(),
+                            crash_report.into(),
+                            diagnosis.unwrap_or_default().into(),
+                        );
+                    }
+                }
+                // the running role changed
+                this.begin_reset_model();
+                this.end_reset_model();
+            }
+        });
         std::thread::spawn(move || {
             let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
             let base_path = config.get_base_path();
@@ -40,10 +123,7 @@ impl InstancesModel {
                 default = default_s
             }
             let account: Option<&Account> = accounts.iter().find(|x| x.uuid == default);
-            let mut instances = Instance::list_instances(config.get_instances_path()).unwrap();
-            instances.sort_by(|x, y| x.path.cmp(&y.path));
-
-            let instance = &instances[item];
+            let instance = &Instance::from_id(config.get_instances_path(), &id).unwrap();
 
             let rt = Runtime::new().unwrap();
             rt.block_on(async move {
@@ -60,18 +140,200 @@ impl InstancesModel {
                 .await
                 .unwrap();
 
-                prepared.launch(true).await.unwrap();
+                let (process, mut events) =
+                    prepared.supervise(prepared.launch(true).await.unwrap());
+                processes
+                    .lock()
+                    .unwrap()
+                    .insert(instance.path.clone(), process);
+
+                while let Some(event) = events.recv().await {
+                    let mut diagnosis = None;
+                    if let LifecycleEvent::Exited(exit) = &event {
+                        processes.lock().unwrap().remove(&instance.path);
+                        diagnosis = diagnose(
+                            &instance.get_game_dir(),
+                            exit,
+                            None,
+                            &RuleDatabase::builtin(),
+                        )
+                        .unwrap_or_default()
+                        .and_then(|diagnosis| diagnosis.summary());
+                    }
+                    on_event((instance.config.name.clone(), event, diagnosis));
+                }
             });
         });
     }
 
+    /// Returns the error, empty if the game is being stopped
+    fn stop(&self, id: String) -> QString {
+        // the instance directory may be gone by now, the ID is the name it had
+        let process = self
+            .processes
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(path, _)| path.file_name().is_some_and(|name| *name == *id))
+            .map(|(_, process)| process.clone());
+        match process {
+            Some(process) => {
+                process.stop(GAME_STOP_TIMEOUT);
+                QString::default()
+            }
+            None => QString::from("The game is not running"),
+        }
+    }
+
+    /// Returns the `id`, which changes if `move_dir` is set, and the `error`, which is empty if
+    /// the instance was renamed
+    fn rename_instance(&mut self, id: String, name: String, move_dir: bool) -> QVariantMap {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        let renamed =
+            Instance::from_id(config.get_instances_path(), &id).and_then(|mut instance| {
+                // the running game would lose its directory
+                let move_dir =
+                    move_dir && !self.processes.lock().unwrap().contains_key(&instance.path);
+                instance.rename(name, move_dir)?;
+                Ok(instance.id().to_string())
+            });
+
+        self.begin_reset_model();
+        self.end_reset_model();
+        let mut result = QVariantMap::default();
+        let (id, error) = match renamed {
+            Ok(id) => (id, String::new()),
+            Err(e) => (id, format!("Could not rename the instance: {e}")),
+        };
+        result.insert("id".into(), QString::from(id).into());
+        result.insert("error".into(), QString::from(error).into());
+        result
+    }
+
+    /// Returns the error, empty if the instance was duplicated
+    fn duplicate_instance(&mut self, id: String, name: String, share_worlds: bool) -> QString {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        let duplicated = Instance::from_id(config.get_instances_path(), &id)
+            .and_then(|instance| instance.duplicate(name, share_worlds));
+
+        self.begin_reset_model();
+        self.end_reset_model();
+        match duplicated {
+            Ok(_) => QString::default(),
+            Err(e) => format!("Could not duplicate the instance: {e}").into(),
+        }
+    }
+
+    /// Returns the error, empty if the instance was deleted. Running instances are kept.
+    fn delete_instance(&mut self, id: String) -> QString {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        let instance = match Instance::from_id(config.get_instances_path(), &id) {
+            Ok(instance) => instance,
+            Err(e) => return format!("Could not delete the instance: {e}").into(),
+        };
+        if self.processes.lock().unwrap().contains_key(&instance.path) {
+            return format!("Stop the game before deleting {}", instance.config.name).into();
+        }
+        let deleted = instance.delete(&config.get_trash_path());
+
+        self.begin_reset_model();
+        self.end_reset_model();
+        match deleted {
+            Ok(_) => QString::default(),
+            Err(e) => format!("Could not delete the instance: {e}").into(),
+        }
+    }
+
+    /// Undo the latest deletion of the instance with the ID `id`. Returns the error, empty if the
+    /// instance was restored.
+    fn restore_instance(&mut self, id: String) -> QString {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        let restored = TrashedInstance::list(&config.get_trash_path()).and_then(|trashed| {
+            match trashed.into_iter().find(|trashed| trashed.id() == id) {
+                Some(trashed) => trashed.restore().map(Some),
+                None => Ok(None),
+            }
+        });
+
+        self.begin_reset_model();
+        self.end_reset_model();
+        match restored {
+            Ok(Some(_)) => QString::default(),
+            Ok(None) => QString::from("The instance is no longer in the trash"),
+            Err(e) => format!("Could not restore the instance: {e}").into(),
+        }
+    }
+
+    /// The names of the group with the ID `id` and the groups it is in, for section headers
+    fn group_label(&self, id: String) -> QString {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        list_instances(&config, self.sort, &self.filter)
+            .into_iter()
+            .map(|(groups, _)| groups)
+            .find(|groups| groups.last().is_some_and(|group| group.id() == id))
+            .map(|groups| {
+                groups
+                    .iter()
+                    .map(|group| &*group.config.name)
+                    .collect::<Vec<_>>()
+                    .join(" / ")
+            })
+            .unwrap_or_default()
+            .into()
+    }
+
+    fn group_collapsed(&self, id: String) -> bool {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        InstanceGroup::from_id(config.get_instances_path(), &id)
+            .map(|group| group.config.collapsed)
+            .unwrap_or_default()
+    }
+
+    /// Returns the error, empty if the group was collapsed or expanded
+    fn set_group_collapsed(&mut self, id: String, collapsed: bool) -> QString {
+        let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
+        let saved =
+            InstanceGroup::from_id(config.get_instances_path(), &id).and_then(|mut group| {
+                group.config.collapsed = collapsed;
+                group.save_config()
+            });
+
+        self.begin_reset_model();
+        self.end_reset_model();
+        match saved {
+            Ok(()) => QString::default(),
+            Err(e) => format!("Could not change the group: {e}").into(),
+        }
+    }
+
+    /// One of `name`, `lastPlayed`, `playtime` and `created`
+    fn set_sort(&mut self, sort: String) {
+        self.sort = match &*sort {
+            "lastPlayed" => InstanceSort::LastPlayed,
+            "playtime" => InstanceSort::Playtime,
+            "created" => InstanceSort::Created,
+            _ => InstanceSort::Name,
+        };
+
+        self.begin_reset_model();
+        self.end_reset_model();
+    }
+
+    /// Show only the instances matching `query`, in which words like `tag:modded` are tags
+    fn set_filter(&mut self, query: String) {
+        self.filter = InstanceFilter::parse(&query);
+
+        self.begin_reset_model();
+        self.end_reset_model();
+    }
+
     fn create_instance(
         &mut self,
         name: String,
         version: String,
         modloader_string: String,
         modloader_version: String,
-    ) {
+    ) -> QString {
         let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
 
         let modloader = match &*modloader_string {
@@ -82,34 +344,36 @@ impl InstancesModel {
             _ => unreachable!(),
         };
 
-        Instance::new(
+        let created = Instance::new(
             name,
             version,
             InstanceLaunchConfig::default(),
             &config.get_instances_path(),
             modloader,
-            Some(modloader_version),
-        )
-        .unwrap();
+            // so that a missing version is reported
+            (!modloader_version.is_empty()).then_some(modloader_version),
+        );
 
         self.begin_reset_model();
         self.end_reset_model();
+        match created {
+            Ok(_) => QString::default(),
+            Err(e) => format!("Could not create the instance: {e}").into(),
+        }
     }
 }
 
 impl QAbstractListModel for InstancesModel {
     fn row_count(&self) -> i32 {
         let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
-        let instances = Instance::list_instances(config.get_instances_path()).unwrap();
-        instances.len() as _
+        list_instances(&config, self.sort, &self.filter).len() as _
     }
 
     fn data(&self, index: QModelIndex, role: i32) -> QVariant {
         let config = Config::new("dev.helixlauncher.HelixLauncher", "HelixLauncher").unwrap();
-        let mut instances = Instance::list_instances(config.get_instances_path()).unwrap();
-        instances.sort_by(|x, y| x.path.cmp(&y.path));
+        let instances = list_instances(&config, self.sort, &self.filter);
 
-        if let Some(elem) = instances.get(index.row() as usize) {
+        if let Some((groups, elem)) = instances.get(index.row() as usize) {
             if role == USER_ROLE {
                 QString::from(&elem.config.name[..]).into()
             } else if role == USER_ROLE + 1 {
@@ -135,6 +399,32 @@ impl QAbstractListModel for InstancesModel {
                 } else {
                     QVariant::default()
                 }
+            } else if role == USER_ROLE + 3 {
+                self.processes
+                    .lock()
+                    .unwrap()
+                    .contains_key(&elem.path)
+                    .into()
+            } else if role == USER_ROLE + 4 {
+                QString::from(elem.id()).into()
+            } else if role == USER_ROLE + 5 {
+                QString::from(groups.last().map(|group| group.id()).unwrap_or_default()).into()
+            } else if role == USER_ROLE + 6 {
+                groups.iter().any(|group| group.config.collapsed).into()
+            } else if role == USER_ROLE + 7 {
+                match elem.icon() {
+                    Some(InstanceIcon::BuiltIn(name)) => QString::from(name).into(),
+                    Some(InstanceIcon::File(path)) => {
+                        QString::from(format!("file://{}", path.display())).into()
+                    }
+                    None => QVariant::default(),
+                }
+            } else if role == USER_ROLE + 8 {
+                QString::from(elem.config.metadata.tags.join(", ")).into()
+            } else if role == USER_ROLE + 9 {
+                QString::from(elem.config.metadata.describe_playtime()).into()
+            } else if role == USER_ROLE + 10 {
+                QString::from(elem.config.metadata.notes.as_deref().unwrap_or_default()).into()
             } else {
                 QVariant::default()
             }
@@ -148,6 +438,14 @@ impl QAbstractListModel for InstancesModel {
         map.insert(USER_ROLE, "name".into());
         map.insert(USER_ROLE + 1, "loader".into());
         map.insert(USER_ROLE + 2, "version".into());
+        map.insert(USER_ROLE + 3, "running".into());
+        map.insert(USER_ROLE + 4, "instanceId".into());
+        map.insert(USER_ROLE + 5, "groupId".into());
+        map.insert(USER_ROLE + 6, "collapsed".into());
+        map.insert(USER_ROLE + 7, "icon".into());
+        map.insert(USER_ROLE + 8, "tags".into());
+        map.insert(USER_ROLE + 9, "playtime".into());
+        map.insert(USER_ROLE + 10, "notes".into());
         map
     }
 }
@@ -1,5 +1,21 @@
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let result = helixlauncher_java::search::search_java()?;
-    println!("{result:?}");
+use helixlauncher_java::search::{java_info, search_java};
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    for path in search_java()? {
+        match java_info(&path).await {
+            Ok(info) => println!(
+                "{}\n  version: {} (major {})\n  vendor: {}\n  architecture: {}\n  kind: {:?}\n  home: {}",
+                info.path.display(),
+                info.version,
+                info.major_version,
+                info.vendor.as_deref().unwrap_or("unknown"),
+                info.architecture,
+                info.kind,
+                info.java_home.display(),
+            ),
+            Err(e) => println!("{}\n  error: {e}", path.display()),
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,297 @@
+//! Probing of Java installs.
+//!
+//! [`java_info`] reads the `release` file in the Java home, which every modern JDK and JRE ships.
+//! If it is missing or incomplete, the `java` executable is run with
+//! `-XshowSettings:properties -version` instead. Results are cached per executable until it is
+//! modified.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{Error as IOError, ErrorKind as IOErrorKind},
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+use thiserror::Error;
+use tokio::{fs, process::Command};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Architecture {
+    X86,
+    X86_64,
+    Arm,
+    Aarch64,
+    Other(String),
+}
+
+impl Architecture {
+    /// The architecture this program was built for.
+    pub fn current() -> Self {
+        Self::parse(std::env::consts::ARCH)
+    }
+
+    /// Parse an architecture name as used by Java's `os.arch` or Rust.
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "x86" | "i386" | "i486" | "i586" | "i686" => Self::X86,
+            "x86_64" | "amd64" | "x64" => Self::X86_64,
+            "arm" | "aarch32" | "armv7l" => Self::Arm,
+            "aarch64" | "arm64" => Self::Aarch64,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl Display for Architecture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::X86 => "x86",
+            Self::X86_64 => "x86_64",
+            Self::Arm => "arm",
+            Self::Aarch64 => "aarch64",
+            Self::Other(name) => name,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum JavaKind {
+    Jre,
+    Jdk,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct JavaInstallInfo {
+    /// The `java` executable
+    pub path: PathBuf,
+    pub java_home: PathBuf,
+    pub version: String,
+    pub major_version: u32,
+    pub vendor: Option<String>,
+    pub architecture: Architecture,
+    pub kind: JavaKind,
+}
+
+#[derive(Error, Debug)]
+pub enum InfoError {
+    #[error("{0}")]
+    IO(#[from] IOError),
+    #[error("{0} is not in a Java home")]
+    NotInJavaHome(PathBuf),
+    #[error("Could not determine the Java version of {0}")]
+    UnknownVersion(PathBuf),
+}
+
+struct CacheEntry {
+    modified: SystemTime,
+    info: JavaInstallInfo,
+}
+
+fn cache() -> &'static Mutex<HashMap<PathBuf, CacheEntry>> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, CacheEntry>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Get information about the Java install the `java` executable at `path` belongs to.
+pub async fn java_info(path: &Path) -> Result<JavaInstallInfo, InfoError> {
+    let path = fs::canonicalize(path).await?;
+    let modified = fs::metadata(&path).await?.modified()?;
+    if let Some(entry) = cache().lock().unwrap().get(&path) {
+        if entry.modified == modified {
+            return Ok(entry.info.clone());
+        }
+    }
+
+    let info = probe(&path).await?;
+    cache().lock().unwrap().insert(
+        path,
+        CacheEntry {
+            modified,
+            info: info.clone(),
+        },
+    );
+    Ok(info)
+}
+
+async fn probe(path: &Path) -> Result<JavaInstallInfo, InfoError> {
+    // <java home>/bin/java
+    let java_home = path
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| InfoError::NotInJavaHome(path.to_path_buf()))?
+        .to_path_buf();
+
+    let release = match fs::read_to_string(java_home.join("release")).await {
+        Err(err) if err.kind() == IOErrorKind::NotFound => None,
+        r => Some(r?),
+    };
+    let properties = match release.as_deref().map(parse_release) {
+        Some(release) if release.version.is_some() && release.arch.is_some() => release,
+        _ => run_java(path).await?,
+    };
+
+    let version = properties
+        .version
+        .ok_or_else(|| InfoError::UnknownVersion(path.to_path_buf()))?;
+    let major_version = parse_major_version(&version)
+        .ok_or_else(|| InfoError::UnknownVersion(path.to_path_buf()))?;
+    let kind = match properties.image_type.as_deref() {
+        Some("JDK") => JavaKind::Jdk,
+        Some("JRE") => JavaKind::Jre,
+        _ if has_javac(&java_home).await => JavaKind::Jdk,
+        _ => JavaKind::Jre,
+    };
+
+    Ok(JavaInstallInfo {
+        path: path.to_path_buf(),
+        java_home,
+        version,
+        major_version,
+        vendor: properties.vendor,
+        architecture: Architecture::parse(properties.arch.as_deref().unwrap_or_default()),
+        kind,
+    })
+}
+
+#[derive(Default, Debug)]
+struct Properties {
+    version: Option<String>,
+    vendor: Option<String>,
+    arch: Option<String>,
+    image_type: Option<String>,
+}
+
+/// Parse a `release` file, which consists of `KEY="value"` lines.
+fn parse_release(release: &str) -> Properties {
+    let mut properties = Properties::default();
+    for line in release.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = Some(value.trim().trim_matches('"').to_string());
+        match key.trim() {
+            "JAVA_VERSION" => properties.version = value,
+            "IMPLEMENTOR" => properties.vendor = value,
+            "OS_ARCH" => properties.arch = value,
+            "IMAGE_TYPE" => properties.image_type = value,
+            _ => {}
+        }
+    }
+    properties
+}
+
+async fn run_java(path: &Path) -> Result<Properties, InfoError> {
+    let output = Command::new(path)
+        .arg("-XshowSettings:properties")
+        .arg("-version")
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    Ok(parse_properties(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Parse the output of `-XshowSettings:properties`, which consists of indented `key = value`
+/// lines. Multi-valued properties continue on further lines without a key.
+fn parse_properties(output: &str) -> Properties {
+    let mut properties = Properties::default();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        let value = Some(value.trim().to_string());
+        match key.trim() {
+            "java.version" => properties.version = value,
+            "java.vendor" => properties.vendor = value,
+            "os.arch" => properties.arch = value,
+            _ => {}
+        }
+    }
+    properties
+}
+
+/// Get the major version from a Java version string like `1.8.0_402`, `17.0.10` or `22-ea`.
+pub fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()? {
+        "1" => parts.next()?.parse().ok(),
+        major => major.parse().ok(),
+    }
+}
+
+async fn has_javac(java_home: &Path) -> bool {
+    let javac = if cfg!(windows) { "javac.exe" } else { "javac" };
+    // the java home of a Java 8 JDK is its jre directory
+    for bin in [java_home.join("bin"), java_home.join("../bin")] {
+        if fs::try_exists(bin.join(javac)).await.unwrap_or(false) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn major_versions() {
+        assert_eq!(parse_major_version("1.8.0_402"), Some(8));
+        assert_eq!(parse_major_version("17.0.10"), Some(17));
+        assert_eq!(parse_major_version("21"), Some(21));
+        assert_eq!(parse_major_version("22-ea"), Some(22));
+        assert_eq!(parse_major_version(""), None);
+    }
+
+    #[test]
+    fn parse_show_settings() {
+        let properties = parse_properties(
+            "Property settings:\n    file.encoding = UTF-8\n    java.class.path = \n    \
+             java.vendor = Eclipse Adoptium\n    java.version = 17.0.10\n    \
+             java.library.path = /usr/java/packages/lib\n        /usr/lib64\n    \
+             os.arch = amd64\n\nopenjdk version \"17.0.10\" 2024-01-16\n",
+        );
+        assert_eq!(properties.version.as_deref(), Some("17.0.10"));
+        assert_eq!(properties.vendor.as_deref(), Some("Eclipse Adoptium"));
+        assert_eq!(properties.arch.as_deref(), Some("amd64"));
+    }
+
+    #[tokio::test]
+    async fn probe_release_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let java_home = dir.path().canonicalize()?;
+        std::fs::create_dir(java_home.join("bin"))?;
+        let java = java_home.join("bin/java");
+        std::fs::write(&java, "")?;
+        std::fs::write(
+            java_home.join("release"),
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"21.0.2\"\nOS_ARCH=\"aarch64\"\n",
+        )?;
+
+        let info = java_info(&java).await?;
+        assert_eq!(
+            info,
+            JavaInstallInfo {
+                path: java.clone(),
+                java_home: java_home.clone(),
+                version: String::from("21.0.2"),
+                major_version: 21,
+                vendor: Some(String::from("Eclipse Adoptium")),
+                architecture: Architecture::Aarch64,
+                kind: JavaKind::Jre,
+            }
+        );
+
+        // cached until the executable changes
+        std::fs::write(java_home.join("bin/javac"), "")?;
+        assert_eq!(java_info(&java).await?.kind, JavaKind::Jre);
+        let later = SystemTime::now() + std::time::Duration::from_secs(10);
+        std::fs::File::options()
+            .write(true)
+            .open(&java)?
+            .set_modified(later)?;
+        assert_eq!(java_info(&java).await?.kind, JavaKind::Jdk);
+        Ok(())
+    }
+}
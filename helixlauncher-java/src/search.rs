@@ -1,18 +1,13 @@
 use cfg_if::cfg_if;
 use std::{
     collections::HashSet,
-    fs,
-    io::{Error as IOError, ErrorKind as IOErrorKind},
+    env, fs,
+    io::Error as IOError,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
-pub struct JavaInstallInfo {
-    path: String,
-    major_version: u32,
-    version: String,
-    architecture: helixlauncher_meta::component::Arch,
-}
+pub use crate::info::{java_info, JavaInstallInfo};
 
 #[derive(Error, Debug)]
 pub enum SearchError {
@@ -26,39 +21,143 @@ const JAVA_EXECUTABLE: &str = "bin\\javaw.exe";
 const JAVA_EXECUTABLE: &str = "bin/java";
 
 #[cfg(windows)]
-fn search_registry(result: &mut HashSet<PathBuf>) {}
+const JAVA_BINARY: &str = "javaw.exe";
+#[cfg(unix)]
+const JAVA_BINARY: &str = "java";
+
+/// Where macOS bundles keep the Java home
+const BUNDLE_HOME: &str = "Contents/Home";
+
+/// A location that can't be read just has nothing to find, it doesn't fail the search.
+fn skip(path: &Path, err: &IOError) {
+    log::debug!("Skipping {} in the Java search: {err}", path.display());
+}
+
+/// Add a Java executable, deduplicated by its canonical path.
+fn add_java(result: &mut HashSet<PathBuf>, path: &Path) -> Result<(), SearchError> {
+    match path.canonicalize() {
+        Ok(path) if path.is_file() => {
+            result.insert(path);
+        }
+        Ok(_) => {}
+        Err(err) => skip(path, &err),
+    }
+    Ok(())
+}
+
+/// Add the Java executable in `home`, which may also be a macOS bundle.
+fn search_java_home(result: &mut HashSet<PathBuf>, home: &Path) -> Result<(), SearchError> {
+    add_java(result, &home.join(JAVA_EXECUTABLE))?;
+    add_java(result, &home.join(BUNDLE_HOME).join(JAVA_EXECUTABLE))
+}
 
+/// Add the Java homes inside `dir`.
 fn search_java_dir<P: AsRef<Path>>(
     result: &mut HashSet<PathBuf>,
     dir: P,
 ) -> Result<(), SearchError> {
-    for dir in match fs::read_dir(dir) {
-        Err(err) if err.kind() == IOErrorKind::NotFound => return Ok(()),
-        r => r,
-    }? {
-        let dir = match dir {
-            Err(err) if err.kind() == IOErrorKind::NotFound => continue,
-            r => r,
-        }?;
-        let mut path = dir.path();
+    let dir = dir.as_ref();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            skip(dir, &err);
+            return Ok(());
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                skip(dir, &err);
+                continue;
+            }
+        };
+        let path = entry.path();
         if !path.is_dir() {
             continue;
         }
-        path.push(JAVA_EXECUTABLE);
-        if path.exists() {
-            result.insert(path);
+        search_java_home(result, &path)?;
+    }
+    Ok(())
+}
+
+/// `JAVA_HOME` and `java` on `PATH`
+fn search_env(result: &mut HashSet<PathBuf>) -> Result<(), SearchError> {
+    if let Some(java_home) = env::var_os("JAVA_HOME") {
+        search_java_home(result, Path::new(&java_home))?;
+    }
+    if let Some(path) = env::var_os("PATH") {
+        for dir in env::split_paths(&path) {
+            // symlinks like /usr/bin/java are resolved to the actual install
+            add_java(result, &dir.join(JAVA_BINARY))?;
+        }
+    }
+    Ok(())
+}
+
+fn home_dir() -> Option<PathBuf> {
+    cfg_if! {
+        if #[cfg(windows)] {
+            env::var_os("USERPROFILE").map(PathBuf::from)
+        } else {
+            env::var_os("HOME").map(PathBuf::from)
         }
     }
+}
+
+/// Installs made by version managers and development tools in the home directory
+fn search_home_dirs(result: &mut HashSet<PathBuf>) -> Result<(), SearchError> {
+    let Some(home) = home_dir() else {
+        return Ok(());
+    };
+    // SDKMAN!, asdf, IntelliJ IDEA and Gradle toolchains
+    for dir in [
+        ".sdkman/candidates/java",
+        ".asdf/installs/java",
+        ".jdks",
+        ".gradle/jdks",
+    ] {
+        search_java_dir(result, home.join(dir))?;
+    }
+    #[cfg(target_os = "macos")]
+    search_java_dir(result, home.join("Library/Java/JavaVirtualMachines"))?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn search_registry(_result: &mut HashSet<PathBuf>) -> Result<(), SearchError> {
+    // TODO: read the JavaSoft, Eclipse Adoptium and Microsoft keys
     Ok(())
 }
 
 fn search_java_dirs(result: &mut HashSet<PathBuf>) -> Result<(), SearchError> {
     cfg_if! {
         if #[cfg(windows)] {
+            search_registry(result)?;
+            if let Some(program_files) = env::var_os("ProgramFiles") {
+                let program_files = PathBuf::from(program_files);
+                for vendor in ["Java", "Eclipse Adoptium", "Microsoft", "Zulu"] {
+                    search_java_dir(result, program_files.join(vendor))?;
+                }
+            }
+        } else if #[cfg(target_os = "macos")] {
+            search_java_dir(result, "/Library/Java/JavaVirtualMachines")?;
+            // Homebrew
+            search_java_dir(result, "/opt/homebrew/opt")?;
+            search_java_dir(result, "/usr/local/opt")?;
         } else if #[cfg(unix)] {
-            #[cfg(target_os = "osx")] {
+            // Debian, Arch, Fedora and openSUSE, plus Oracle's RPMs
+            for dir in ["/usr/lib/jvm", "/usr/lib64/jvm", "/usr/java"] {
+                search_java_dir(result, dir)?;
+            }
+            search_java_dir(result, "/opt")?;
+            // Flatpak SDK extensions, e.g. /usr/lib/sdk/openjdk17/jvm/openjdk-17 inside the sandbox
+            search_java_home(result, Path::new("/app/jre"))?;
+            if let Ok(extensions) = fs::read_dir("/usr/lib/sdk") {
+                for extension in extensions.flatten() {
+                    search_java_dir(result, extension.path().join("jvm"))?;
+                }
             }
-            search_java_dir(result, "/usr/lib/jvm")?;
         } else {
             compile_error!("Unknown platform");
         }
@@ -67,9 +166,66 @@ fn search_java_dirs(result: &mut HashSet<PathBuf>) -> Result<(), SearchError> {
 }
 
 pub fn search_java() -> Result<HashSet<PathBuf>, SearchError> {
+    search_java_in(&[])
+}
+
+/// Search the default locations and `extra_roots`, such as the Java installs managed by the
+/// launcher. Each root may either be a Java home, or a directory containing Java homes.
+///
+/// The result contains the canonical paths of the Java executables.
+pub fn search_java_in(extra_roots: &[PathBuf]) -> Result<HashSet<PathBuf>, SearchError> {
     let mut result = HashSet::new();
+    for root in extra_roots {
+        search_java_home(&mut result, root)?;
+        search_java_dir(&mut result, root)?;
+    }
+    search_env(&mut result)?;
+    search_home_dirs(&mut result)?;
     search_java_dirs(&mut result)?;
     Ok(result)
 }
 
-pub async fn java_info(path: &Path) {}
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn fake_java_home(home: &Path) -> Result<PathBuf, IOError> {
+        fs::create_dir_all(home.join("bin"))?;
+        fs::write(home.join(JAVA_EXECUTABLE), "")?;
+        home.join(JAVA_EXECUTABLE).canonicalize()
+    }
+
+    #[test]
+    fn search_extra_roots() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let managed = dir.path().join("java");
+        let java_17 = fake_java_home(&managed.join("temurin-17"))?;
+        let java_21 = fake_java_home(&managed.join("zulu-21.jdk").join(BUNDLE_HOME))?;
+        // the same install through a symlink
+        std::os::unix::fs::symlink(managed.join("temurin-17"), managed.join("current"))?;
+        let single = fake_java_home(&dir.path().join("single"))?;
+
+        let result = search_java_in(&[managed, dir.path().join("single")])?;
+        assert!(result.contains(&java_17));
+        assert!(result.contains(&java_21));
+        assert!(result.contains(&single));
+        assert!(!result
+            .iter()
+            .any(|path| path.starts_with(dir.path().join("java/current"))));
+        Ok(())
+    }
+
+    #[test]
+    fn skip_unreadable_roots() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = tempfile::tempdir()?;
+        let java = fake_java_home(&dir.path().join("java/temurin-17"))?;
+        let file = dir.path().join("file");
+        fs::write(&file, "")?;
+        // a symlink loop inside a searched directory
+        std::os::unix::fs::symlink("loop", dir.path().join("java/loop"))?;
+
+        let result = search_java_in(&[file, dir.path().join("java")])?;
+        assert!(result.contains(&java));
+        Ok(())
+    }
+}
@@ -0,0 +1,417 @@
+//! The protocol spoken between the launcher and `helixlauncher-javalaunch` over the wrapper's
+//! stdin.
+//!
+//! The stream is a sequence of frames. Every frame starts with a one-byte tag, followed by the
+//! payload length as a little-endian `u32` and the payload itself:
+//!
+//! | Tag | Payload                                   | Meaning                                    |
+//! |-----|-------------------------------------------|--------------------------------------------|
+//! | `H` | `HXJL`, version as little-endian `u32`    | Handshake, always the first frame          |
+//! | `l` | path                                      | JLI library, or the JVM library for JNI    |
+//! | `m` | `jli` or `jni`                            | Launch mode, JLI if absent                 |
+//! | `d` | path                                      | Working directory                          |
+//! | `e` | name, NUL, value                          | Set an environment variable                |
+//! | `u` | name                                      | Remove an environment variable             |
+//! | `o` | option                                    | JVM option                                 |
+//! | `c` | path                                      | Classpath entry                            |
+//! | `C` | class name                                | Main class                                 |
+//! | `a` | argument                                  | Game argument                              |
+//! | `E` | empty                                     | End of the request                         |
+//!
+//! All strings are UTF-8. Frames with a tag of `0x80` or above are optional: readers skip the ones
+//! they don't know, so they can be added without bumping [`VERSION`]. Unknown tags below `0x80`
+//! are an error.
+//!
+//! The wrapper answers through the status file whose path is its first argument: once it has
+//! read the request, it writes [`Status::Accepted`], and when it fails before the main class is
+//! run, [`Status::Failed`] with the [`ErrorCode`] of the failure. The wrapper also exits with that
+//! code, but as the game may exit with any code, only the status file tells a failure of the
+//! wrapper apart from the game's own exit.
+
+use std::io::{self, Read, Write};
+
+use thiserror::Error;
+
+pub const MAGIC: &[u8; 4] = b"HXJL";
+pub const VERSION: u32 = 1;
+
+const TAG_HELLO: u8 = b'H';
+const TAG_LIBRARY: u8 = b'l';
+const TAG_MODE: u8 = b'm';
+const TAG_WORKING_DIRECTORY: u8 = b'd';
+const TAG_ENV: u8 = b'e';
+const TAG_ENV_REMOVE: u8 = b'u';
+const TAG_JVM_OPTION: u8 = b'o';
+const TAG_CLASSPATH: u8 = b'c';
+const TAG_MAIN_CLASS: u8 = b'C';
+const TAG_ARG: u8 = b'a';
+const TAG_END: u8 = b'E';
+const FIRST_OPTIONAL_TAG: u8 = 0x80;
+
+/// Frames larger than this are rejected, to avoid allocating huge buffers for a corrupt stream.
+const MAX_FRAME_LENGTH: u32 = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum LaunchMode {
+    /// Call `JLI_Launch` from the JLI library, like the `java` executable does
+    #[default]
+    Jli,
+    /// Create the VM through the JNI invocation API of the JVM library
+    Jni,
+}
+
+impl LaunchMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Jli => "jli",
+            Self::Jni => "jni",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LaunchRequest {
+    pub library: String,
+    pub mode: LaunchMode,
+    pub working_directory: Option<String>,
+    pub env: Vec<(String, String)>,
+    pub env_remove: Vec<String>,
+    pub jvm_options: Vec<String>,
+    pub classpath: Vec<String>,
+    pub main_class: String,
+    pub args: Vec<String>,
+}
+
+/// Reasons for the wrapper to fail before the game is started, reported through the status file.
+/// The wrapper exits with the same codes, which the game can use as well.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorCode {
+    Io = 200,
+    NotHelix = 201,
+    UnsupportedVersion = 202,
+    UnknownTag = 203,
+    InvalidUtf8 = 204,
+    MalformedFrame = 205,
+    Truncated = 206,
+    MissingField = 207,
+    LibraryLoad = 208,
+    Launch = 209,
+}
+
+impl ErrorCode {
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        Some(match code {
+            200 => Self::Io,
+            201 => Self::NotHelix,
+            202 => Self::UnsupportedVersion,
+            203 => Self::UnknownTag,
+            204 => Self::InvalidUtf8,
+            205 => Self::MalformedFrame,
+            206 => Self::Truncated,
+            207 => Self::MissingField,
+            208 => Self::LibraryLoad,
+            209 => Self::Launch,
+            _ => return None,
+        })
+    }
+
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Io => "javalaunch could not read its input",
+            Self::NotHelix => "javalaunch was not started by Helix Launcher",
+            Self::UnsupportedVersion => "javalaunch does not support this protocol version",
+            Self::UnknownTag => "javalaunch received an unknown message",
+            Self::InvalidUtf8 => "javalaunch received a string that is not valid UTF-8",
+            Self::MalformedFrame => "javalaunch received a malformed message",
+            Self::Truncated => "javalaunch input ended early",
+            Self::MissingField => "javalaunch did not receive all required fields",
+            Self::LibraryLoad => "javalaunch could not load the Java library",
+            Self::Launch => "javalaunch could not start Java",
+        }
+    }
+}
+
+/// The contents of the status file.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Status {
+    /// The request was read and the main class is about to be run
+    Accepted,
+    /// The wrapper failed before the main class was run
+    Failed(ErrorCode),
+}
+
+impl Status {
+    pub fn encode(self) -> String {
+        match self {
+            Self::Accepted => String::from("accepted\n"),
+            Self::Failed(code) => format!("failed {}\n", code.exit_code()),
+        }
+    }
+
+    pub fn decode(status: &str) -> Option<Self> {
+        match status.trim_end().split_once(' ') {
+            None if status.trim_end() == "accepted" => Some(Self::Accepted),
+            Some(("failed", code)) => {
+                ErrorCode::from_exit_code(code.parse().ok()?).map(Self::Failed)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Not a javalaunch request")]
+    NotHelix,
+    #[error("Unsupported protocol version {0}, expected {VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("Unknown tag {0:#04x}")]
+    UnknownTag(u8),
+    #[error("Frame {0:#04x} is not valid UTF-8")]
+    InvalidUtf8(u8),
+    #[error("Malformed frame {0:#04x}")]
+    MalformedFrame(u8),
+    #[error("Input ended before the end of the request")]
+    Truncated,
+    #[error("Missing {0}")]
+    MissingField(&'static str),
+}
+
+impl ProtocolError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Io(_) => ErrorCode::Io,
+            Self::NotHelix => ErrorCode::NotHelix,
+            Self::UnsupportedVersion(_) => ErrorCode::UnsupportedVersion,
+            Self::UnknownTag(_) => ErrorCode::UnknownTag,
+            Self::InvalidUtf8(_) => ErrorCode::InvalidUtf8,
+            Self::MalformedFrame(_) => ErrorCode::MalformedFrame,
+            Self::Truncated => ErrorCode::Truncated,
+            Self::MissingField(_) => ErrorCode::MissingField,
+        }
+    }
+}
+
+impl LaunchRequest {
+    /// Fails only if a string is too large for a frame.
+    pub fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut hello = MAGIC.to_vec();
+        hello.extend_from_slice(&VERSION.to_le_bytes());
+        write_frame(writer, TAG_HELLO, &hello)?;
+        write_frame(writer, TAG_LIBRARY, self.library.as_bytes())?;
+        write_frame(writer, TAG_MODE, self.mode.as_str().as_bytes())?;
+        if let Some(working_directory) = &self.working_directory {
+            write_frame(writer, TAG_WORKING_DIRECTORY, working_directory.as_bytes())?;
+        }
+        for (key, value) in &self.env {
+            write_frame(writer, TAG_ENV, format!("{key}\0{value}").as_bytes())?;
+        }
+        for key in &self.env_remove {
+            write_frame(writer, TAG_ENV_REMOVE, key.as_bytes())?;
+        }
+        for option in &self.jvm_options {
+            write_frame(writer, TAG_JVM_OPTION, option.as_bytes())?;
+        }
+        for entry in &self.classpath {
+            write_frame(writer, TAG_CLASSPATH, entry.as_bytes())?;
+        }
+        write_frame(writer, TAG_MAIN_CLASS, self.main_class.as_bytes())?;
+        for arg in &self.args {
+            write_frame(writer, TAG_ARG, arg.as_bytes())?;
+        }
+        write_frame(writer, TAG_END, &[])
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self, ProtocolError> {
+        // check the header separately, an old or foreign request may claim a huge frame length
+        let mut header = [0; 5];
+        read_exact(reader, &mut header)?;
+        if header != [TAG_HELLO, 8, 0, 0, 0] {
+            return Err(ProtocolError::NotHelix);
+        }
+        let mut hello = [0; 8];
+        read_exact(reader, &mut hello)?;
+        if hello[..4] != MAGIC[..] {
+            return Err(ProtocolError::NotHelix);
+        }
+        let version = u32::from_le_bytes(hello[4..].try_into().unwrap());
+        if version != VERSION {
+            return Err(ProtocolError::UnsupportedVersion(version));
+        }
+
+        let mut request = Self::default();
+        let mut library = None;
+        let mut main_class = None;
+        loop {
+            let (tag, payload) = read_frame(reader)?;
+            let string = || String::from_utf8(payload).map_err(|_| ProtocolError::InvalidUtf8(tag));
+            match tag {
+                TAG_END => break,
+                TAG_LIBRARY => library = Some(string()?),
+                TAG_MODE => {
+                    request.mode = match &*string()? {
+                        "jli" => LaunchMode::Jli,
+                        "jni" => LaunchMode::Jni,
+                        _ => return Err(ProtocolError::MalformedFrame(tag)),
+                    }
+                }
+                TAG_WORKING_DIRECTORY => request.working_directory = Some(string()?),
+                TAG_ENV => {
+                    let env = string()?;
+                    let (key, value) = env
+                        .split_once('\0')
+                        .ok_or(ProtocolError::MalformedFrame(tag))?;
+                    request.env.push((key.to_string(), value.to_string()));
+                }
+                TAG_ENV_REMOVE => request.env_remove.push(string()?),
+                TAG_JVM_OPTION => request.jvm_options.push(string()?),
+                TAG_CLASSPATH => request.classpath.push(string()?),
+                TAG_MAIN_CLASS => main_class = Some(string()?),
+                TAG_ARG => request.args.push(string()?),
+                TAG_HELLO => return Err(ProtocolError::MalformedFrame(tag)),
+                tag if tag >= FIRST_OPTIONAL_TAG => {}
+                tag => return Err(ProtocolError::UnknownTag(tag)),
+            }
+        }
+
+        request.library = library.ok_or(ProtocolError::MissingField("library path"))?;
+        request.main_class = main_class.ok_or(ProtocolError::MissingField("main class"))?;
+        Ok(request)
+    }
+}
+
+fn write_frame(writer: &mut impl Write, tag: u8, payload: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(payload.len())
+        .ok()
+        .filter(|length| *length <= MAX_FRAME_LENGTH)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&[tag])?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(payload)
+}
+
+fn read_frame(reader: &mut impl Read) -> Result<(u8, Vec<u8>), ProtocolError> {
+    let mut header = [0; 5];
+    read_exact(reader, &mut header)?;
+    let tag = header[0];
+    let length = u32::from_le_bytes(header[1..].try_into().unwrap());
+    if length > MAX_FRAME_LENGTH {
+        return Err(ProtocolError::MalformedFrame(tag));
+    }
+    let mut payload = vec![0; length as usize];
+    read_exact(reader, &mut payload)?;
+    Ok((tag, payload))
+}
+
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<(), ProtocolError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => ProtocolError::Truncated,
+        _ => e.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> LaunchRequest {
+        LaunchRequest {
+            library: String::from("/usr/lib/jvm/java-21/lib/libjli.so"),
+            mode: LaunchMode::Jli,
+            working_directory: Some(String::from("/instances/test/.minecraft")),
+            env: vec![(String::from("MANGOHUD"), String::from("1"))],
+            env_remove: vec![String::from("_JAVA_OPTIONS")],
+            jvm_options: vec![String::from("-Xmx2G")],
+            classpath: vec![String::from("a.jar"), String::from("b.jar")],
+            main_class: String::from("net.minecraft.client.main.Main"),
+            args: vec![String::from("--username"), String::from("Jürgen")],
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), ProtocolError> {
+        let request = request();
+        assert_eq!(LaunchRequest::read(&mut &request.encode()?[..])?, request);
+
+        let request = LaunchRequest {
+            mode: LaunchMode::Jni,
+            working_directory: None,
+            ..LaunchRequest::default()
+        };
+        assert_eq!(LaunchRequest::read(&mut &request.encode()?[..])?, request);
+        Ok(())
+    }
+
+    #[test]
+    fn skip_optional_frames() -> Result<(), ProtocolError> {
+        let request = request();
+        let mut encoded = request.encode()?;
+        let end = encoded.len() - 5;
+        let mut optional = Vec::new();
+        write_frame(&mut optional, 0x80, b"from the future")?;
+        encoded.splice(end..end, optional);
+        assert_eq!(LaunchRequest::read(&mut &encoded[..])?, request);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let read = |bytes: &[u8]| LaunchRequest::read(&mut &bytes[..]).unwrap_err().code();
+
+        assert_eq!(read(b"japath\0aarg\0"), ErrorCode::NotHelix);
+        assert_eq!(read(b""), ErrorCode::Truncated);
+
+        let mut future = Vec::new();
+        let mut hello = MAGIC.to_vec();
+        hello.extend_from_slice(&(VERSION + 1).to_le_bytes());
+        write_frame(&mut future, TAG_HELLO, &hello).unwrap();
+        assert_eq!(read(&future), ErrorCode::UnsupportedVersion);
+
+        let encoded = request().encode().unwrap();
+        assert_eq!(read(&encoded[..encoded.len() - 5]), ErrorCode::Truncated);
+
+        let mut unknown = encoded.clone();
+        let end = unknown.len() - 5;
+        unknown.splice(end..end, [b'z', 0, 0, 0, 0]);
+        assert_eq!(read(&unknown), ErrorCode::UnknownTag);
+
+        let mut invalid = encoded[..encoded.len() - 5].to_vec();
+        write_frame(&mut invalid, TAG_ARG, &[0xff, 0xfe]).unwrap();
+        assert_eq!(read(&invalid), ErrorCode::InvalidUtf8);
+
+        let mut missing = Vec::new();
+        LaunchRequest::default().write(&mut missing).unwrap();
+        let main_class = missing.len() - 10;
+        missing.drain(main_class..main_class + 5); // the empty main class frame
+        assert_eq!(read(&missing), ErrorCode::MissingField);
+    }
+
+    #[test]
+    fn exit_codes() {
+        for code in 200..=209 {
+            assert_eq!(ErrorCode::from_exit_code(code).unwrap().exit_code(), code);
+        }
+        assert_eq!(ErrorCode::from_exit_code(1), None);
+    }
+
+    #[test]
+    fn status() {
+        for status in [Status::Accepted, Status::Failed(ErrorCode::LibraryLoad)] {
+            assert_eq!(Status::decode(&status.encode()), Some(status));
+        }
+        assert_eq!(Status::decode(""), None);
+        assert_eq!(Status::decode("failed 1"), None);
+    }
+}
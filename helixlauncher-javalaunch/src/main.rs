@@ -1,170 +1,201 @@
 use std::{
+    env,
     ffi::{c_int, c_uchar},
-    io::{stdin, BufRead},
+    fmt::Display,
+    fs,
+    io::stdin,
+    path::PathBuf,
+    process,
     ptr::{null, null_mut},
+    sync::OnceLock,
 };
 
+use helixlauncher_javalaunch_protocol::{ErrorCode, LaunchMode, LaunchRequest, Status};
+use jni::{
+    objects::{JObject, JValue},
+    InitArgsBuilder, JNIEnv, JNIVersion, JavaVM,
+};
 use local_encoding_ng::Encoder;
 
-/*#[repr(C)]
-struct JavaVMOption {
-    optionString: *const c_char,
-    extraInfo: *const c_void,
-}
+#[cfg(windows)]
+const CLASSPATH_SEPARATOR: &str = ";";
+#[cfg(not(windows))]
+const CLASSPATH_SEPARATOR: &str = ":";
 
-const JNI_VERSION_1_6: i32 = 0x00010006;
+/// Exit code when the main method throws, same as the java launcher
+const UNCAUGHT_EXCEPTION_EXIT_CODE: i32 = 1;
 
-#[repr(C)]
-struct JavaVMInitArgs {
-    version: i32,
+/// The status file passed by the launcher as the first argument
+static STATUS_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
 
-    nOptions: i32,
-    options: *const JavaVMOption,
-    ignoreUnrecognized: bool,
+fn write_status(status: Status) {
+    if let Some(path) = STATUS_PATH.get().and_then(Option::as_ref) {
+        if let Err(e) = fs::write(path, status.encode()) {
+            eprintln!("Could not write the javalaunch status: {e}");
+        }
+    }
 }
 
-#[repr(C)]
-struct JNIInvokeInterface_ {
-    reserved0: *const c_void,
-    reserved1: *const c_void,
-    reserved2: *const c_void,
-
-    destroy_java_vm: unsafe extern "system" fn(vm: *const JavaVM) -> i32,
-    attach_current_thread: unsafe extern "system" fn(
-        vm: *const JavaVM,
-        penv: *mut *const JNIEnv,
-        args: *const c_void,
-    ) -> i32,
-    detach_current_thread: unsafe extern "system" fn(vm: *const JavaVM),
-    get_env: unsafe extern "system" fn(vm: *const JavaVM, *mut *const JNIEnv, version: i32) -> i32,
-    attach_current_thread_as_daemon: unsafe extern "system" fn(
-        vm: *const JavaVM,
-        *mut *const JNIEnv,
-        args: *const c_void,
-    ) -> i32,
+fn fail(code: ErrorCode, message: impl Display) -> ! {
+    eprintln!("{}: {message}", code.description());
+    write_status(Status::Failed(code));
+    process::exit(code.exit_code());
 }
 
-#[repr(C)]
-struct JNINativeInterface_ {
-    reserved0: *const c_void,
-    reserved1: *const c_void,
-    reserved2: *const c_void,
+fn main() {
+    STATUS_PATH.get_or_init(|| env::args_os().nth(1).map(PathBuf::from));
+    let request = LaunchRequest::read(&mut stdin().lock()).unwrap_or_else(|e| fail(e.code(), e));
+    write_status(Status::Accepted);
+
+    if let Some(working_directory) = &request.working_directory {
+        if let Err(e) = env::set_current_dir(working_directory) {
+            fail(ErrorCode::Launch, e);
+        }
+    }
+    for key in &request.env_remove {
+        env::remove_var(key);
+    }
+    for (key, value) in &request.env {
+        env::set_var(key, value);
+    }
 
-    reserved3: *const c_void,
-    GetVersion: unsafe extern "system" fn(*const JNIEnv) -> i32,
+    let code = match request.mode {
+        LaunchMode::Jli => launch_jli(request),
+        LaunchMode::Jni => launch_jni(request),
+    };
+    process::exit(code);
 }
 
-type JavaVM = *const JNIInvokeInterface_;
-type JNIEnv = *const JNINativeInterface_;*/
+fn launch_jli(request: LaunchRequest) -> i32 {
+    let mut argv = vec![String::from("java")];
+    argv.extend(request.jvm_options);
+    if !request.classpath.is_empty() {
+        argv.push(String::from("-classpath"));
+        argv.push(request.classpath.join(CLASSPATH_SEPARATOR));
+    }
+    argv.push(request.main_class);
+    argv.extend(request.args);
 
-fn main() {
-    let (jli_path, argv, is_jli) = {
-        let mut buf = Vec::new();
-        let mut jli_path = None;
-        let mut argv = vec![String::from("java")];
-        let mut stdin = stdin().lock();
-        let mut is_jli = true;
-        loop {
-            stdin.read_until(0, &mut buf).unwrap();
-            if buf.is_empty() {
-                break;
-            }
-            match buf[0] {
-                // TODO: should we pass the full jli library file path, the library directory path,
-                //       or the java home?
-                b'j' => jli_path = Some(String::from_utf8(buf[1..].to_vec()).unwrap()),
-                b'a' => argv.push(String::from_utf8(buf[1..].to_vec()).unwrap()),
-                b'o' => is_jli = false,
-                _ => panic!("Incompatible Java wrapper"),
+    let mut argv: Vec<_> = argv
+        .into_iter()
+        .map(|s| {
+            let mut bytes = local_encoding_ng::Encoding::ANSI
+                .to_bytes(&s)
+                .unwrap_or_else(|e| fail(ErrorCode::Launch, e));
+            bytes.reserve_exact(1);
+            bytes.push(0);
+            bytes.shrink_to_fit();
+            bytes
+        })
+        .collect();
+    let jli = unsafe { libloading::Library::new(request.library) }
+        .unwrap_or_else(|e| fail(ErrorCode::LibraryLoad, e));
+    let mut cargv: Vec<_> = argv.iter_mut().map(|v| v.as_mut_ptr()).collect();
+    #[allow(clippy::type_complexity)]
+    let jli_launch: libloading::Symbol<
+        unsafe extern "system" fn(
+            argc: c_int,
+            argv: *mut *mut c_uchar,
+            jargc: c_int,
+            jargv: *mut *const c_uchar,
+            appclassc: c_int,
+            appclassv: *mut *const c_uchar,
+            fullversion: *const c_uchar,
+            dotversion: *const c_uchar,
+            pname: *const c_uchar,
+            lname: *const c_uchar,
+            javaargs: bool,
+            cpwildcard: bool,
+            javaw: bool,
+            ergo: i32,
+        ) -> c_int,
+    > = unsafe { jli.get(b"JLI_Launch") }.unwrap_or_else(|e| fail(ErrorCode::LibraryLoad, e));
+
+    unsafe {
+        jli_launch(
+            cargv.len() as c_int,
+            cargv.as_mut_ptr(),
+            0,
+            null_mut(),
+            0,
+            null_mut(),
+            null(),
+            null(),
+            b"java\0".as_ptr(),
+            b"java\0".as_ptr(),
+            false,
+            false,
+            false,
+            0,
+        )
+    }
+}
+
+/// Create the VM from the JVM library through the invocation API and run the main class on this
+/// thread, like the java launcher does.
+fn launch_jni(request: LaunchRequest) -> i32 {
+    let mut args = InitArgsBuilder::new()
+        .version(JNIVersion::V8)
+        .ignore_unrecognized(false);
+    for option in &request.jvm_options {
+        args = args.option(option);
+    }
+    if !request.classpath.is_empty() {
+        args = args.option(format!(
+            "-Djava.class.path={}",
+            request.classpath.join(CLASSPATH_SEPARATOR)
+        ));
+    }
+    let args = args.build().unwrap_or_else(|e| fail(ErrorCode::Launch, e));
+    let library = request.library;
+    let vm = JavaVM::with_libjvm(args, || Ok(&library)).unwrap_or_else(|e| match e {
+        jni::errors::StartJvmError::LoadError(..) => fail(ErrorCode::LibraryLoad, e),
+        _ => fail(ErrorCode::Launch, e),
+    });
+
+    let code = {
+        let mut env = vm
+            .attach_current_thread()
+            .unwrap_or_else(|e| fail(ErrorCode::Launch, e));
+        match run_main(&mut env, &request.main_class, &request.args) {
+            Ok(()) => 0,
+            Err(jni::errors::Error::JavaException) => {
+                // prints the stack trace to stderr
+                let _ = env.exception_describe();
+                let _ = env.exception_clear();
+                UNCAUGHT_EXCEPTION_EXIT_CODE
             }
-            buf.clear();
+            Err(e) => fail(ErrorCode::Launch, e),
         }
-        (jli_path.expect("JLI path not passed"), argv, is_jli)
+        // the attach guard detaches this thread here
     };
-    println!("{:?}", argv);
-    if is_jli {
-        let mut argv: Vec<_> = argv
-            .into_iter()
-            .map(|s| {
-                let mut bytes = local_encoding_ng::Encoding::ANSI.to_bytes(&s).unwrap();
-                bytes.reserve_exact(1);
-                bytes.push(0);
-                bytes.shrink_to_fit();
-                bytes
-            })
-            .collect();
-        let jli = unsafe { libloading::Library::new(jli_path) }.unwrap();
-        let mut cargv: Vec<_> = argv.iter_mut().map(|v| v.as_mut_ptr()).collect();
-        #[allow(clippy::type_complexity)]
-        let jli_launch: libloading::Symbol<
-            unsafe extern "system" fn(
-                argc: c_int,
-                argv: *mut *mut c_uchar,
-                jargc: c_int,
-                jargv: *mut *const c_uchar,
-                appclassc: c_int,
-                appclassv: *mut *const c_uchar,
-                fullversion: *const c_uchar,
-                dotversion: *const c_uchar,
-                pname: *const c_uchar,
-                lname: *const c_uchar,
-                javaargs: bool,
-                cpwildcard: bool,
-                javaw: bool,
-                ergo: i32,
-            ) -> c_int,
-        > = unsafe { jli.get(b"JLI_Launch") }.unwrap();
-
-        unsafe {
-            jli_launch(
-                cargv.len() as c_int,
-                cargv.as_mut_ptr(),
-                0,
-                null_mut(),
-                0,
-                null_mut(),
-                null(),
-                null(),
-                b"java\0".as_ptr(),
-                b"java\0".as_ptr(),
-                false,
-                false,
-                false,
-                0,
-            );
-        }
-    } else {
-        let mut args = jni::InitArgsBuilder::new();
-        for arg in argv {
-            args = args.option(arg);
-        }
-        let vm = jni::JavaVM::with_libjvm(args.build().unwrap(), || Ok(jli_path)).unwrap();
-        let env = vm.attach_current_thread().unwrap();
-        todo!();
-        /*let jni_create_java_vm: libloading::Symbol<
-            unsafe extern "system" fn(
-                vm: *mut *const JavaVM,
-                penv: *mut *const JNIEnv,
-                args: *const JavaVMInitArgs,
-            ),
-        > = unsafe { jli.get(b"JNI_CreateJavaVM") }.unwrap();
-        let mut vm: *const JavaVM = null();
-        let mut env: *const JNIEnv = null();
-        let options: Vec<_> = argv
-            .iter()
-            .skip(1)
-            .map(|s| JavaVMOption {
-                optionString: s.as_ptr() as _,
-                extraInfo: null(),
-            })
-            .collect();
-        let vm_args = JavaVMInitArgs {
-            version: JNI_VERSION_1_6,
-            nOptions: options.len() as i32,
-            options: options.as_ptr(),
-            ignoreUnrecognized: false,
-        };
-        unsafe { jni_create_java_vm(&mut vm, &mut env, &vm_args) };
-        unsafe { ((**vm).destroy_java_vm)(vm)};*/
+
+    // SAFETY: all JNI references were created in the attach guard's scope and are gone by now.
+    // Waits until all non-daemon threads have exited, so the game keeps running after main
+    // returned.
+    if let Err(e) = unsafe { vm.destroy() } {
+        eprintln!("Could not destroy the Java VM: {e}");
+    }
+    code
+}
+
+fn run_main(env: &mut JNIEnv<'_>, main_class: &str, args: &[String]) -> jni::errors::Result<()> {
+    let class = env.find_class(main_class.replace('.', "/"))?;
+    let java_args = env.new_object_array(
+        args.len().try_into().unwrap(),
+        "java/lang/String",
+        JObject::null(),
+    )?;
+    for (i, arg) in args.iter().enumerate() {
+        let arg = env.new_string(arg)?;
+        env.set_object_array_element(&java_args, i.try_into().unwrap(), &arg)?;
+        env.delete_local_ref(arg)?;
     }
+    env.call_static_method(
+        &class,
+        "main",
+        "([Ljava/lang/String;)V",
+        &[JValue::Object(&java_args)],
+    )?;
+    Ok(())
 }